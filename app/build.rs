@@ -0,0 +1,135 @@
+//! Compiles the EV charging simulator's vehicle catalog into the crate, failing the build if any
+//! entry is incomplete or its charge curve can't be simulated.
+
+use std::{collections::HashSet, env, fmt::Write, fs, path::Path};
+
+use serde::Deserialize;
+
+const CATALOG: &str = "src/toys/ev_charge_sim/vehicles.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Catalog {
+    version: u32,
+    vehicle: Vec<VehicleEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VehicleEntry {
+    name: String,
+    model_year: u16,
+    trim: String,
+    battery_kwh: f64,
+    epa_miles: f64,
+    pack_voltage: u16,
+    onboard_charger_kw: f64,
+    source: String,
+    #[serde(default)]
+    notes: String,
+    /// state of charge in percent and power in kW
+    curve: Vec<[f64; 2]>,
+}
+
+impl VehicleEntry {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is empty".to_string());
+        }
+        if !(2010..=2100).contains(&self.model_year) {
+            return Err(format!("model year {} is out of range", self.model_year));
+        }
+        for (field, value) in [
+            ("battery_kwh", self.battery_kwh),
+            ("epa_miles", self.epa_miles),
+            ("onboard_charger_kw", self.onboard_charger_kw),
+        ] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(format!("{field} must be positive, got {value}"));
+            }
+        }
+        if self.pack_voltage == 0 {
+            return Err("pack_voltage must be positive".to_string());
+        }
+        if !self.source.starts_with("https://") {
+            return Err(format!("source {:?} isn't an https url", self.source));
+        }
+        let (Some(first), Some(last)) = (self.curve.first(), self.curve.last()) else {
+            return Err("curve has no points".to_string());
+        };
+        // the simulator interpolates between points and looks up every SOC a session passes
+        if first[0] != 0.0 || last[0] != 100.0 {
+            return Err(format!(
+                "curve must cover 0% to 100%, it covers {}% to {}%",
+                first[0], last[0]
+            ));
+        }
+        if let Some(pair) = self.curve.windows(2).find(|pair| pair[1][0] <= pair[0][0]) {
+            return Err(format!(
+                "curve SOC must increase, {}% is followed by {}%",
+                pair[0][0], pair[1][0]
+            ));
+        }
+        if let Some([soc, kw]) = self
+            .curve
+            .iter()
+            .find(|[_, kw]| !kw.is_finite() || *kw < 0.0)
+        {
+            return Err(format!("curve power at {soc}% is {kw} kW"));
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={CATALOG}");
+    let toml = fs::read_to_string(CATALOG).expect("vehicle catalog should be readable");
+    let catalog: Catalog =
+        toml::from_str(&toml).unwrap_or_else(|e| panic!("{CATALOG} is invalid: {e}"));
+    let mut names = HashSet::new();
+    for vehicle in &catalog.vehicle {
+        if let Err(e) = vehicle.validate() {
+            panic!("{CATALOG}: {}: {e}", vehicle.name);
+        }
+        // scenarios refer to vehicles by name
+        if !names.insert(&vehicle.name) {
+            panic!("{CATALOG}: {} is listed twice", vehicle.name);
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "const VEHICLE_DATA_VERSION: u32 = {};",
+        catalog.version
+    )
+    .unwrap();
+    writeln!(out, "static VEHICLES: &[VehicleSpec] = &[").unwrap();
+    for vehicle in &catalog.vehicle {
+        let curve = vehicle
+            .curve
+            .iter()
+            .map(|[soc, kw]| format!("CurvePoint::new({soc:?}, {kw:?})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            out,
+            "    VehicleSpec {{ name: {:?}, model_year: {}, trim: {:?}, battery_max: Energy::from_kwh({:?}), \
+             charge_curve: ChargeCurve {{ data_points: Cow::Borrowed(&[{curve}]) }}, epa_miles: {:?}, \
+             pack_voltage: {}, onboard_charger: OnboardCharger::single_phase({:?}), source: {:?}, notes: {:?} }},",
+            vehicle.name,
+            vehicle.model_year,
+            vehicle.trim,
+            vehicle.battery_kwh,
+            vehicle.epa_miles,
+            vehicle.pack_voltage,
+            vehicle.onboard_charger_kw,
+            vehicle.source,
+            vehicle.notes,
+        )
+        .unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("vehicles.rs");
+    fs::write(path, out).expect("generated vehicle catalog should be writable");
+}
@@ -1,6 +1,12 @@
+mod line_chart;
 mod match_formatter;
 mod select;
 
+#[cfg(feature = "ssr")]
+pub use line_chart::line_chart_svg;
+pub use line_chart::{
+    chart_ink, ChartAxis, ChartMarker, ChartSeries, LineChart, LineChartData,
+};
 pub use match_formatter::MatchFormatter;
 pub use select::Select;
 
@@ -0,0 +1,971 @@
+use std::{iter, sync::Arc};
+
+use itertools::Itertools;
+use leptos::{ev, html, prelude::*};
+use leptos_use::{
+    use_element_size, use_mouse_in_element, use_preferred_dark, UseElementSizeReturn,
+    UseMouseInElementReturn,
+};
+
+/// Colors handed to series in order, the echarts palette the charts used before
+const PALETTE: [&str; 9] = [
+    "#5470c6", "#91cc75", "#fac858", "#ee6666", "#73c0de", "#3ba272", "#fc8452", "#9a60b4",
+    "#ea7ccc",
+];
+
+/// Points drawn per series, longer series are thinned so the markup stays small.
+/// Zooming in thins only what's on screen, bringing the detail back.
+const MAX_POINTS: usize = 500;
+
+/// Width the chart is laid out at before the browser has measured it, and on the server
+const DEFAULT_WIDTH: f64 = 1000.0;
+
+/// Most series listed in the tooltip, the rest are summarized
+const MAX_TOOLTIP_ENTRIES: usize = 10;
+
+/// Narrower drags than this are clicks, not zooms
+const MIN_ZOOM_PX: f64 = 8.0;
+
+/// How much of the visible range each press of the zoom buttons or keys keeps
+const ZOOM_STEP: f64 = 0.5;
+
+/// How far the arrow keys move a zoomed chart, as a share of the visible range
+const PAN_STEP: f64 = 0.25;
+
+/// The narrowest zoom shows this share of the data, any closer and ticks lose their precision
+const MIN_ZOOM_SHARE: f64 = 0.001;
+
+/// Rows in the data table, evenly spread over the x axis so long runs stay readable
+const MAX_TABLE_ROWS: usize = 200;
+
+const TICKS: f64 = 5.0;
+const MARGIN_LEFT: f64 = 80.0;
+const MARGIN_RIGHT: f64 = 20.0;
+/// room for the second y axis' labels
+const MARGIN_RIGHT_AXIS: f64 = 80.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 50.0;
+
+pub type AxisLabel = Arc<dyn Fn(f64) -> String + Send + Sync>;
+
+#[derive(Clone)]
+pub struct ChartAxis {
+    name: String,
+    label: AxisLabel,
+    /// candidate distances between ticks, picked from round numbers if empty
+    steps: &'static [f64],
+    range: Option<(f64, f64)>,
+}
+
+impl ChartAxis {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            label: Arc::new(|value| format!("{value}")),
+            steps: &[],
+            range: None,
+        }
+    }
+
+    /// How tick labels and tooltip values on this axis are written
+    pub fn label(mut self, label: impl Fn(f64) -> String + Send + Sync + 'static) -> Self {
+        self.label = Arc::new(label);
+        self
+    }
+
+    pub fn steps(mut self, steps: &'static [f64]) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Fixes the axis to `min..max` instead of fitting it to the data
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    /// The distance between ticks, `None` for an empty or endless span
+    fn step(&self, span: f64) -> Option<f64> {
+        if !(span > 0.0 && span.is_finite()) {
+            return None;
+        }
+        let rough = span / TICKS;
+        if let Some(step) = self.steps.iter().find(|step| **step >= rough) {
+            return Some(*step);
+        }
+        // 1, 2 or 5 times a power of ten
+        let magnitude = 10f64.powf(rough.log10().floor());
+        let step = [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|m| m * magnitude)
+            .find(|step| *step > 0.0 && *step >= rough);
+        Some(step.unwrap_or(rough))
+    }
+
+    /// Round numbers between `min` and `max`, multiplied out so they don't pick up rounding error
+    fn ticks(&self, min: f64, max: f64) -> Vec<f64> {
+        let Some(step) = self.step(max - min) else {
+            return vec![];
+        };
+        let first = (min / step - 1e-6).ceil() as i64;
+        let last = (max / step + 1e-6).floor() as i64;
+        (first..=last).map(|i| i as f64 * step).collect()
+    }
+}
+
+#[derive(Clone)]
+enum Fill {
+    None,
+    /// down to zero
+    Zero,
+    /// down to another line, sorted by x
+    Between(Vec<(f64, f64)>),
+}
+
+#[derive(Clone)]
+pub struct ChartSeries {
+    name: String,
+    /// sorted by x
+    points: Vec<(f64, f64)>,
+    color: Option<&'static str>,
+    fill: Fill,
+    dash: Option<&'static str>,
+    width: f64,
+    right_axis: bool,
+    /// labelled dots on the line
+    markers: Vec<(f64, f64, String)>,
+}
+
+impl ChartSeries {
+    pub fn line(name: impl Into<String>, points: Vec<(f64, f64)>) -> Self {
+        Self {
+            name: name.into(),
+            points,
+            color: None,
+            fill: Fill::None,
+            dash: None,
+            width: 2.0,
+            right_axis: false,
+            markers: vec![],
+        }
+    }
+
+    pub fn color(mut self, color: &'static str) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Shades the area between the line and zero
+    pub fn filled(mut self) -> Self {
+        self.fill = Fill::Zero;
+        self
+    }
+
+    /// Shades the area between the line and `base`
+    pub fn filled_from(mut self, base: Vec<(f64, f64)>) -> Self {
+        self.fill = Fill::Between(base);
+        self
+    }
+
+    pub fn dashed(mut self) -> Self {
+        self.dash = Some("6 4");
+        self
+    }
+
+    pub fn dotted(mut self) -> Self {
+        self.dash = Some("2 3");
+        self
+    }
+
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Plots against the chart's second y axis
+    pub fn right_axis(mut self) -> Self {
+        self.right_axis = true;
+        self
+    }
+
+    pub fn marker(mut self, x: f64, y: f64, label: impl Into<String>) -> Self {
+        self.markers.push((x, y, label.into()));
+        self
+    }
+}
+
+#[derive(Clone)]
+pub enum ChartMarker {
+    /// A dashed line across the plot, labelled at the top
+    VerticalLine { x: f64, label: String },
+    /// Shades the plot between two x values
+    Band { from: f64, to: f64 },
+}
+
+/// Everything a [`LineChart`] draws
+#[derive(Clone)]
+pub struct LineChartData {
+    title: String,
+    x_axis: ChartAxis,
+    y_axis: ChartAxis,
+    right_axis: Option<ChartAxis>,
+    series: Vec<ChartSeries>,
+    markers: Vec<ChartMarker>,
+    /// what the chart shows in a sentence or two, for anyone who can't see it
+    description: Option<String>,
+}
+
+impl LineChartData {
+    pub fn new(title: impl Into<String>, x_axis: ChartAxis, y_axis: ChartAxis) -> Self {
+        Self {
+            title: title.into(),
+            x_axis,
+            y_axis,
+            right_axis: None,
+            series: vec![],
+            markers: vec![],
+            description: None,
+        }
+    }
+
+    /// Text read out and shown above the data table in place of the chart.
+    /// Without one the summary just lists each series' range.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// A second y axis on the right, for series added with [`ChartSeries::right_axis`]
+    pub fn right_axis(mut self, axis: ChartAxis) -> Self {
+        self.right_axis = Some(axis);
+        self
+    }
+
+    pub fn series(mut self, series: ChartSeries) -> Self {
+        self.series.push(series);
+        self
+    }
+
+    pub fn marker(mut self, marker: ChartMarker) -> Self {
+        self.markers.push(marker);
+        self
+    }
+
+    fn color(&self, index: usize) -> &'static str {
+        self.series[index]
+            .color
+            .unwrap_or(PALETTE[index % PALETTE.len()])
+    }
+
+    fn axis_for(&self, series: &ChartSeries) -> &ChartAxis {
+        match (&self.right_axis, series.right_axis) {
+            (Some(axis), true) => axis,
+            _ => &self.y_axis,
+        }
+    }
+
+    /// The description, followed by where each series starts, ends and peaks
+    fn summary(&self) -> String {
+        let series = self.series.iter().filter_map(|series| {
+            let (first, last) = (series.points.first()?, series.points.last()?);
+            // the first time it reaches its highest
+            let peak = series
+                .points
+                .iter()
+                .reduce(|peak, p| if p.1 > peak.1 { p } else { peak })?;
+            let (x, y) = (&self.x_axis.label, &self.axis_for(series).label);
+            Some(format!(
+                "{} runs from {} to {}, peaking at {} at {}.",
+                series.name,
+                x(first.0),
+                x(last.0),
+                y(peak.1),
+                x(peak.0)
+            ))
+        });
+        let description = self
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("{} by {}.", self.title, self.x_axis.name.to_lowercase()));
+        iter::once(description).chain(series).join(" ")
+    }
+
+    /// One row per x value, sampled down to [`MAX_TABLE_ROWS`], with each series' value there
+    fn table(&self) -> Vec<(f64, Vec<Option<f64>>)> {
+        let xs = self
+            .series
+            .iter()
+            .flat_map(|series| series.points.iter().map(|p| p.0))
+            .sorted_by(f64::total_cmp)
+            .dedup()
+            .collect::<Vec<_>>();
+        let stride = xs.len().div_ceil(MAX_TABLE_ROWS).max(1);
+        let mut rows = xs.iter().step_by(stride).copied().collect::<Vec<_>>();
+        if let Some(last) = xs.last() {
+            if rows.last() != Some(last) {
+                rows.push(*last);
+            }
+        }
+        rows.into_iter()
+            .map(|x| {
+                let values = self
+                    .series
+                    .iter()
+                    .map(|series| nearest(&series.points, x).map(|p| p.1))
+                    .collect();
+                (x, values)
+            })
+            .collect()
+    }
+}
+
+/// Maps data values onto the plot area
+#[derive(Clone, Copy, PartialEq)]
+struct Scale {
+    min: f64,
+    max: f64,
+    from: f64,
+    to: f64,
+}
+
+impl Scale {
+    fn at(&self, value: f64) -> f64 {
+        self.from + (value - self.min) / (self.max - self.min) * (self.to - self.from)
+    }
+
+    fn invert(&self, at: f64) -> f64 {
+        self.min + (at - self.from) / (self.to - self.from) * (self.max - self.min)
+    }
+}
+
+/// Where everything goes for one size, zoom and set of visible series
+#[derive(Clone, PartialEq)]
+struct Layout {
+    width: f64,
+    height: f64,
+    x: Scale,
+    y: Scale,
+    right: Option<Scale>,
+    x_ticks: Vec<f64>,
+    y_ticks: Vec<f64>,
+    right_ticks: Vec<f64>,
+}
+
+/// The smallest and largest value, widened when they're the same
+fn extent(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+    if !min.is_finite() {
+        None
+    } else if max > min {
+        Some((min, max))
+    } else {
+        Some((min, min + 1.0))
+    }
+}
+
+/// Fits a value axis to `values`, from zero and out to the nearest tick
+fn fit(axis: &ChartAxis, values: impl Iterator<Item = f64>) -> (f64, f64) {
+    axis.range.unwrap_or_else(|| {
+        let (min, max) = extent(values).unwrap_or((0.0, 1.0));
+        let (min, max) = (min.min(0.0), max.max(0.0));
+        match axis.step(max - min) {
+            Some(step) => ((min / step).floor() * step, (max / step).ceil() * step),
+            None => (min, max),
+        }
+    })
+}
+
+/// The points of `points` inside `min..max`, plus one either side so the line reaches the edge
+fn in_range(points: &[(f64, f64)], min: f64, max: f64) -> &[(f64, f64)] {
+    let start = points.partition_point(|p| p.0 < min).saturating_sub(1);
+    let end = (points.partition_point(|p| p.0 <= max) + 1).min(points.len());
+    &points[start..end.max(start)]
+}
+
+/// The point closest to `x`, if `x` is within the series
+fn nearest(points: &[(f64, f64)], x: f64) -> Option<(f64, f64)> {
+    let (first, last) = (points.first()?, points.last()?);
+    if x < first.0 || x > last.0 {
+        return None;
+    }
+    let next = points.partition_point(|p| p.0 < x);
+    [next.saturating_sub(1), next.min(points.len() - 1)]
+        .into_iter()
+        .map(|i| points[i])
+        .min_by(|a, b| (a.0 - x).abs().total_cmp(&(b.0 - x).abs()))
+}
+
+/// Keeps every nth point so there are at most [`MAX_POINTS`], always including the last
+fn thin(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let stride = points.len().div_ceil(MAX_POINTS).max(1);
+    let mut thinned = points.iter().step_by(stride).copied().collect::<Vec<_>>();
+    if let Some(last) = points.last() {
+        if thinned.last() != Some(last) {
+            thinned.push(*last);
+        }
+    }
+    thinned
+}
+
+impl Layout {
+    fn new(
+        chart: &LineChartData,
+        hidden: &[String],
+        zoom: Option<(f64, f64)>,
+        width: f64,
+        height: f64,
+    ) -> Self {
+        let visible = || chart.series.iter().filter(|s| !hidden.contains(&s.name));
+        let (x_min, x_max) = zoom.or(chart.x_axis.range).unwrap_or_else(|| {
+            extent(visible().flat_map(|s| s.points.iter().map(|p| p.0))).unwrap_or((0.0, 1.0))
+        });
+        let values = |right: bool| {
+            visible()
+                .filter(move |s| s.right_axis == right)
+                .flat_map(move |s| in_range(&s.points, x_min, x_max).iter().map(|p| p.1))
+        };
+        let (y_min, y_max) = fit(&chart.y_axis, values(false));
+        let right_margin = match chart.right_axis {
+            Some(_) => MARGIN_RIGHT_AXIS,
+            None => MARGIN_RIGHT,
+        };
+        let x = Scale {
+            min: x_min,
+            max: x_max,
+            from: MARGIN_LEFT,
+            to: (width - right_margin).max(MARGIN_LEFT + 1.0),
+        };
+        let y = Scale {
+            min: y_min,
+            max: y_max,
+            from: (height - MARGIN_BOTTOM).max(MARGIN_TOP + 1.0),
+            to: MARGIN_TOP,
+        };
+        let right = chart.right_axis.as_ref().map(|axis| {
+            let (min, max) = fit(axis, values(true));
+            Scale { min, max, ..y }
+        });
+        Self {
+            width,
+            height,
+            x,
+            y,
+            right,
+            x_ticks: chart.x_axis.ticks(x_min, x_max),
+            y_ticks: chart.y_axis.ticks(y_min, y_max),
+            right_ticks: match (&chart.right_axis, right) {
+                (Some(axis), Some(scale)) => axis.ticks(scale.min, scale.max),
+                _ => vec![],
+            },
+        }
+    }
+
+    fn y_for(&self, series: &ChartSeries) -> Scale {
+        match (series.right_axis, self.right) {
+            (true, Some(right)) => right,
+            _ => self.y,
+        }
+    }
+}
+
+/// Text and gridline colors for the page theme
+pub fn chart_ink(dark: bool) -> (&'static str, &'static str) {
+    if dark {
+        ("#fffbeb", "rgba(255, 251, 235, 0.15)")
+    } else {
+        ("#1e293b", "rgba(30, 41, 59, 0.15)")
+    }
+}
+
+/// The chart itself, without any of the interactive overlays.
+/// `clip_id` has to be unique on the page, it keeps zoomed lines inside the plot.
+fn plot(
+    chart: &LineChartData,
+    layout: &Layout,
+    hidden: &[String],
+    dark: bool,
+    clip_id: &str,
+) -> impl IntoView {
+    let (text, grid) = chart_ink(dark);
+    let Layout { x, y, .. } = *layout;
+    let clip = format!("url(#{clip_id})");
+    let zero = y.at(0f64.clamp(y.min, y.max));
+    let bands = chart
+        .markers
+        .iter()
+        .filter_map(|marker| match marker {
+            ChartMarker::Band { from, to } => {
+                let (left, right) = (x.at(*from).max(x.from), x.at(*to).min(x.to));
+                (right > left).then(|| {
+                    view! {
+                        <rect x=format!("{left:.1}") y=format!("{:.1}", y.to) width=format!("{:.1}", right - left)
+                            height=format!("{:.1}", y.from - y.to) fill="#5373BB" fill-opacity="0.15" />
+                    }
+                })
+            }
+            ChartMarker::VerticalLine { .. } => None,
+        })
+        .collect_view();
+    let y_ticks = layout
+        .y_ticks
+        .iter()
+        .map(|tick| {
+            let at = y.at(*tick);
+            view! {
+                <line x1=format!("{:.1}", x.from) y1=format!("{at:.1}") x2=format!("{:.1}", x.to) y2=format!("{at:.1}") stroke=grid />
+                <text x=format!("{:.1}", x.from - 6.0) y=format!("{:.1}", at + 4.0) text-anchor="end" fill=text>{(chart.y_axis.label)(*tick)}</text>
+            }
+        })
+        .collect_view();
+    let right_ticks = chart.right_axis.as_ref().zip(layout.right).map(|(axis, right)| {
+        layout
+            .right_ticks
+            .iter()
+            .map(|tick| {
+                view! {
+                    <text x=format!("{:.1}", x.to + 6.0) y=format!("{:.1}", right.at(*tick) + 4.0) fill=text>{(axis.label)(*tick)}</text>
+                }
+            })
+            .collect_view()
+    });
+    let x_ticks = layout
+        .x_ticks
+        .iter()
+        .map(|tick| {
+            let at = x.at(*tick);
+            view! {
+                <line x1=format!("{at:.1}") y1=format!("{:.1}", y.from) x2=format!("{at:.1}") y2=format!("{:.1}", y.from + 5.0) stroke=text stroke-opacity="0.4" />
+                <text x=format!("{at:.1}") y=format!("{:.1}", y.from + 20.0) text-anchor="middle" fill=text>{(chart.x_axis.label)(*tick)}</text>
+            }
+        })
+        .collect_view();
+    let series = chart
+        .series
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| !hidden.contains(&s.name))
+        .map(|(i, series)| {
+            let color = chart.color(i);
+            let scale = layout.y_for(series);
+            let to_path = |points: &[(f64, f64)]| {
+                thin(in_range(points, x.min, x.max))
+                    .iter()
+                    .map(|(px, py)| format!("{:.1} {:.1}", x.at(*px), scale.at(*py)))
+                    .join(" L")
+            };
+            let line = to_path(&series.points);
+            let area = match &series.fill {
+                Fill::None => None,
+                _ if line.is_empty() => None,
+                Fill::Zero => {
+                    let points = in_range(&series.points, x.min, x.max);
+                    let (first, last) = (points[0].0, points[points.len() - 1].0);
+                    let base = scale.at(0f64.clamp(scale.min, scale.max));
+                    Some(format!(
+                        "M{:.1} {base:.1} L{line} L{:.1} {base:.1} Z",
+                        x.at(first),
+                        x.at(last)
+                    ))
+                }
+                Fill::Between(base) => {
+                    let mut base = thin(in_range(base, x.min, x.max));
+                    base.reverse();
+                    let base = base
+                        .iter()
+                        .map(|(px, py)| format!("{:.1} {:.1}", x.at(*px), scale.at(*py)))
+                        .join(" L");
+                    Some(format!("M{line} L{base} Z"))
+                }
+            };
+            let markers = series
+                .markers
+                .iter()
+                .filter(|(mx, _, _)| (x.min..=x.max).contains(mx))
+                .map(|(mx, my, label)| {
+                    let (cx, cy) = (x.at(*mx), scale.at(*my));
+                    view! {
+                        <circle cx=format!("{cx:.1}") cy=format!("{cy:.1}") r="4" fill=color />
+                        <text x=format!("{cx:.1}") y=format!("{:.1}", cy - 8.0) text-anchor="middle" font-size="11" fill=text>{label.clone()}</text>
+                    }
+                })
+                .collect_view();
+            view! {
+                <g clip-path=clip.clone()>
+                    {area.map(|area| view! { <path d=area fill=color fill-opacity="0.3" stroke="none" /> })}
+                    <path d=format!("M{line}") fill="none" stroke=color stroke-width=series.width.to_string()
+                        stroke-dasharray=series.dash stroke-linejoin="round" />
+                    {markers}
+                </g>
+            }
+        })
+        .collect_view();
+    let lines = chart
+        .markers
+        .iter()
+        .filter_map(|marker| match marker {
+            ChartMarker::VerticalLine { x: at, label } if (x.min..=x.max).contains(at) => {
+                let at = x.at(*at);
+                Some(view! {
+                    <line x1=format!("{at:.1}") y1=format!("{:.1}", y.to) x2=format!("{at:.1}") y2=format!("{:.1}", y.from)
+                        stroke=text stroke-opacity="0.5" stroke-dasharray="4 4" />
+                    <text x=format!("{:.1}", at + 4.0) y=format!("{:.1}", y.to + 12.0) font-size="11" fill=text>{label.clone()}</text>
+                })
+            }
+            _ => None,
+        })
+        .collect_view();
+    view! {
+        <defs>
+            <clipPath id=clip_id.to_string()>
+                <rect x=format!("{:.1}", x.from) y=format!("{:.1}", y.to) width=format!("{:.1}", x.to - x.from) height=format!("{:.1}", y.from - y.to) />
+            </clipPath>
+        </defs>
+        <text x="10" y="22" font-size="18" font-weight="bold" fill=text>{chart.title.clone()}</text>
+        {bands}
+        {y_ticks}
+        {right_ticks}
+        {x_ticks}
+        <line x1=format!("{:.1}", x.from) y1=format!("{zero:.1}") x2=format!("{:.1}", x.to) y2=format!("{zero:.1}") stroke=text stroke-opacity="0.6" />
+        <text x=format!("{:.1}", (x.from + x.to) / 2.0) y=format!("{:.1}", layout.height - 8.0) text-anchor="middle" fill=text>{chart.x_axis.name.clone()}</text>
+        <text transform=format!("translate(16 {:.1}) rotate(-90)", (y.from + y.to) / 2.0) text-anchor="middle" fill=text>{chart.y_axis.name.clone()}</text>
+        {chart.right_axis.as_ref().map(|axis| view! {
+            <text transform=format!("translate({:.1} {:.1}) rotate(90)", layout.width - 12.0, (y.from + y.to) / 2.0) text-anchor="middle" fill=text>{axis.name.clone()}</text>
+        })}
+        {series}
+        {lines}
+    }
+}
+
+/// Draws `chart` to standalone SVG markup on the server, for images and link previews.
+/// There's no legend, series should be few enough to tell apart or named in the title.
+#[cfg(feature = "ssr")]
+pub fn line_chart_svg(chart: &LineChartData, width: f64, height: f64) -> String {
+    let layout = Layout::new(chart, &[], None, width, height);
+    let svg = Owner::new().with(|| plot(chart, &layout, &[], false, "plot").to_html());
+    // leptos marks empty optional views with `<!>`, which isn't valid XML
+    let svg = svg.replace("<!>", "");
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {width} {height}" width="{width}" height="{height}" font-family="sans-serif" font-size="13">{svg}</svg>"#
+    )
+}
+
+/// A line chart drawn as SVG, so it renders on the server as well as in the browser.
+/// Click the legend to hide series, hover for values and drag across the plot to zoom.
+/// The zoom buttons, or `+`, `-`, the arrow keys and `0` with the plot focused, do the same
+/// without a mouse.
+#[component]
+pub fn LineChart(
+    #[prop(into)] chart: Signal<LineChartData>,
+    /// unique on the page
+    id: &'static str,
+    #[prop(default = 500.0)] height: f64,
+) -> impl IntoView {
+    let container = NodeRef::<html::Div>::new();
+    let UseElementSizeReturn { width, .. } = use_element_size(container);
+    let UseMouseInElementReturn {
+        element_x,
+        element_y,
+        is_outside,
+        ..
+    } = use_mouse_in_element(container);
+    let dark = use_preferred_dark();
+    let width = Signal::derive(move || match width() {
+        width if width > 0.0 => width,
+        _ => DEFAULT_WIDTH,
+    });
+    let hidden = RwSignal::new(Vec::<String>::new());
+    let zoom = RwSignal::new(None::<(f64, f64)>);
+    // where a zoom drag started, in pixels
+    let drag_start = RwSignal::new(None::<f64>);
+    Effect::watch(
+        move || chart.track(),
+        move |_, _, _| {
+            zoom.set(None);
+            hidden.set(vec![]);
+        },
+        false,
+    );
+    let layout = Memo::new(move |_| {
+        chart
+            .with(|chart| hidden.with(|hidden| Layout::new(chart, hidden, zoom(), width(), height)))
+    });
+    let in_plot = move |layout: &Layout| {
+        !is_outside()
+            && (layout.x.from..=layout.x.to).contains(&element_x())
+            && (layout.y.to..=layout.y.from).contains(&element_y())
+    };
+    let clip_id = format!("{id}-plot");
+    let summary_id = format!("{id}-summary");
+    let guide = move || {
+        layout.with(|layout| {
+            let (text, _) = chart_ink(dark());
+            let hover = in_plot(layout).then(|| {
+                let at = element_x();
+                view! {
+                    <line x1=format!("{at:.1}") y1=format!("{:.1}", layout.y.to) x2=format!("{at:.1}") y2=format!("{:.1}", layout.y.from)
+                        stroke=text stroke-opacity="0.5" pointer-events="none" />
+                }
+            });
+            let selection = drag_start().map(|start| {
+                let end = element_x().clamp(layout.x.from, layout.x.to);
+                view! {
+                    <rect x=format!("{:.1}", start.min(end)) y=format!("{:.1}", layout.y.to) width=format!("{:.1}", (end - start).abs())
+                        height=format!("{:.1}", layout.y.from - layout.y.to) fill=text fill-opacity="0.1" pointer-events="none" />
+                }
+            });
+            view! { {hover} {selection} }
+        })
+    };
+    let tooltip = move || {
+        let layout = layout.get();
+        if !in_plot(&layout) || drag_start().is_some() {
+            return None;
+        }
+        let at = element_x();
+        let x = layout.x.invert(at);
+        let entries = chart.with(|chart| {
+            hidden.with(|hidden| {
+                chart
+                    .series
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| !hidden.contains(&s.name))
+                    .filter_map(|(i, series)| {
+                        let (_, value) = nearest(&series.points, x)?;
+                        Some((
+                            chart.color(i),
+                            series.name.clone(),
+                            value,
+                            (chart.axis_for(series).label)(value),
+                        ))
+                    })
+                    .sorted_by(|a, b| b.2.total_cmp(&a.2))
+                    .collect::<Vec<_>>()
+            })
+        });
+        if entries.is_empty() {
+            return None;
+        }
+        let heading = chart.with(|chart| (chart.x_axis.label)(x));
+        let more = entries.len().saturating_sub(MAX_TOOLTIP_ENTRIES);
+        // keep the box on the side of the cursor with more room
+        let style = if at < layout.width / 2.0 {
+            format!("left: {:.0}px; top: {MARGIN_TOP}px", at + 12.0)
+        } else {
+            format!(
+                "right: {:.0}px; top: {MARGIN_TOP}px",
+                layout.width - at + 12.0
+            )
+        };
+        Some(view! {
+            <div class="absolute pointer-events-none rounded-lg bg-white dark:bg-slate-800 border border-slate-300 dark:border-slate-500 p-2 text-sm shadow-md" style=style>
+                <div class="font-medium">{heading}</div>
+                {entries.into_iter().take(MAX_TOOLTIP_ENTRIES).map(|(color, name, _, value)| view! {
+                    <div class="flex flex-row gap-1 items-center">
+                        <span class="inline-block w-3 h-3 rounded-sm" style=format!("background-color: {color}")></span>
+                        {name}": "{value}
+                    </div>
+                }).collect_view()}
+                {(more > 0).then(|| view! { <div>"and "{more}" more"</div> })}
+            </div>
+        })
+    };
+    // the x axis with nothing zoomed in
+    let full_x = move || {
+        chart.with_untracked(|chart| {
+            hidden.with_untracked(|hidden| Layout::new(chart, hidden, None, 1.0, 1.0).x)
+        })
+    };
+    let finish_drag = move || {
+        let Some(start) = drag_start.get_untracked() else {
+            return;
+        };
+        drag_start.set(None);
+        let layout = layout.get_untracked();
+        let end = element_x.get_untracked().clamp(layout.x.from, layout.x.to);
+        if (end - start).abs() >= MIN_ZOOM_PX {
+            let (a, b) = (layout.x.invert(start), layout.x.invert(end));
+            let full = full_x();
+            let span = (b - a).abs().max((full.max - full.min) * MIN_ZOOM_SHARE);
+            let middle = (a + b) / 2.0;
+            zoom.set(Some((middle - span / 2.0, middle + span / 2.0)));
+        }
+    };
+    // zooms around the middle of what's visible by `factor` and moves it by `pan` of its width,
+    // never past the data
+    let step_zoom = move |factor: f64, pan: f64| {
+        let full = full_x();
+        let current = layout.get_untracked().x;
+        let full_span = full.max - full.min;
+        let span = ((current.max - current.min) * factor)
+            .max(full_span * MIN_ZOOM_SHARE)
+            .min(full_span);
+        if span >= full_span * (1.0 - 1e-9) {
+            zoom.set(None);
+            return;
+        }
+        let middle = ((current.min + current.max) / 2.0 + pan * (current.max - current.min))
+            .clamp(full.min + span / 2.0, full.max - span / 2.0);
+        zoom.set(Some((middle - span / 2.0, middle + span / 2.0)));
+    };
+    let on_key = move |e: ev::KeyboardEvent| {
+        match e.key().as_str() {
+            "+" | "=" => step_zoom(ZOOM_STEP, 0.0),
+            "-" | "_" => step_zoom(1.0 / ZOOM_STEP, 0.0),
+            "ArrowLeft" => step_zoom(1.0, -PAN_STEP),
+            "ArrowRight" => step_zoom(1.0, PAN_STEP),
+            "0" | "Escape" => zoom.set(None),
+            _ => return,
+        }
+        e.prevent_default();
+    };
+    let zoom_button = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 px-1.5 border border-slate-300 dark:border-slate-500 transition-colors";
+    view! {
+        <div class="flex flex-col gap-1">
+            <div class="flex flex-row flex-wrap gap-x-3 gap-y-1 text-sm max-h-24 overflow-y-auto items-center">
+                {move || chart.with(|chart| chart.series.iter().enumerate().map(|(i, series)| {
+                    let color = chart.color(i);
+                    let name = series.name.clone();
+                    let toggle = name.clone();
+                    let is_hidden = move || hidden.with(|hidden| hidden.contains(&name));
+                    view! {
+                        <button class="flex flex-row gap-1 items-center" class:opacity-40=is_hidden.clone()
+                            aria-pressed=move || (!is_hidden()).to_string()
+                            on:click=move |_| hidden.update(|hidden| {
+                                if let Some(index) = hidden.iter().position(|h| *h == toggle) {
+                                    hidden.remove(index);
+                                } else {
+                                    hidden.push(toggle.clone());
+                                }
+                            })>
+                            <span class="inline-block w-3 h-3 rounded-sm" style=format!("background-color: {color}")></span>
+                            {series.name.clone()}
+                        </button>
+                    }
+                }).collect_view())}
+                <button class=zoom_button aria-label="Zoom in" on:click=move |_| step_zoom(ZOOM_STEP, 0.0)>"+"</button>
+                <button class=zoom_button aria-label="Zoom out" disabled=move || zoom().is_none()
+                    on:click=move |_| step_zoom(1.0 / ZOOM_STEP, 0.0)>"-"</button>
+                <button class=zoom_button class:hidden=move || zoom().is_none() on:click=move |_| zoom.set(None)>"Reset zoom"</button>
+            </div>
+            <div node_ref=container class="relative w-full select-none" style=format!("height: {height}px")>
+                <svg xmlns="http://www.w3.org/2000/svg" width="100%" height=height.to_string() font-family="sans-serif" font-size="13"
+                    role="img" aria-label=move || chart.with(|chart| chart.title.clone()) aria-describedby=summary_id.clone()
+                    viewBox=move || format!("0 0 {:.0} {height}", width())
+                    tabindex="0" on:keydown=on_key
+                    on:mousedown=move |_| {
+                        if layout.with_untracked(in_plot) {
+                            drag_start.set(Some(element_x.get_untracked()));
+                        }
+                    }
+                    on:mouseup=move |_| finish_drag()
+                    on:mouseleave=move |_| drag_start.set(None)>
+                    {move || chart.with(|chart| layout.with(|layout| hidden.with(|hidden| plot(chart, layout, hidden, dark(), &clip_id))))}
+                    {guide}
+                </svg>
+                {tooltip}
+            </div>
+            <ChartTable chart summary_id />
+        </div>
+    }
+    .into_any()
+}
+
+/// The chart's summary and its data as a table, for screen readers and keyboards.
+/// Rows are only built once it's opened, long runs make for a lot of them.
+#[component]
+fn ChartTable(chart: Signal<LineChartData>, summary_id: String) -> impl IntoView {
+    let (open, set_open) = signal(false);
+    let table = move || {
+        open().then(|| {
+            chart.with(|chart| {
+                let headings = chart
+                    .series
+                    .iter()
+                    .map(|series| {
+                        let axis = chart.axis_for(series);
+                        view! { <th scope="col" class="px-2 text-left">{series.name.clone()}" ("{axis.name.clone()}")"</th> }
+                    })
+                    .collect_view();
+                let rows = chart
+                    .table()
+                    .into_iter()
+                    .map(|(x, values)| {
+                        let cells = chart
+                            .series
+                            .iter()
+                            .zip(values)
+                            .map(|(series, value)| {
+                                let value = value.map(|value| (chart.axis_for(series).label)(value));
+                                view! { <td class="px-2">{value.unwrap_or_else(|| "-".to_string())}</td> }
+                            })
+                            .collect_view();
+                        view! {
+                            <tr class="even:bg-slate-50 dark:even:bg-slate-700">
+                                <th scope="row" class="px-2 text-left font-normal whitespace-nowrap">{(chart.x_axis.label)(x)}</th>
+                                {cells}
+                            </tr>
+                        }
+                    })
+                    .collect_view();
+                view! {
+                    <div tabindex="0" role="region" aria-label=format!("{} data", chart.title)
+                        class="max-h-96 overflow-auto border border-slate-300 dark:border-slate-500 rounded-lg">
+                        <table class="text-sm">
+                            <caption class="sr-only">{chart.title.clone()}</caption>
+                            <thead class="sticky top-0 bg-white dark:bg-slate-800">
+                                <tr>
+                                    <th scope="col" class="px-2 text-left">{chart.x_axis.name.clone()}</th>
+                                    {headings}
+                                </tr>
+                            </thead>
+                            <tbody>{rows}</tbody>
+                        </table>
+                    </div>
+                }
+            })
+        })
+    };
+    view! {
+        <details class="flex flex-col gap-1 text-sm" on:toggle=move |e| set_open(event_target::<web_sys::HtmlDetailsElement>(&e).open())>
+            <summary class="cursor-pointer">"Chart as text and table"</summary>
+            <p id=summary_id>{move || chart.with(LineChartData::summary)}</p>
+            {table}
+        </details>
+    }
+    .into_any()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_fall_on_round_numbers() {
+        let axis = ChartAxis::new("kW");
+        assert_eq!(axis.ticks(0.0, 100.0), [0.0, 20.0, 40.0, 60.0, 80.0, 100.0]);
+        assert_eq!(axis.ticks(3.0, 12.0), [4.0, 6.0, 8.0, 10.0, 12.0]);
+    }
+
+    #[test]
+    fn empty_or_endless_spans_have_no_ticks() {
+        let axis = ChartAxis::new("kW");
+        assert!(axis.ticks(5.0, 5.0).is_empty());
+        assert!(axis.ticks(5.0, 1.0).is_empty());
+        assert!(axis.ticks(0.0, f64::INFINITY).is_empty());
+        assert!(axis.ticks(f64::NAN, 1.0).is_empty());
+        assert_eq!(fit(&axis, [f64::NAN].into_iter()), (0.0, 1.0));
+    }
+
+    #[test]
+    fn single_points_still_get_an_axis() {
+        let chart = LineChartData::new("", ChartAxis::new("x"), ChartAxis::new("y"))
+            .series(ChartSeries::line("flat", vec![(3.0, 7.0)]));
+        let layout = Layout::new(&chart, &[], None, 600.0, 400.0);
+        assert!(layout.x.max > layout.x.min);
+        assert!(!layout.x_ticks.is_empty() && !layout.y_ticks.is_empty());
+        assert!(layout.x.at(3.0).is_finite() && layout.y.at(7.0).is_finite());
+    }
+}
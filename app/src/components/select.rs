@@ -37,7 +37,7 @@ where
     let labels = Memo::new(move |_| {
         items.with(|i| {
             i.iter()
-                .map(|i| as_label(i))
+                .map(as_label)
                 .enumerate()
                 .collect::<Vec<_>>()
         })
@@ -1,3 +1,5 @@
+#![recursion_limit = "256"]
+
 mod components;
 mod flickr;
 mod home_page;
@@ -10,14 +12,21 @@ use crate::{
     home_page::*,
     pictures::*,
     projects::*,
-    toys::{ToyPage, VehicleSim},
+    toys::{
+        ChargeTimeCalculator, DepotScheduler, SharedScenario, ToyPage, TripPlanner,
+        VehicleCatalog, VehicleSim,
+    },
 };
 use leptos::prelude::*;
 use leptos_meta::*;
-use leptos_router::{components::*, path};
+use leptos_router::{components::*, path, SsrMode};
 
 pub mod error_template;
 
+#[cfg(feature = "ssr")]
+pub use toys::scenario_preview_png;
+pub use toys::PREVIEW_PATH;
+
 #[component]
 pub fn App() -> impl IntoView {
     provide_meta_context();
@@ -43,6 +52,11 @@ pub fn App() -> impl IntoView {
                         <Route path=path!("/photos") view=Pictures />
                         <Route path=path!("/toys") view=ToyPage />
                         <Route path=path!("/toys/ev-charger-sim") view=VehicleSim />
+                        <Route path=path!("/toys/ev-charger-sim/s/:id") view=SharedScenario ssr=SsrMode::Async />
+                        <Route path=path!("/toys/charge-time") view=ChargeTimeCalculator />
+                        <Route path=path!("/toys/trip-planner") view=TripPlanner />
+                        <Route path=path!("/toys/depot-scheduler") view=DepotScheduler />
+                        <Route path=path!("/toys/vehicles") view=VehicleCatalog />
                     </Routes>
                 </main>
             </div>
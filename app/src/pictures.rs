@@ -49,7 +49,7 @@ pub(crate) fn Pictures() -> impl IntoView {
                 {move || {
                     let pictures = recent_pictures.get();
                     // ignore errors for now
-                    let pictures = pictures.map(|p| p.ok()).flatten();
+                    let pictures = pictures.and_then(|p| p.ok());
                     pictures.map(|p| {
                         p.recent_pictures.into_iter().map(|p| view!{
                             <a href=format!("https://www.flickr.com/photos/198236541@N06/{}/in/dateposted-public/", p.id)
@@ -76,7 +76,7 @@ pub(crate) fn SmallPhotos() -> impl IntoView {
                 {move || {
                     let pictures = recent_pictures.get();
                     // ignore errors for now
-                    let pictures = pictures.map(|p| p.ok()).flatten();
+                    let pictures = pictures.and_then(|p| p.ok());
                     pictures.map(|p| {
                         p.recent_pictures.into_iter().map(|p| view!{
                             <div class="snap-center snap-always m-5 rounded-lg overflow-hidden hover:ring-2 hover:ring-amber-400 dark:hover:ring-teal-400 transition-all duration-300">
@@ -2,16 +2,11 @@ use base64::{engine::general_purpose, Engine};
 use const_soft_float::soft_f64::SoftF64;
 use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use itertools::Itertools;
-use leptos::{html, prelude::*};
-use leptos_meta::{Script, Title};
+use leptos::prelude::*;
+use leptos_meta::Title;
 use leptos_router::hooks::{use_location, use_navigate};
 use leptos_router::NavigateOptions;
-use leptos_use::{
-    core::Size, use_element_size, use_element_size_with_options, use_media_query,
-    use_preferred_dark, UseElementSizeOptions, UseElementSizeReturn,
-};
-use log::info;
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::{Borrow, Cow},
     collections::VecDeque,
@@ -25,7 +20,50 @@ use thiserror::Error;
 
 // class="collapse"
 
-use crate::components::Select;
+use crate::components::{ChartAxis, ChartMarker, ChartSeries, LineChart, LineChartData, Select};
+
+mod calculator;
+mod catalog;
+mod charger_presets;
+mod clock;
+mod compare;
+mod depot;
+mod export;
+mod forecast;
+mod history;
+mod occupancy;
+mod preferences;
+mod preview;
+mod scenario;
+mod short_link;
+mod solar;
+mod trip_planner;
+
+pub use calculator::ChargeTimeCalculator;
+pub use catalog::VehicleCatalog;
+use charger_presets::{ChargerPreset, ChargerPresetDropdown, PresetKey};
+use clock::{
+    daily_arrivals, format_minute_of_day, parse_minute_of_day, ScenarioStartEditor, MAX_DAYS,
+};
+use compare::CurveComparison;
+pub use depot::DepotScheduler;
+use export::SimulationExport;
+use forecast::{Forecast, ForecastPanel};
+use history::{EditHistory, EditKind, UndoRedo};
+use occupancy::PlugOccupancyChart;
+use preferences::{preferences, provide_preferences, split_query, Preferences, PreferencesEditor};
+#[cfg(feature = "ssr")]
+pub use preview::scenario_preview_png;
+use preview::ScenarioPreviewMeta;
+pub use preview::PREVIEW_PATH;
+use scenario::{
+    from_versioned_json, to_versioned_json, ScenarioError, ScenarioErrorBanner, ScenarioFiles,
+    Versioned, MAX_SCENARIO_BYTES,
+};
+pub use short_link::SharedScenario;
+use short_link::ShortLink;
+use solar::{SolarArray, SolarEditor};
+pub use trip_planner::TripPlanner;
 
 /// Percent full represents a percent number from 0% to 100%, and will strictly enforce that.
 /// Represented as a u16 from 0-10000 internally
@@ -113,9 +151,7 @@ impl Energy {
 
 impl Display for Energy {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // lets just assume this is usually kwh for now.
-        let kwh = self.watt_hours / 1000.0;
-        write!(f, "{kwh:.1} KwH")
+        write!(f, "{:.1} kWh", self.as_kwh())
     }
 }
 
@@ -138,10 +174,20 @@ impl Add for Energy {
     }
 }
 
+impl Sub for Energy {
+    type Output = Energy;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            watt_hours: self.watt_hours - rhs.watt_hours,
+        }
+    }
+}
+
 impl Sum for Energy {
-    fn sum<I: Iterator<Item = Self>>(mut iter: I) -> Self {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
         let mut watt_hours = 0.0;
-        while let Some(next) = iter.next() {
+        for next in iter {
             watt_hours += next.watt_hours;
         }
         Energy { watt_hours }
@@ -220,7 +266,7 @@ impl Mul<i32> for Power {
     type Output = Power;
 
     fn mul(mut self, rhs: i32) -> Self::Output {
-        self.watts = self.watts + rhs;
+        self.watts *= rhs;
         self
     }
 }
@@ -246,8 +292,7 @@ impl Sub for Power {
 
 impl Display for Power {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let power_kw = self.as_kw();
-        write!(f, "{power_kw:.1} kw")
+        write!(f, "{:.1} kW", self.as_kw())
     }
 }
 
@@ -261,9 +306,9 @@ impl Add for Power {
 }
 
 impl Sum<Power> for Power {
-    fn sum<I: Iterator<Item = Power>>(mut iter: I) -> Self {
+    fn sum<I: Iterator<Item = Power>>(iter: I) -> Self {
         let mut watts = 0;
-        while let Some(next) = iter.next() {
+        for next in iter {
             watts += next.watts;
         }
         Power { watts }
@@ -294,7 +339,7 @@ struct ChargeCurve {
 impl ChargeCurve {
     /// calculates the average charge charge power
     fn average_power(&self) -> Power {
-        if self.data_points.len() < 1 {
+        if self.data_points.is_empty() {
             return Power::from_kw(0.0);
         }
         let total_power = self
@@ -323,7 +368,8 @@ impl ChargeCurve {
         }
     }
 
-    /// linearly interpolates the power between two different charge for the given SOC
+    /// linearly interpolates the power between two different charge for the given SOC, a SOC
+    /// past either end of the curve, from discharging or overshooting, gets that end's power
     fn power_at(&self, percent: PercentFull) -> Power {
         let internal_soc = percent.0;
         if let Some(exact) = self
@@ -344,7 +390,11 @@ impl ChargeCurve {
             // y = mx + b (simple slope)
             ((b.charge_power - a.charge_power) / span_length * length) + a.charge_power
         } else {
-            unreachable!("invalid percent provided {}", percent);
+            match (self.data_points.first(), self.data_points.last()) {
+                (Some(first), _) if internal_soc < first.state_of_charge.0 => first.charge_power,
+                (_, Some(last)) => last.charge_power,
+                _ => Power::default(),
+            }
         }
     }
 
@@ -387,19 +437,307 @@ impl ChargeCurve {
             data_points: data.into(),
         })
     }
+
+    /// The curve as delivered by a charger that can't exceed `max`, optionally never dropping
+    /// below `floor`. Extra points are added where the curve crosses either limit
+    fn limited(&self, max: Option<Power>, floor: Option<Power>) -> ChargeCurve {
+        if max.is_none() && floor.is_none() {
+            return self.clone();
+        }
+        let clamp = |power: Power| {
+            let power = max.map(|max| power.min(max)).unwrap_or(power);
+            floor.map(|floor| power.max(floor)).unwrap_or(power)
+        };
+        let mut data = vec![];
+        for (a, b) in self.data_points.iter().tuple_windows() {
+            data.push(CurvePoint {
+                state_of_charge: a.state_of_charge,
+                charge_power: clamp(a.charge_power),
+            });
+            let (low, high) = if a.charge_power <= b.charge_power {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            let mut crossings = [floor, max]
+                .into_iter()
+                .flatten()
+                .filter(|limit| low.charge_power < *limit && *limit < high.charge_power)
+                .map(|limit| {
+                    let fraction = (limit - a.charge_power).watts as f64
+                        / (b.charge_power - a.charge_power).watts as f64;
+                    let span = (b.state_of_charge - a.state_of_charge).as_float();
+                    CurvePoint {
+                        state_of_charge: PercentFull::new(
+                            a.state_of_charge.as_float() + span * fraction,
+                        ),
+                        charge_power: clamp(limit),
+                    }
+                })
+                .collect::<Vec<_>>();
+            crossings.sort_by_key(|point| point.state_of_charge);
+            data.extend(crossings);
+        }
+        if let Some(last) = self.data_points.last() {
+            data.push(CurvePoint {
+                state_of_charge: last.state_of_charge,
+                charge_power: clamp(last.charge_power),
+            });
+        }
+        ChargeCurve {
+            data_points: data.into(),
+        }
+    }
+
+    /// Time spent charging from `start` to `end`.
+    /// Power is linear between two points, so each span integrates to `E * ln(p2 / p1) / (p2 - p1)`
+    fn charge_time(&self, battery_max: Energy, start: PercentFull, end: PercentFull) -> Duration {
+        if start >= end {
+            return Duration::ZERO;
+        }
+        let Some(curve) = self.percent_to_percent(start, end) else {
+            return Duration::ZERO;
+        };
+        let hours = curve
+            .data_points
+            .iter()
+            .tuple_windows()
+            .map(|(a, b)| span_hours(battery_max, a, b))
+            .sum::<f64>();
+        Duration::try_from_secs_f64(hours * 60.0 * 60.0).unwrap_or_default()
+    }
+
+    /// The state of charge reached after charging for `elapsed` starting at `start`
+    fn soc_after(&self, battery_max: Energy, start: PercentFull, elapsed: Duration) -> PercentFull {
+        let full = PercentFull::new(100.0);
+        let Some(curve) = self.percent_to_percent(start, full) else {
+            return start;
+        };
+        let mut remaining_hours = elapsed.as_secs_f64() / 60.0 / 60.0;
+        for (a, b) in curve.data_points.iter().tuple_windows() {
+            let hours = span_hours(battery_max, a, b);
+            if hours < remaining_hours {
+                remaining_hours -= hours;
+                continue;
+            }
+            // invert the span integral to find how far into the span we got
+            let energy = (b.state_of_charge - a.state_of_charge) * battery_max;
+            let p1 = a.charge_power.watts.max(1) as f64;
+            let p2 = b.charge_power.watts.max(1) as f64;
+            let fraction = if p1 == p2 {
+                remaining_hours * p1 / energy.watt_hours
+            } else {
+                let power = p1 * (remaining_hours * (p2 - p1) / energy.watt_hours).exp();
+                (power - p1) / (p2 - p1)
+            };
+            let span = (b.state_of_charge - a.state_of_charge).as_float();
+            return PercentFull::new(
+                a.state_of_charge.as_float() + span * fraction.clamp(0.0, 1.0),
+            );
+        }
+        full
+    }
+
+    /// Time to add `energy` starting at `start`, or `None` if it won't fit in the battery
+    fn time_to_add(
+        &self,
+        battery_max: Energy,
+        start: PercentFull,
+        energy: Energy,
+    ) -> Option<Duration> {
+        if battery_max.watt_hours <= 0.0 {
+            return None;
+        }
+        let end = start.as_float() + energy.watt_hours / battery_max.watt_hours * 100.0;
+        if end > 100.0 {
+            return None;
+        }
+        Some(self.charge_time(battery_max, start, PercentFull::new(end)))
+    }
+
+    /// Walks the curve from `start` to `end` in small steps of charge, adding up how long each
+    /// step takes at the power the curve allows.
+    fn charge_session(
+        &self,
+        battery_max: Energy,
+        start: PercentFull,
+        end: PercentFull,
+    ) -> Vec<SessionPoint> {
+        // 0.25% of charge per step
+        const STEP: i16 = 25;
+        if self.data_points.is_empty() || start >= end {
+            return vec![];
+        }
+        let mut point = SessionPoint {
+            elapsed: Duration::ZERO,
+            state_of_charge: start,
+            power: self.power_at(start),
+            energy_added: Energy::default(),
+        };
+        let mut points = vec![point];
+        while point.state_of_charge < end {
+            let next_soc = PercentFull((point.state_of_charge.0 + STEP).min(end.0));
+            let next_power = self.power_at(next_soc);
+            let energy = (next_soc - point.state_of_charge) * battery_max;
+            point = SessionPoint {
+                elapsed: point.elapsed
+                    + self.charge_time(battery_max, point.state_of_charge, next_soc),
+                state_of_charge: next_soc,
+                power: next_power,
+                energy_added: point.energy_added + energy,
+            };
+            points.push(point);
+        }
+        points
+    }
+}
+
+/// Hours to charge across the span between two curve points
+fn span_hours(battery_max: Energy, a: &CurvePoint, b: &CurvePoint) -> f64 {
+    let energy = (b.state_of_charge - a.state_of_charge) * battery_max;
+    let p1 = a.charge_power.watts.max(1) as f64;
+    let p2 = b.charge_power.watts.max(1) as f64;
+    if p1 == p2 {
+        energy.watt_hours / p1
+    } else {
+        energy.watt_hours * (p2 / p1).ln() / (p2 - p1)
+    }
+}
+
+/// The state of a charging session after charging along a [`ChargeCurve`] for `elapsed`
+#[derive(Clone, Copy)]
+struct SessionPoint {
+    elapsed: Duration,
+    state_of_charge: PercentFull,
+    power: Power,
+    energy_added: Energy,
+}
+
+/// The phases of an AC supply
+#[derive(
+    Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+enum AcPhases {
+    #[default]
+    Single,
+    Three,
+}
+
+impl AcPhases {
+    fn count(self) -> u32 {
+        match self {
+            AcPhases::Single => 1,
+            AcPhases::Three => 3,
+        }
+    }
+
+    /// nominal voltage per phase, 240V split phase or 230V for each of the three phases
+    fn volts(self) -> f64 {
+        match self {
+            AcPhases::Single => 240.0,
+            AcPhases::Three => 230.0,
+        }
+    }
+
+    /// power a circuit rated for `amps` per phase can supply
+    fn power(self, amps: f64) -> Power {
+        Power::from_kw(amps * self.volts() * self.count() as f64 / 1000.0)
+    }
+
+    /// amps per phase drawn at the given power
+    fn amps(self, power: Power) -> f64 {
+        power.watts as f64 / (self.volts() * self.count() as f64)
+    }
+}
+
+impl Display for AcPhases {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AcPhases::Single => write!(f, "single phase"),
+            AcPhases::Three => write!(f, "three phase"),
+        }
+    }
+}
+
+/// The AC to DC charger built into a vehicle, which limits how fast it can charge on AC
+#[derive(Clone, Copy, PartialEq, PartialOrd, Default)]
+struct OnboardCharger {
+    max_power: Power,
+    phases: AcPhases,
+}
+
+impl OnboardCharger {
+    const fn single_phase(kilowatts: f64) -> Self {
+        Self {
+            max_power: Power::from_kw(kilowatts),
+            phases: AcPhases::Single,
+        }
+    }
+
+    /// power the vehicle can take from a plug with the given phases rated for `plug_max`
+    fn ac_limit(&self, phases: AcPhases, plug_max: Power) -> Power {
+        let used = self.phases.count().min(phases.count()) as f64;
+        // each phase has its own share of the plug and of the onboard charger,
+        // so a single phase vehicle on a three phase plug only gets a third of it
+        let plug = plug_max * (used / phases.count() as f64);
+        let onboard = self.max_power * (used / self.phases.count() as f64);
+        plug.min(onboard)
+    }
 }
 
+/// Vehicles keep requesting at least this much power until they unplug
+const MIN_POWER_REQUEST: Power = Power::from_kw(5.0);
+
 /// Contains the specification for a vehicle
 #[derive(Clone, PartialEq, PartialOrd, Default)]
 struct VehicleSpec {
+    /// unique, scenarios refer to vehicles by name
     name: &'static str,
+    model_year: u16,
+    trim: &'static str,
     battery_max: Energy,
     charge_curve: ChargeCurve,
     epa_miles: f64,
+    /// nominal architecture voltage, 400 or 800 volt class
+    pack_voltage: u16,
+    onboard_charger: OnboardCharger,
+    /// where the range and capacity figures came from
+    source: &'static str,
+    notes: &'static str,
 }
 
 impl Eq for VehicleSpec {}
 
+impl VehicleSpec {
+    /// The site `source` is on, short enough to show in a list
+    fn source_site(&self) -> &'static str {
+        let source = self.source;
+        let host = source.split_once("://").map_or(source, |(_, rest)| rest);
+        host.split('/')
+            .next()
+            .unwrap_or(host)
+            .trim_start_matches("www.")
+    }
+
+    /// miles of EPA rated range the given energy is worth
+    fn epa_miles_for(&self, energy: Energy) -> f64 {
+        if self.battery_max.watt_hours <= 0.0 {
+            return 0.0;
+        }
+        energy.watt_hours / self.battery_max.watt_hours * self.epa_miles
+    }
+
+    /// energy needed to add the given miles of EPA rated range
+    fn energy_for_epa_miles(&self, miles: f64) -> Energy {
+        if self.epa_miles <= 0.0 {
+            return Energy::default();
+        }
+        Energy {
+            watt_hours: miles / self.epa_miles * self.battery_max.watt_hours,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq)]
 struct SpecKey {
     name: Cow<'static, str>,
@@ -416,7 +754,7 @@ impl TryFrom<&SpecKey> for &'static VehicleSpec {
 
     fn try_from(value: &SpecKey) -> Result<Self, Self::Error> {
         VEHICLES
-            .into_iter()
+            .iter()
             .find(|v| v.name == value.name)
             .ok_or_else(|| VehicleLookupError::NotFound(value.name.clone()))
     }
@@ -435,6 +773,10 @@ struct Vehicle {
     spec: SpecKey,
     current_charge: Energy,
     unplug_at: Energy,
+    /// the charge vehicle to grid may discharge down to, `None` if the vehicle doesn't take part
+    v2g_reserve: Option<Energy>,
+    /// minutes after midnight it arrives each day, `None` arrives when the day starts
+    arrival: Option<u16>,
 }
 
 impl Vehicle {
@@ -443,17 +785,25 @@ impl Vehicle {
             spec: spec.into(),
             current_charge: state_of_charge,
             unplug_at,
+            v2g_reserve: None,
+            arrival: None,
         }
     }
 
     fn spec_details(&self) -> &'static VehicleSpec {
         static DEFAULT: VehicleSpec = VehicleSpec {
             name: "",
+            model_year: 0,
+            trim: "",
             battery_max: Energy::from_kwh(0.0),
             charge_curve: ChargeCurve {
                 data_points: Cow::Borrowed(&[]),
             },
             epa_miles: SoftF64(0.0).to_f64(),
+            pack_voltage: 0,
+            onboard_charger: OnboardCharger::single_phase(0.0),
+            source: "",
+            notes: "",
         };
         self.spec.borrow().try_into().ok().unwrap_or(&DEFAULT)
     }
@@ -475,6 +825,36 @@ impl Vehicle {
         PercentFull::new(soc)
     }
 
+    fn set_soc(&mut self, soc: PercentFull) {
+        self.current_charge = soc * self.spec_details().battery_max;
+    }
+
+    fn set_unplug_soc(&mut self, soc: PercentFull) {
+        self.unplug_at = soc * self.spec_details().battery_max;
+    }
+
+    fn v2g_reserve_soc(&self) -> Option<PercentFull> {
+        let reserve = self.v2g_reserve?;
+        let battery_max = self.spec_details().battery_max.watt_hours;
+        if battery_max <= 0.0 {
+            return Some(PercentFull(0));
+        }
+        Some(PercentFull::new(reserve.watt_hours / battery_max * 100.0))
+    }
+
+    fn set_v2g_reserve(&mut self, soc: Option<PercentFull>) {
+        self.v2g_reserve = soc.map(|soc| soc * self.spec_details().battery_max);
+    }
+
+    /// Swaps the model, keeping the same start, unplug and reserve state of charge
+    fn set_spec(&mut self, spec: &'static VehicleSpec) {
+        let (soc, unplug_at, reserve) = (self.soc(), self.unplug_at_soc(), self.v2g_reserve_soc());
+        self.spec = spec.into();
+        self.set_soc(soc);
+        self.set_unplug_soc(unplug_at);
+        self.set_v2g_reserve(reserve);
+    }
+
     /// Returns the next charge request- None if wants to unplug
     fn get_next_power_request(&mut self, charger_available: Power) -> Option<Power> {
         if self.current_charge >= self.unplug_at {
@@ -486,151 +866,20 @@ impl Vehicle {
                 .charge_curve
                 .power_at(soc)
                 .min(charger_available)
-                .max(Power::from_kw(5.0)),
+                .max(MIN_POWER_REQUEST),
         )
     }
 
-    // Charges the battery and returns the amount of energy added
+    // Charges the battery, or discharges it with negative power, and returns the energy added
     fn charge(&mut self, power: Power, dt: Duration) -> Energy {
         let added_energy = power * dt;
         self.current_charge += added_energy;
-        assert!(power.as_kw().is_sign_positive());
         added_energy
     }
 }
 
-static VEHICLES: &'static [VehicleSpec] = &[
-    VehicleSpec {
-        name: "KIA EV6 Long Range AWD",
-        battery_max: Energy::from_kwh(77.4),
-        charge_curve: ChargeCurve {
-            data_points: Cow::Borrowed(&[
-                // TODO: refine this curve
-                CurvePoint::new(0.00, 20.0),
-                CurvePoint::new(2.0, 220.0),
-                CurvePoint::new(45.0, 238.0),
-                CurvePoint::new(50.0, 198.0),
-                CurvePoint::new(55.0, 198.0),
-                CurvePoint::new(60.0, 100.0),
-                CurvePoint::new(70.00, 198.0),
-                CurvePoint::new(77.0, 75.0),
-                CurvePoint::new(78.0, 168.0),
-                CurvePoint::new(82.0, 10.0),
-                CurvePoint::new(83.0, 125.0),
-                CurvePoint::new(100.0, 20.0),
-            ]),
-        },
-        epa_miles: 270.0,
-    },
-    VehicleSpec {
-        name: "Lucid Air Grand Touring",
-        battery_max: Energy::from_kwh(112.0),
-        charge_curve: ChargeCurve {
-            data_points: Cow::Borrowed(&[
-                CurvePoint::new(0.00, 200.0),
-                CurvePoint::new(2.0, 280.0),
-                CurvePoint::new(10.0, 300.0),
-                CurvePoint::new(20.0, 290.0),
-                CurvePoint::new(80.0, 100.0),
-                CurvePoint::new(100.0, 10.0),
-            ]),
-        },
-        epa_miles: 510.0,
-    },
-    VehicleSpec {
-        name: "Porsche Taycan 2022",
-        battery_max: Energy::from_kwh(93.4),
-        charge_curve: ChargeCurve {
-            data_points: Cow::Borrowed(&[
-                CurvePoint::new(0.00, 260.0),
-                CurvePoint::new(21.0, 265.0),
-                CurvePoint::new(22.0, 250.0),
-                CurvePoint::new(28.0, 200.0),
-                CurvePoint::new(80.0, 100.0),
-                CurvePoint::new(100.0, 10.0),
-            ]),
-        },
-        epa_miles: 510.0,
-    },
-    VehicleSpec {
-        name: "Chevy Bolt 2022",
-        battery_max: Energy::from_kwh(65.0),
-        charge_curve: ChargeCurve {
-            data_points: Cow::Borrowed(&[
-                CurvePoint::new(0.0, 55.0),
-                CurvePoint::new(50.0, 55.0),
-                CurvePoint::new(70.0, 33.0),
-                CurvePoint::new(93.0, 26.0),
-                CurvePoint::new(100.0, 5.0),
-            ]),
-        },
-        epa_miles: 259.0,
-    },
-    VehicleSpec {
-        name: "Tesla Model 3 LR AWD 2021",
-        battery_max: Energy::from_kwh(82.0),
-        charge_curve: ChargeCurve {
-            data_points: Cow::Borrowed(&[
-                CurvePoint::new(0.0, 80.0),
-                CurvePoint::new(8.0, 225.0),
-                CurvePoint::new(11.0, 250.0),
-                CurvePoint::new(20.0, 250.0),
-                CurvePoint::new(24.0, 250.0),
-                CurvePoint::new(26.0, 200.0),
-                CurvePoint::new(34.0, 200.0),
-                CurvePoint::new(36.0, 150.0),
-                CurvePoint::new(66.0, 120.0),
-                CurvePoint::new(69.0, 120.0),
-                CurvePoint::new(80.0, 60.0),
-                CurvePoint::new(100.0, 20.0),
-            ]),
-        },
-        epa_miles: 358.0,
-    },
-    VehicleSpec {
-        name: "Rivian R1S Standard Pack",
-        battery_max: Energy::from_kwh(105.0),
-        charge_curve: ChargeCurve {
-            data_points: Cow::Borrowed(&[
-                // There might be a revised charge curve for 2023 but I can't find a full sample
-                CurvePoint::new(0.0, 100.0),
-                CurvePoint::new(1.0, 190.0),
-                CurvePoint::new(47.0, 230.0),
-                CurvePoint::new(50.0, 173.0),
-                CurvePoint::new(55.0, 147.0),
-                CurvePoint::new(57.0, 175.0),
-                CurvePoint::new(60.0, 145.0),
-                CurvePoint::new(70.0, 75.0),
-                CurvePoint::new(80.0, 75.0),
-                CurvePoint::new(100.0, 15.0),
-            ]),
-        },
-        epa_miles: 352.0,
-    },
-    VehicleSpec {
-        name: "GMC Hummer EV Pickup",
-        battery_max: Energy::from_kwh(212.0),
-        charge_curve: ChargeCurve {
-            data_points: Cow::Borrowed(&[
-                // There might be a revised charge curve for 2023 but I can't find a full sample
-                CurvePoint::new(0.0, 150.0),
-                CurvePoint::new(1.0, 335.0),
-                CurvePoint::new(2.0, 338.0),
-                CurvePoint::new(34.0, 345.0),
-                CurvePoint::new(36.0, 306.0),
-                CurvePoint::new(40.0, 294.0),
-                CurvePoint::new(50.0, 257.0),
-                CurvePoint::new(62.0, 255.0),
-                CurvePoint::new(70.0, 115.0),
-                CurvePoint::new(80.0, 45.0),
-                CurvePoint::new(83.0, 17.0),
-                CurvePoint::new(90.0, 51.0),
-                CurvePoint::new(100.0, 15.0),
-            ]),
-        },
-        epa_miles: 352.0,
-    },
-];
+// `VEHICLES` and `VEHICLE_DATA_VERSION`, generated by build.rs from ev_charge_sim/vehicles.toml
+include!(concat!(env!("OUT_DIR"), "/vehicles.rs"));
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 enum LoadSharingStrategy {
@@ -658,19 +907,19 @@ enum LoadSharingStrategy {
 fn VehicleDropdown(
     #[prop(into)] current_vehicle: Signal<Option<&'static VehicleSpec>>,
     #[prop(into)] set_vehicle: SignalSetter<Option<&'static VehicleSpec>>,
+    /// limits the stats to what a charger of this power could deliver
+    #[prop(optional, into)]
+    charger_max: MaybeProp<Power>,
 ) -> impl IntoView {
-    let vehicles = RwSignal::new(
-        VEHICLES
-            .into_iter()
-            .map(|vehicle_spec| vehicle_spec)
-            .collect::<Vec<_>>(),
-    );
+    let vehicles = RwSignal::new(VEHICLES.iter().collect::<Vec<_>>());
     view! {
         <Select items=vehicles.into() as_label=move |v| v.name.to_string() choice=current_vehicle set_choice=set_vehicle let:vehicle>
             <div class="flex flex-row gap-2">
-                <span>{vehicle.battery_max.to_string()}</span>
-                <span>{vehicle.charge_curve.average_power().to_string()}" avg"</span>
-                <span>{vehicle.charge_curve.percent_to_percent(PercentFull::new(10.0), PercentFull::new(80.0)).map(|curve| curve.average_power().to_string())}" 10->80% avg"</span>
+                <span>{vehicle.model_year}" "{vehicle.trim}</span>
+                <span>{move || preferences().energy(vehicle.battery_max)}</span>
+                <span>{move || preferences().power(vehicle.charge_curve.limited(charger_max.get(), None).average_power())}" avg"</span>
+                <span>{move || vehicle.charge_curve.limited(charger_max.get(), None).percent_to_percent(PercentFull::new(10.0), PercentFull::new(80.0)).map(|curve| preferences().power(curve.average_power()))}" 10->80% avg"</span>
+                <span class="text-slate-500 dark:text-slate-400">"via "{vehicle.source_site()}</span>
             </div>
         </Select>
     }
@@ -682,34 +931,37 @@ fn VehicleChooser(
     set_vehicles: SignalSetter<VecDeque<Vehicle>>,
 ) -> impl IntoView {
     let (vehicle_spec, set_vehicle_spec) = signal::<Option<&'static VehicleSpec>>(None);
-    let specs = Memo::new(move |_| {
-        vehicle_spec()
-            .map(|spec: &VehicleSpec| spec.clone())
-            .unwrap_or_default()
-    });
+    let specs = Memo::new(move |_| vehicle_spec().cloned().unwrap_or_default());
     let (start_energy, set_start_energy) = signal(PercentFull::new(10.0));
     let (unplug_at, set_unplug_at) = signal(PercentFull::new(80.0));
-    let estimated_charge_time = move || {
-        (unplug_at() - start_energy()) * specs().battery_max
-            / specs()
-                .charge_curve
-                .percent_to_percent(start_energy(), unplug_at())
-                .map(|curve| curve.average_power())
-                .unwrap_or(Power::from_kw(0.0))
-    };
+    let (charger_max, set_charger_max) = signal(None::<Power>);
+    let (power_floor, set_power_floor) = signal(false);
+    let curve = Memo::new(move |_| {
+        specs()
+            .charge_curve
+            .limited(charger_max(), power_floor().then_some(MIN_POWER_REQUEST))
+    });
+    let estimated_charge_time =
+        move || curve().charge_time(specs().battery_max, start_energy(), unplug_at());
     view! {
         <div class="flex flex-col">
                 <h4 class="text-xl">"Add Vehicle:"</h4>
                 <div class="flex flex-col xl:flex-row gap-1">
-                    <VehicleDropdown current_vehicle=vehicle_spec set_vehicle=set_vehicle_spec />
+                    <VehicleDropdown current_vehicle=vehicle_spec set_vehicle=set_vehicle_spec charger_max />
                     <div class="flex flex-col" class:invisible=move || vehicle_spec.with(|spec| spec.is_none())>
-                        <span>"battery capacity: "{move || specs().battery_max.to_string()}</span>
-                        <span>"avg charge speed: "{move || specs().charge_curve.average_power().to_string()}</span>
-                        <span>"avg "{move || start_energy().to_string()}"->"{move || unplug_at().to_string()}" charge speed:"<span>{move || specs().charge_curve.percent_to_percent(start_energy(), unplug_at()).map(|curve| curve.average_power().to_string())}</span></span>
-                        <span>{move || format!("estimated charge time: {:.2} mins", (estimated_charge_time().as_secs_f64() / 60.0))}</span>
+                        <span>{move || format!("{} {}, {} V class", specs().model_year, specs().trim, specs().pack_voltage)}</span>
+                        <span>"battery capacity: "{move || preferences().energy(specs().battery_max)}</span>
+                        <span>"onboard AC charger: "{move || format!("{} {}", preferences().power(specs().onboard_charger.max_power), specs().onboard_charger.phases)}</span>
+                        <span>"avg charge speed: "{move || preferences().power(curve().average_power())}</span>
+                        <span>"avg "{move || preferences().percent(start_energy())}"->"{move || preferences().percent(unplug_at())}" charge speed:"<span>{move || curve().percent_to_percent(start_energy(), unplug_at()).map(|curve| preferences().power(curve.average_power()))}</span></span>
+                        <span>{move || format!("EPA range: {}, {}", preferences().distance(specs().epa_miles), preferences().efficiency(specs().epa_miles, specs().battery_max))}</span>
+                        <span>{move || format!("estimated charge time: {} mins", preferences().number(estimated_charge_time().as_secs_f64() / 60.0, 2))}</span>
+                        <span>{move || format!("range added: {}", preferences().distance(specs().epa_miles_for((unplug_at() - start_energy()) * specs().battery_max)))}</span>
+                        <span class="text-sm" class:hidden=move || specs().notes.is_empty()>{move || specs().notes}</span>
+                        <span class="text-sm">"source: "<a class="underline" href=move || specs().source target="_blank" rel="noopener">{move || specs().source_site()}</a>{format!(", vehicle data v{VEHICLE_DATA_VERSION}")}</span>
                     </div>
                     <div class:invisible=move || vehicle_spec.with(|spec| spec.is_none()) class="flex flex-col">
-                        <label for="battery-soc" class="block mb-2 text-sm font-medium text-slate-700 dark:text-slate-200">"Charge start battery%: "{move || start_energy().to_string()}" "{move || (start_energy() * specs().battery_max).to_string()}</label>
+                        <label for="battery-soc" class="block mb-2 text-sm font-medium text-slate-700 dark:text-slate-200">"Charge start battery%: "{move || preferences().percent(start_energy())}" "{move || preferences().energy(start_energy() * specs().battery_max)}</label>
                         <input id="battery-soc" type="range" class="w-full h-2 bg-amber-200 rounded-lg appearance-none cursor-pointer dark:bg-slate-600 accent-amber-500 dark:accent-teal-500" prop:value=move || start_energy().as_float().to_string() on:input=move |e| {
                             if let Ok(value) = event_target_value(&e).parse() {
                                 if unplug_at.get_untracked().as_float() < value {
@@ -720,7 +972,7 @@ fn VehicleChooser(
                         }/>
                     </div>
                     <div class:collapse=move || vehicle_spec.with(|spec| spec.is_none()) class="flex flex-col">
-                        <label for="battery-soc" class="block mb-2 text-sm font-medium text-slate-700 dark:text-slate-200">"Unplug at Battery SOC%: "{move || unplug_at().to_string()}" "{move || (unplug_at() * specs().battery_max).to_string()}</label>
+                        <label for="battery-soc" class="block mb-2 text-sm font-medium text-slate-700 dark:text-slate-200">"Unplug at Battery SOC%: "{move || preferences().percent(unplug_at())}" "{move || preferences().energy(unplug_at() * specs().battery_max)}</label>
                         <input id="battery-soc" type="range" class="w-full h-2 bg-amber-200 rounded-lg appearance-none cursor-pointer dark:bg-slate-600 accent-amber-500 dark:accent-teal-500" prop:value=move || unplug_at().as_float().to_string() on:input=move |e| {
                             if let Ok(value) = event_target_value(&e).parse() {
                                 if start_energy.get_untracked().as_float() > value {
@@ -730,6 +982,23 @@ fn VehicleChooser(
                             }
                         }/>
                     </div>
+                    <div class:collapse=move || vehicle_spec.with(|spec| spec.is_none()) class="flex flex-col gap-1">
+                        <label for="preview-charger-max" class="text-sm font-medium text-slate-700 dark:text-slate-200">"Charger max kW (blank for unlimited)"</label>
+                        <input id="preview-charger-max" class="dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-36 transition-colors"
+                            prop:value=move || charger_max().map(|max| max.as_kw().to_string()).unwrap_or_default()
+                            on:change=move |e| {
+                                let value = event_target_value(&e);
+                                if value.trim().is_empty() {
+                                    set_charger_max(None);
+                                } else if let Ok(value) = value.trim().parse::<f64>() {
+                                    set_charger_max(Some(Power::from_kw(value.max(1.0))));
+                                }
+                            } />
+                        <label class="flex flex-row gap-1 items-center text-sm">
+                            <input type="checkbox" class="accent-amber-500 dark:accent-teal-500" prop:checked=power_floor on:change=move |e| set_power_floor(event_target_checked(&e)) />
+                            {move || format!("never below {} like the simulation", preferences().power(MIN_POWER_REQUEST))}
+                        </label>
+                    </div>
                     <button class:collapse=move || vehicle_spec.with(|spec| spec.is_none()) class="bg-amber-500 dark:bg-teal-600 text-white p-2 border border-amber-600 dark:border-teal-500 hover:bg-amber-600 dark:hover:bg-teal-500 rounded-lg font-medium transition-colors"
                         on:click=move |_| {
                             if let Some(current) = vehicle_spec.get_untracked() {
@@ -742,162 +1011,434 @@ fn VehicleChooser(
                         "ADD +"
                     </button>
                 </div>
-                <ChargeCurve spec=vehicle_spec start_soc=start_energy end_soc=unplug_at />
+                <ChargeCurve spec=vehicle_spec limited_curve=curve start_soc=start_energy end_soc=unplug_at />
             </div>
     }
 }
 
+/// Moves the item at `from` so it ends up at index `to`
+fn move_item<T>(items: &mut VecDeque<T>, from: usize, to: usize) {
+    if let Some(item) = items.remove(from) {
+        items.insert(to.min(items.len()), item);
+    }
+}
+
+/// The items with `count` copies of the one at `index` right after it
+fn with_copies<'a, T, C>(items: impl IntoIterator<Item = &'a T>, index: usize, count: usize) -> C
+where
+    T: Clone + 'a,
+    C: FromIterator<T>,
+{
+    items
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, item)| std::iter::repeat_n(item, if i == index { count + 1 } else { 1 }))
+        .cloned()
+        .collect()
+}
+
 #[component]
 fn VehicleList(
     #[prop(into)] vehicles: Signal<VecDeque<Vehicle>>,
     set_vehicles: SignalSetter<VecDeque<Vehicle>>,
+    set_vehicles_intermediate: SignalSetter<VecDeque<Vehicle>>,
 ) -> impl IntoView {
+    let (editing, set_editing) = signal(None::<usize>);
+    let (dragging, set_dragging) = signal(None::<usize>);
+    let update = move |f: &dyn Fn(&mut VecDeque<Vehicle>)| {
+        let mut vehicles = vehicles.get_untracked();
+        f(&mut vehicles);
+        set_editing(None);
+        set_vehicles(vehicles);
+    };
+    let button = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 px-1.5 border border-slate-300 dark:border-slate-500 transition-colors";
     view! {
-        <div class="grid grid-cols-4 gap-1" class:collapse=move || vehicles.with(|v| v.is_empty())>
-            <h2 class="text-xl col-span-4">"Vehicles:"</h2>
-
+        <div class="flex flex-col gap-1" class:collapse=move || vehicles.with(|v| v.is_empty())>
+            <h2 class="text-xl">"Vehicles:"</h2>
+            <span class="text-sm">"Vehicles without an arrival time arrive in this order when the day starts, drag to reorder."</span>
             <For each={move || vehicles().into_iter().enumerate()}
-                key=|(i, v)| (*i, v.spec_details().name)
+                key=|(i, v)| (*i, v.spec_details().name, v.current_charge.watt_hours as i64, v.unplug_at.watt_hours as i64, v.v2g_reserve.map(|r| r.watt_hours as i64), v.arrival)
                 let:vehicle>
-                <div class="col-span-2">{vehicle.1.spec.name.clone()}</div>
-                <div>{vehicle.1.soc().to_string()}" -> "{vehicle.1.unplug_at_soc().to_string()}</div>
-                <button class="hover:bg-red-500 bg-red-600 rounded-lg w-10 border border-red-700 text-white transition-colors" on:click=move |_| { let mut vehicles = vehicles(); vehicles.remove(vehicle.0); set_vehicles(vehicles); }>"X"</button>
+                <div class="flex flex-row gap-1 items-center rounded-lg p-1 cursor-grab"
+                    class:bg-amber-100=move || editing() == Some(vehicle.0)
+                    class:dark:bg-slate-700=move || editing() == Some(vehicle.0)
+                    draggable="true"
+                    on:dragstart=move |_| set_dragging(Some(vehicle.0))
+                    on:dragend=move |_| set_dragging(None)
+                    on:dragover=move |e| e.prevent_default()
+                    on:drop=move |e| {
+                        e.prevent_default();
+                        if let Some(from) = dragging.get_untracked() {
+                            set_dragging(None);
+                            if from != vehicle.0 {
+                                update(&|v| move_item(v, from, vehicle.0));
+                            }
+                        }
+                    }>
+                    <span class="select-none" aria-hidden="true">"⠿"</span>
+                    <div class="grow">"#"{vehicle.0 + 1}" "{vehicle.1.spec.name.clone()}</div>
+                    <div>{let (soc, unplug_at) = (vehicle.1.soc(), vehicle.1.unplug_at_soc()); move || format!("{} -> {}", preferences().percent(soc), preferences().percent(unplug_at))}</div>
+                    {vehicle.1.v2g_reserve_soc().map(|reserve| view! { <div class="text-sm" title="Vehicle to grid reserve">"V2G ≥ "{move || preferences().percent(reserve)}</div> })}
+                    {vehicle.1.arrival.map(|arrival| view! { <div class="text-sm" title="Arrives each day at">"@ "{move || preferences().time_of_day(arrival)}</div> })}
+                    <button class=button title="Move up" disabled=vehicle.0 == 0 on:click=move |_| update(&|v| move_item(v, vehicle.0, vehicle.0.saturating_sub(1)))>"↑"</button>
+                    <button class=button title="Move down" on:click=move |_| update(&|v| move_item(v, vehicle.0, vehicle.0 + 1))>"↓"</button>
+                    <button class=button on:click=move |_| set_editing(Some(vehicle.0))>"Edit"</button>
+                    <button class=button title="Duplicate" on:click=move |_| update(&|v| *v = with_copies(v.iter(), vehicle.0, 1))>"+1"</button>
+                    <button class=button title="Duplicate 10 times" on:click=move |_| update(&|v| *v = with_copies(v.iter(), vehicle.0, 10))>"+10"</button>
+                    <button class="hover:bg-red-500 bg-red-600 rounded-lg w-10 border border-red-700 text-white transition-colors" on:click=move |_| update(&|v| { v.remove(vehicle.0); })>"X"</button>
+                </div>
             </For>
+            {move || editing().map(|index| view! {
+                <VehicleEditor index vehicles set_vehicles set_vehicles_intermediate on_close=move || set_editing(None) />
+            })}
+        </div>
+    }
+}
 
+#[component]
+fn VehicleEditor(
+    index: usize,
+    #[prop(into)] vehicles: Signal<VecDeque<Vehicle>>,
+    set_vehicles: SignalSetter<VecDeque<Vehicle>>,
+    set_vehicles_intermediate: SignalSetter<VecDeque<Vehicle>>,
+    on_close: impl Fn() + Send + Sync + 'static,
+) -> impl IntoView {
+    let vehicle = Memo::new(move |_| vehicles.with(|v| v.get(index).cloned()));
+    let update = move |setter: SignalSetter<VecDeque<Vehicle>>, f: &dyn Fn(&mut Vehicle)| {
+        let mut vehicles = vehicles.get_untracked();
+        if let Some(vehicle) = vehicles.get_mut(index) {
+            f(vehicle);
+            setter.set(vehicles);
+        }
+    };
+    let spec = Signal::derive(move || vehicle.with(|v| v.as_ref().map(|v| v.spec_details())));
+    let set_spec = SignalSetter::map(move |spec: Option<&'static VehicleSpec>| {
+        if let Some(spec) = spec {
+            update(set_vehicles, &|v| v.set_spec(spec));
+        }
+    });
+    let start_soc =
+        move || vehicle.with(|v| v.as_ref().map(|v| v.soc().as_float()).unwrap_or_default());
+    let unplug_soc = move || {
+        vehicle.with(|v| {
+            v.as_ref()
+                .map(|v| v.unplug_at_soc().as_float())
+                .unwrap_or_default()
+        })
+    };
+    let set_start_soc = move |setter, value: f64| {
+        update(setter, &|v| {
+            if v.unplug_at_soc().as_float() < value {
+                v.set_unplug_soc(PercentFull::new((value + 5.0).min(100.0)));
+            }
+            v.set_soc(PercentFull::new(value.min(95.0)));
+        });
+    };
+    let set_unplug_soc = move |setter, value: f64| {
+        update(setter, &|v| {
+            if v.soc().as_float() > value {
+                v.set_soc(PercentFull::new((value - 5.0).max(0.0)));
+            }
+            v.set_unplug_soc(PercentFull::new(value.max(5.0)));
+        });
+    };
+    let reserve_soc = move || vehicle.with(|v| v.as_ref().and_then(|v| v.v2g_reserve_soc()));
+    let set_reserve_soc = move |setter, value: f64| {
+        update(setter, &|v| {
+            v.set_v2g_reserve(Some(PercentFull::new(value)))
+        });
+    };
+    let arrival = move || vehicle.with(|v| v.as_ref().and_then(|v| v.arrival));
+    let slider = "w-full h-2 bg-amber-200 rounded-lg appearance-none cursor-pointer dark:bg-slate-600 accent-amber-500 dark:accent-teal-500";
+    view! {
+        <div class="flex flex-col gap-1 p-2 rounded-lg bg-white dark:bg-slate-800 border-l-4 border-amber-400 dark:border-teal-500 shadow-sm">
+            <div class="flex flex-row gap-2 items-center">
+                <h4 class="text-lg grow">"Editing vehicle #"{index + 1}</h4>
+                <button class="rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 px-1.5 border border-slate-300 dark:border-slate-500 transition-colors" on:click=move |_| on_close()>"Done"</button>
+            </div>
+            <VehicleDropdown current_vehicle=spec set_vehicle=set_spec />
+            <label for="edit-battery-soc" class="block text-sm font-medium text-slate-700 dark:text-slate-200">"Charge start battery%: "{move || preferences().percent(PercentFull::new(start_soc()))}</label>
+            <input id="edit-battery-soc" type="range" class=slider prop:value=move || start_soc().to_string()
+                on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_start_soc(set_vehicles_intermediate, value) }
+                on:change=move |e| if let Ok(value) = event_target_value(&e).parse() { set_start_soc(set_vehicles, value) } />
+            <label for="edit-unplug-soc" class="block text-sm font-medium text-slate-700 dark:text-slate-200">"Unplug at Battery SOC%: "{move || preferences().percent(PercentFull::new(unplug_soc()))}</label>
+            <input id="edit-unplug-soc" type="range" class=slider prop:value=move || unplug_soc().to_string()
+                on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_unplug_soc(set_vehicles_intermediate, value) }
+                on:change=move |e| if let Ok(value) = event_target_value(&e).parse() { set_unplug_soc(set_vehicles, value) } />
+            <label class="flex flex-row gap-1 items-center text-sm">
+                <input type="checkbox" class="accent-amber-500 dark:accent-teal-500" prop:checked=move || reserve_soc().is_some()
+                    on:change=move |e| {
+                        let reserve = event_target_checked(&e).then(|| PercentFull::new(30.0));
+                        update(set_vehicles, &|v| v.set_v2g_reserve(reserve));
+                    } />
+                "Vehicle to grid, can discharge on bidirectional chargers"
+            </label>
+            <label for="edit-v2g-reserve" class="block text-sm font-medium text-slate-700 dark:text-slate-200" class:collapse=move || reserve_soc().is_none()>"Keep at least battery%: "{move || reserve_soc().map(|soc| preferences().percent(soc))}</label>
+            <input id="edit-v2g-reserve" type="range" class=slider class:collapse=move || reserve_soc().is_none() prop:value=move || reserve_soc().map(|soc| soc.as_float().to_string())
+                on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_reserve_soc(set_vehicles_intermediate, value) }
+                on:change=move |e| if let Ok(value) = event_target_value(&e).parse() { set_reserve_soc(set_vehicles, value) } />
+            <label class="flex flex-row gap-1 items-center text-sm">
+                "Arrives each day at (blank for when the day starts)"
+                <input type="time" class="dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 transition-colors"
+                    prop:value=move || arrival().map(format_minute_of_day).unwrap_or_default()
+                    on:change=move |e| {
+                        let arrival = parse_minute_of_day(&event_target_value(&e));
+                        update(set_vehicles, &|v| v.arrival = arrival);
+                    } />
+            </label>
         </div>
     }
+    .into_any()
 }
 
+/// Buttons and inputs for picking a [`LoadSharingStrategy`] and its parameters.
+/// Typing in the inputs writes to `set_strategy_intermediate`, finishing an edit writes to `set_strategy`.
 #[component]
-fn ChargerBuilder(
-    #[prop(into)] chargers: Signal<Vec<Charger>>,
-    set_chargers: SignalSetter<Vec<Charger>>,
+fn LoadSharingEditor(
+    #[prop(into)] strategy: Signal<LoadSharingStrategy>,
+    set_strategy: SignalSetter<LoadSharingStrategy>,
+    set_strategy_intermediate: SignalSetter<LoadSharingStrategy>,
+    #[prop(into)] grid_connection: Signal<Power>,
 ) -> impl IntoView {
-    let (grid_connection, set_grid_connection) = signal(Power::from_kw(600.0));
-    let load_share = RwSignal::new(LoadSharingStrategy::None);
-    let number_of_plugs = Memo::new(move |_| match load_share.get() {
+    let update = move |setter: SignalSetter<LoadSharingStrategy>,
+                       f: &dyn Fn(&mut LoadSharingStrategy)| {
+        let mut strategy = strategy.get_untracked();
+        f(&mut strategy);
+        setter.set(strategy);
+    };
+    let number_of_plugs = Memo::new(move |_| match strategy.get() {
         LoadSharingStrategy::None => None,
         LoadSharingStrategy::Paired { number_of_plugs } => Some(number_of_plugs),
         LoadSharingStrategy::Split { number_of_plugs } => Some(number_of_plugs),
-        LoadSharingStrategy::Granular { number_of_plugs, .. } => Some(number_of_plugs),
+        LoadSharingStrategy::Granular {
+            number_of_plugs, ..
+        } => Some(number_of_plugs),
     });
-    let set_number_of_plugs = move |plugs: u32| {
-        load_share.update(|strategy| match strategy {
+    let set_number_of_plugs = move |setter, plugs: u32| {
+        update(setter, &|strategy| match strategy {
             LoadSharingStrategy::None => {}
             LoadSharingStrategy::Paired { number_of_plugs } => *number_of_plugs = plugs,
             LoadSharingStrategy::Split { number_of_plugs } => *number_of_plugs = plugs,
-            LoadSharingStrategy::Granular { number_of_plugs, .. } => *number_of_plugs = plugs,
+            LoadSharingStrategy::Granular {
+                number_of_plugs, ..
+            } => *number_of_plugs = plugs,
         });
     };
-    let power_step = Memo::new(move |_| match load_share.get() {
+    let power_step = Memo::new(move |_| match strategy.get() {
         LoadSharingStrategy::Granular { power_step, .. } => Some(power_step),
         _ => None,
     });
-    let set_power_step = move |step: Power| {
-        load_share.update(|strategy| match strategy {
-            LoadSharingStrategy::Granular { power_step, .. } => *power_step = step,
-            _ => {}
+    let set_power_step = move |setter, step: Power| {
+        update(setter, &|strategy| {
+            if let LoadSharingStrategy::Granular { power_step, .. } = strategy {
+                *power_step = step;
+            }
         });
     };
-    let max_per_plug = Memo::new(move |_| match load_share.get() {
+    let max_per_plug = Memo::new(move |_| match strategy.get() {
         LoadSharingStrategy::Granular { max_per_plug, .. } => Some(max_per_plug),
         _ => None,
     });
-    let set_max_per_plug = move |per_plug: Power| {
-        load_share.update(|strategy| match strategy {
-            LoadSharingStrategy::Granular { max_per_plug, .. } => *max_per_plug = per_plug,
-            _ => {}
+    let set_max_per_plug = move |setter, per_plug: Power| {
+        update(setter, &|strategy| {
+            if let LoadSharingStrategy::Granular { max_per_plug, .. } = strategy {
+                *max_per_plug = per_plug;
+            }
         });
     };
     let btn_active =
         "rounded-lg bg-amber-100 dark:bg-teal-900 p-1.5 border border-amber-400 dark:border-teal-500 font-medium";
     let btn_inactive = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors";
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-36 shrink transition-colors";
+    view! {
+        <div class="col-span-2 gap-1">
+            "Load sharing strategy:"
+            <button class=move || if matches!(strategy(), LoadSharingStrategy::None) { btn_active } else { btn_inactive  } on:click=move |_| set_strategy.set(LoadSharingStrategy::None)>"None"</button>
+            <button class=move || if matches!(strategy(), LoadSharingStrategy::Paired { .. }) { btn_active } else { btn_inactive  } on:click=move |_| set_strategy.set(LoadSharingStrategy::Paired {
+                number_of_plugs: 2
+            })>"Even"</button>
+            <button class=move || if matches!(strategy(), LoadSharingStrategy::Split { .. }) { btn_active } else { btn_inactive  } on:click=move |_| set_strategy.set(LoadSharingStrategy::Split {
+                number_of_plugs: 2
+            })>"Split"</button>
+            <button class=move || if matches!(strategy(), LoadSharingStrategy::Granular { .. }) { btn_active } else { btn_inactive  }  on:click=move |_| set_strategy.set(LoadSharingStrategy::Granular {
+                number_of_plugs: 8,
+                power_step: Power::from_kw(25.0),
+                max_per_plug: Power::from_kw(400.0),
+            })>"Granular"</button>
+            <div class="grid grid-cols-2 gap-1" class:collapse=move || number_of_plugs().is_none()>
+                <span>
+                    "Number of plugs:"
+                    {move || number_of_plugs().unwrap_or(1)}
+                </span>
+                <input class=input prop:value=move || number_of_plugs().unwrap_or_default()
+                    on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_number_of_plugs(set_strategy_intermediate, value) }
+                    on:change=move |e| if let Ok(value) = event_target_value(&e).parse() { set_number_of_plugs(set_strategy, value) } />
+                "Avg power per plug: " {move || preferences().power(grid_connection() / (if number_of_plugs().unwrap_or(1) == 0 { 1 } else { number_of_plugs().unwrap_or(1) }) as i32)}
+            </div>
+            <div class="grid grid-cols-2 gap-1" class:collapse=move || !matches!(strategy(), LoadSharingStrategy::Granular { .. })>
+                <span>
+                    "Power step:"
+                    {move || preferences().power(power_step().unwrap_or(Power::from_kw(1.0)))}
+                </span>
+                <input class=input prop:value=move || power_step().unwrap_or_default().as_kw()
+                    on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_power_step(set_strategy_intermediate, Power::from_kw(value)) }
+                    on:change=move |e| if let Ok(value) = event_target_value(&e).parse() { set_power_step(set_strategy, Power::from_kw(value)) } />
+                <span>
+                    "Max per plug:"
+                    {move || preferences().power(max_per_plug().unwrap_or(Power::from_kw(1.0)))}
+                </span>
+                <input class=input prop:value=move || max_per_plug().unwrap_or_default().as_kw()
+                    on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_max_per_plug(set_strategy_intermediate, Power::from_kw(value)) }
+                    on:change=move |e| if let Ok(value) = event_target_value(&e).parse() { set_max_per_plug(set_strategy, Power::from_kw(value)) } />
+            </div>
+        </div>
+    }
+}
+
+/// Picks between DC and AC chargers and edits the grid connection,
+/// which is entered as the circuit's amps per phase for AC
+#[component]
+fn ChargerSupplyEditor(
+    #[prop(into)] kind: Signal<ChargerKind>,
+    #[prop(into)] grid_connection: Signal<Power>,
+    /// switches the kind along with a grid connection that suits it
+    set_supply: SignalSetter<(ChargerKind, Power)>,
+    set_grid_connection: SignalSetter<Power>,
+    set_grid_connection_intermediate: SignalSetter<Power>,
+) -> impl IntoView {
+    let btn_active =
+        "rounded-lg bg-amber-100 dark:bg-teal-900 p-1.5 border border-amber-400 dark:border-teal-500 font-medium";
+    let btn_inactive = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors";
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-36 transition-colors";
+    let choose = move |next: ChargerKind| {
+        let power = match (kind.get_untracked(), next) {
+            (ChargerKind::Dc, ChargerKind::Dc)
+            | (ChargerKind::Ac { .. }, ChargerKind::Ac { .. }) => grid_connection.get_untracked(),
+            (_, ChargerKind::Dc) => Power::from_kw(600.0),
+            (_, ChargerKind::Ac { phases }) => phases.power(80.0),
+        };
+        set_supply.set((next, power));
+    };
+    let options = [
+        ChargerKind::Dc,
+        ChargerKind::Ac {
+            phases: AcPhases::Single,
+        },
+        ChargerKind::Ac {
+            phases: AcPhases::Three,
+        },
+    ];
+    // the value typed into the input, kw for DC and amps for AC
+    let to_input = move |power: Power| match kind() {
+        ChargerKind::Dc => power.as_kw(),
+        ChargerKind::Ac { phases } => phases.amps(power).round(),
+    };
+    let from_input = move |value: f64| match kind.get_untracked() {
+        ChargerKind::Dc => Power::from_kw(value),
+        ChargerKind::Ac { phases } => phases.power(value),
+    };
+    view! {
+        <div class="col-span-2 flex flex-row flex-wrap gap-1 items-center">
+            "Charger type:"
+            {options.into_iter().map(|option| view! {
+                <button class=move || if kind() == option { btn_active } else { btn_inactive } on:click=move |_| choose(option)>{option.to_string()}</button>
+            }).collect_view()}
+        </div>
+        <div>
+            {move || match kind() {
+                ChargerKind::Dc => format!("Grid Connection: {}", preferences().power(grid_connection())),
+                ChargerKind::Ac { phases } => format!("Circuit amps per phase: {:.0}A ({})", phases.amps(grid_connection()), preferences().power(grid_connection())),
+            }}
+        </div>
+        <input class=input prop:value=move || to_input(grid_connection())
+            on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_grid_connection_intermediate.set(from_input(value)) }
+            on:change=move |e| if let Ok(value) = event_target_value(&e).parse() { set_grid_connection.set(from_input(value)) } />
+    }
+}
+
+#[component]
+fn ChargerBuilder(
+    #[prop(into)] chargers: Signal<Vec<Charger>>,
+    set_chargers: SignalSetter<Vec<Charger>>,
+) -> impl IntoView {
+    let (grid_connection, set_grid_connection) = signal(Power::from_kw(600.0));
+    let (kind, set_kind) = signal(ChargerKind::Dc);
+    let (bidirectional, set_bidirectional) = signal(false);
+    let (preset, set_preset) = signal(None::<&'static ChargerPreset>);
+    let load_share = RwSignal::new(LoadSharingStrategy::None);
+    let set_load_share = SignalSetter::map(move |strategy| load_share.set(strategy));
+    let set_grid_connection = SignalSetter::from(set_grid_connection);
+    let set_supply = SignalSetter::map(move |(kind, power)| {
+        set_kind(kind);
+        set_grid_connection.set(power);
+    });
+    // picking a preset fills in everything below, which can then be tweaked
+    let pick_preset = SignalSetter::map(move |picked: Option<&'static ChargerPreset>| {
+        if let Some(picked) = picked {
+            let charger = picked.charger();
+            set_kind(charger.kind);
+            set_grid_connection.set(charger.grid_connection);
+            load_share.set(charger.strategy);
+            set_bidirectional(charger.bidirectional);
+        }
+        set_preset(picked);
+    });
     view! {
         <div class="flex flex-col">
                 <h4 class="text-xl">"Add Charger: "</h4>
+                <div class="flex flex-row flex-wrap gap-2 items-center">
+                    "Start from hardware:"
+                    <ChargerPresetDropdown current_preset=preset set_preset=pick_preset />
+                </div>
                 <div class="grid grid-cols-2">
-                    <div>
-                        "Grid Connection: "{move || grid_connection().to_string()}
-                    </div>
-                    <div>
-                        <input class="dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-36 transition-colors" value=grid_connection.get_untracked().as_kw() on:input=move |e| {
-                            if let Ok(kwh) = event_target_value(&e).parse::<f64>() {
-                                set_grid_connection(Power::from_kw(kwh));
-                            }
-                        } />
-                    </div>
-                    <div class="col-span-2 gap-1">
-                        "Load sharing strategy:"
-                        <button class=move || if matches!(load_share(), LoadSharingStrategy::None) { btn_active } else { btn_inactive  } on:click=move |_| load_share.set(LoadSharingStrategy::None)>"None"</button>
-                        <button class=move || if matches!(load_share(), LoadSharingStrategy::Paired { .. }) { btn_active } else { btn_inactive  } on:click=move |_| load_share.set(LoadSharingStrategy::Paired {
-                            number_of_plugs: 2
-                        })>"Even"</button>
-                        <button class=move || if matches!(load_share(), LoadSharingStrategy::Split { .. }) { btn_active } else { btn_inactive  } on:click=move |_| load_share.set(LoadSharingStrategy::Split {
-                            number_of_plugs: 2
-                        })>"Split"</button>
-                        <button class=move || if matches!(load_share(), LoadSharingStrategy::Granular { .. }) { btn_active } else { btn_inactive  }  on:click=move |_| load_share.set(LoadSharingStrategy::Granular {
-                            number_of_plugs: 8,
-                            power_step: Power::from_kw(25.0),
-                            max_per_plug: Power::from_kw(400.0),
-                        })>"Granular"</button>
-                        <div class="grid grid-cols-2 gap-1" class:collapse=move || number_of_plugs().is_none()>
-                            <span>
-                                "Number of plugs:"
-                                {move || number_of_plugs().unwrap_or(1)}
-                            </span>
-                            <input class="dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-36 shrink transition-colors" prop:value=move || number_of_plugs().unwrap_or_default() on:input=move |e| {
-                                if let Ok(value) = event_target_value(&e).parse() {
-                                    set_number_of_plugs(value);
-                                }
-                            }/>
-                            "Avg power per plug: " {move || (grid_connection() / (if number_of_plugs().unwrap_or(1) == 0 { 1 } else { number_of_plugs().unwrap_or(1) }) as i32).to_string()}
-                        </div>
-                        <div class="grid grid-cols-2 gap-1" class:collapse=move || !matches!(load_share(), LoadSharingStrategy::Granular { .. })>
-                            <span>
-                                "Power step:"
-                                {move || power_step().unwrap_or(Power::from_kw(1.0)).to_string()}
-                            </span>
-                            <input class="dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-36 shrink transition-colors" prop:value=move || power_step().unwrap_or_default().as_kw() on:input=move |e| {
-                                if let Ok(value) = event_target_value(&e).parse() {
-                                    set_power_step(Power::from_kw(value));
-                                }
-                            }/>
-                            <span>
-                                "Max per plug:"
-                                {move || max_per_plug().unwrap_or(Power::from_kw(1.0)).to_string()}
-                            </span>
-                            <input class="dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-36 shrink transition-colors" prop:value=move || max_per_plug().unwrap_or_default().as_kw() on:input=move |e| {
-                                if let Ok(value) = event_target_value(&e).parse() {
-                                    set_max_per_plug(Power::from_kw(value));
-                                }
-                            }/>
-                        </div>
-                    </div>
+                    <ChargerSupplyEditor kind grid_connection set_supply set_grid_connection set_grid_connection_intermediate=set_grid_connection />
+                    <LoadSharingEditor strategy=load_share set_strategy=set_load_share set_strategy_intermediate=set_load_share grid_connection />
+                    <label class="flex flex-row gap-1 items-center text-sm col-span-2">
+                        <input type="checkbox" class="accent-amber-500 dark:accent-teal-500" prop:checked=bidirectional on:change=move |e| set_bidirectional(event_target_checked(&e)) />
+                        "Bidirectional, V2G vehicles can discharge through it"
+                    </label>
                     <button class="bg-amber-500 dark:bg-teal-600 text-white hover:bg-amber-600 dark:hover:bg-teal-500 rounded-lg p-2 font-medium transition-colors" on:click=move |_| {
                         let strategy = load_share.get_untracked();
                         let mut chargers = chargers();
-                        chargers.push(Charger::new(grid_connection.get_untracked(), strategy));
+                        let mut charger = Charger::new(grid_connection.get_untracked(), strategy, kind.get_untracked());
+                        charger.bidirectional = bidirectional.get_untracked();
+                        // a preset tweaked before adding no longer names the hardware
+                        charger.preset = preset.get_untracked().filter(|preset| preset.describes(&charger)).map(PresetKey::from);
+                        chargers.push(charger);
                         set_chargers(chargers);
                         load_share.set(LoadSharingStrategy::None);
+                        set_preset(None);
                     }>"Add charger +"</button>
                 </div>
             </div>
     }
+    .into_any()
 }
 
 #[component]
 fn ChargerList(
     #[prop(into)] chargers: Signal<Vec<Charger>>,
     set_chargers: SignalSetter<Vec<Charger>>,
+    set_chargers_intermediate: SignalSetter<Vec<Charger>>,
 ) -> impl IntoView {
+    let (editing, set_editing) = signal(None::<usize>);
+    let update = move |f: &dyn Fn(&mut Vec<Charger>)| {
+        let mut chargers = chargers.get_untracked();
+        f(&mut chargers);
+        set_editing(None);
+        set_chargers(chargers);
+    };
+    let button = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 px-1.5 border border-slate-300 dark:border-slate-500 transition-colors";
     view! {
-        <div class="grid grid-cols-2" class:collapse=move || chargers.with(|c| c.is_empty())>
+        <div class="grid grid-cols-2 gap-1" class:collapse=move || chargers.with(|c| c.is_empty())>
             <h3 class="text-xl col-span-2">"Chargers: "</h3>
             <For each=move || chargers.get().into_iter().enumerate()
-            key=|(i, c)| (*i, c.grid_connection.watts, format!("{:?}", c.strategy))
+            key=|(i, c)| (*i, c.grid_connection.watts, format!("{:?}", c.strategy), c.kind, c.bidirectional, c.preset.clone())
             let:charger>
-            <div class="p-2 flex flex-row rounded-lg gap-1 bg-white dark:bg-slate-800 border-l-4 border-amber-400 dark:border-teal-500 shadow-sm">
-                "Grid power: "{charger.1.grid_connection.to_string()}<br/>
+            <div class="p-2 flex flex-row rounded-lg gap-1 bg-white dark:bg-slate-800 border-l-4 border-amber-400 dark:border-teal-500 shadow-sm"
+                class:ring-2=move || editing() == Some(charger.0)>
+                {charger.1.preset.as_ref().map(|preset| view! { <span class="font-medium" title=preset.notes()>{preset.name.to_string()}</span><br/> })}
+                {let supply = charger.1.clone(); move || supply.supply_label(&preferences())}{charger.1.bidirectional.then_some(", bidirectional")}<br/>
                 {match charger.1.strategy {
                     LoadSharingStrategy::None => "None".into_any(),
                     LoadSharingStrategy::Paired { number_of_plugs } => format!("Paired - {number_of_plugs}").into_any(),
@@ -912,116 +1453,180 @@ fn ChargerList(
                     </div>}.into_any(),
                 }}
             </div>
-            <button class="hover:bg-red-500 bg-red-600 rounded-lg w-10 border border-red-700 text-white transition-colors" on:click=move |_| {
-                let mut chargers = chargers();
-                chargers.remove(charger.0);
-                set_chargers(chargers);
-            }>"X"</button>
+            <div class="flex flex-row gap-1 items-center">
+                <button class=button on:click=move |_| set_editing(Some(charger.0))>"Edit"</button>
+                <button class=button title="Duplicate" on:click=move |_| update(&|c| *c = with_copies(c.iter(), charger.0, 1))>"+1"</button>
+                <button class=button title="Duplicate 10 times" on:click=move |_| update(&|c| *c = with_copies(c.iter(), charger.0, 10))>"+10"</button>
+                <button class="hover:bg-red-500 bg-red-600 rounded-lg w-10 border border-red-700 text-white transition-colors" on:click=move |_| update(&|c| { c.remove(charger.0); })>"X"</button>
+            </div>
             </For>
+            {move || editing().map(|index| view! {
+                <ChargerEditor index chargers set_chargers set_chargers_intermediate on_close=move || set_editing(None) />
+            })}
         </div>
     }
 }
 
 #[component]
-fn ChargeCurve(
-    #[prop(into)] spec: Signal<Option<&'static VehicleSpec>>,
-    #[prop(into)] start_soc: Signal<PercentFull>,
-    #[prop(into)] end_soc: Signal<PercentFull>,
+fn ChargerEditor(
+    index: usize,
+    #[prop(into)] chargers: Signal<Vec<Charger>>,
+    set_chargers: SignalSetter<Vec<Charger>>,
+    set_chargers_intermediate: SignalSetter<Vec<Charger>>,
+    on_close: impl Fn() + Send + Sync + 'static,
 ) -> impl IntoView {
-    let container = NodeRef::<html::Div>::new();
-    let UseElementSizeReturn { width, height } = use_element_size(container);
-    let dark_mode = use_preferred_dark();
-    Effect::new(move |_| {
-        let start_soc = start_soc();
-        let end_soc = end_soc();
-        let desired_width = (width() - 5.0).max(100.0) as u32;
-        let desired_height = (height() - 5.0).max(100.0) as u32;
-
-        if let Some(spec) = spec() {
-            #[cfg(feature = "hydrate")]
+    let charger = Memo::new(move |_| chargers.with(|c| c.get(index).cloned()));
+    let update = move |setter: SignalSetter<Vec<Charger>>, f: &dyn Fn(&mut Charger)| {
+        let mut chargers = chargers.get_untracked();
+        if let Some(charger) = chargers.get_mut(index) {
+            f(charger);
+            if charger
+                .preset
+                .as_ref()
+                .is_some_and(|preset| !preset.describes(charger))
             {
-                use charming::{
-                    component::{Axis, Legend, Title, VisualMap, VisualMapPiece, VisualMapType},
-                    element::{
-                        AreaStyle, AxisLabel, AxisType, Label, LineStyle, MarkLine, MarkLineData,
-                        MarkLineVariant, NameLocation, Symbol,
-                    },
-                    series::Line,
-                    WasmRenderer,
-                };
-                let points = spec
-                    .charge_curve
-                    .data_points
-                    .iter()
-                    .map(|point| vec![point.state_of_charge.as_float(), point.charge_power.as_kw()])
-                    .collect::<Vec<_>>();
-                let chart = charming::Chart::new()
-                    .title(Title::new().text("Charging Curve"))
-                    .x_axis(
-                        Axis::new()
-                            .name("Battery SOC%")
-                            .name_location(NameLocation::Center)
-                            .type_(AxisType::Value),
-                    )
-                    .y_axis(
-                        Axis::new()
-                            .name("Charge Power (KW)")
-                            .type_(AxisType::Value)
-                            .name_location(NameLocation::Center)
-                            .name_gap(35.0)
-                            .axis_label(AxisLabel::new().show(true)),
-                    )
-                    .visual_map(
-                        VisualMap::new()
-                            .type_(VisualMapType::Piecewise)
-                            .show(false)
-                            .dimension(0)
-                            .series_index(0)
-                            .pieces(vec![VisualMapPiece::new()
-                                .gt(start_soc.as_float())
-                                .lt(end_soc.as_float())])
-                            .color(vec!["#5373BB70"]),
-                    )
-                    .series(
-                        Line::new()
-                            .name(spec.name)
-                            .data(points)
-                            .smooth(0.5)
-                            .show_symbol(false)
-                            .line_style(LineStyle::new().width(4.0).color("#5373BB"))
-                            .mark_line(
-                                MarkLine::new()
-                                    .symbol(vec![Symbol::None, Symbol::None])
-                                    .label(Label::new().show(false))
-                                    .data(vec![
-                                        MarkLineVariant::Simple(
-                                            MarkLineData::new().x_axis(start_soc.as_float()),
-                                        ),
-                                        MarkLineVariant::Simple(
-                                            MarkLineData::new().x_axis(end_soc.as_float()),
-                                        ),
-                                    ]),
-                            )
-                            .area_style(AreaStyle::new()),
-                    )
-                    .legend(Legend::new());
-                let html = WasmRenderer::new(desired_width, desired_height);
-                html.theme(match dark_mode() {
-                    true => charming::theme::Theme::Dark,
-                    false => charming::theme::Theme::Chalk,
-                })
-                .render("chargecurve", &chart)
-                .unwrap();
+                charger.preset = None;
             }
+            setter.set(chargers);
         }
+    };
+    let grid_connection = Signal::derive(move || {
+        charger.with(|c| c.as_ref().map(|c| c.grid_connection).unwrap_or_default())
+    });
+    let kind =
+        Signal::derive(move || charger.with(|c| c.as_ref().map(|c| c.kind).unwrap_or_default()));
+    let set_supply = SignalSetter::map(move |(kind, power): (ChargerKind, Power)| {
+        update(set_chargers, &|c| {
+            c.kind = kind;
+            c.grid_connection = power;
+        })
+    });
+    let set_grid_connection =
+        SignalSetter::map(move |power: Power| update(set_chargers, &|c| c.grid_connection = power));
+    let set_grid_connection_intermediate = SignalSetter::map(move |power: Power| {
+        update(set_chargers_intermediate, &|c| c.grid_connection = power)
     });
+    let strategy = Signal::derive(move || {
+        charger.with(|c| {
+            c.as_ref()
+                .map(|c| c.strategy)
+                .unwrap_or(LoadSharingStrategy::None)
+        })
+    });
+    let set_strategy =
+        SignalSetter::map(move |strategy| update(set_chargers, &|c| c.strategy = strategy));
+    let set_strategy_intermediate = SignalSetter::map(move |strategy| {
+        update(set_chargers_intermediate, &|c| c.strategy = strategy)
+    });
+    let bidirectional = move || charger.with(|c| c.as_ref().is_some_and(|c| c.bidirectional));
     view! {
-        <Script src="https://cdn.jsdelivr.net/npm/echarts@5.4.2/dist/echarts.min.js"></Script>
-        <Script src="https://cdn.jsdelivr.net/npm/echarts-gl@2.0.9/dist/echarts-gl.min.js"></Script>
-        <div class:collapse=move || spec().is_none() class="flex flex-col">
-            <div node_ref=container class="w-full h-screen md:h-[50vh]">{move || { let _ = dark_mode(); let _ = width(); let _ = height(); view!{ <div id="chargecurve"></div> }}}</div>
-            <span>"Please note that the displayed curve may not be accurate."</span>
-            <span>"Assumes charger can match voltage of the vehicle and optimal battery temperature."</span>
+        <div class="col-span-2 flex flex-col gap-1 p-2 rounded-lg bg-white dark:bg-slate-800 border-l-4 border-amber-400 dark:border-teal-500 shadow-sm">
+            <div class="flex flex-row gap-2 items-center">
+                <h4 class="text-lg grow">"Editing charger #"{index + 1}</h4>
+                <button class="rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 px-1.5 border border-slate-300 dark:border-slate-500 transition-colors" on:click=move |_| on_close()>"Done"</button>
+            </div>
+            <div class="grid grid-cols-2">
+                <ChargerSupplyEditor kind grid_connection set_supply set_grid_connection set_grid_connection_intermediate />
+                <LoadSharingEditor strategy set_strategy set_strategy_intermediate grid_connection />
+                <label class="flex flex-row gap-1 items-center text-sm col-span-2">
+                    <input type="checkbox" class="accent-amber-500 dark:accent-teal-500" prop:checked=bidirectional
+                        on:change=move |e| { let checked = event_target_checked(&e); update(set_chargers, &|c| c.bidirectional = checked) } />
+                    "Bidirectional, V2G vehicles can discharge through it"
+                </label>
+            </div>
+        </div>
+    }
+    .into_any()
+}
+
+/// The vehicle's curve with the session's SOC range shaded, and the power the charger cuts off
+/// shaded red above it
+fn charge_curve_chart(
+    spec: Option<&VehicleSpec>,
+    limited_curve: &ChargeCurve,
+    start_soc: PercentFull,
+    end_soc: PercentFull,
+) -> LineChartData {
+    let points = |curve: &ChargeCurve| {
+        curve
+            .data_points
+            .iter()
+            .map(|point| (point.state_of_charge.as_float(), point.charge_power.as_kw()))
+            .collect::<Vec<_>>()
+    };
+    let prefs = preferences();
+    let chart = LineChartData::new(
+        "Charging Curve",
+        ChartAxis::new("Battery SOC%")
+            .range(0.0, 100.0)
+            .label(move |soc| format!("{}%", prefs.number(soc, 0))),
+        ChartAxis::new("Charge Power (kW)").label(move |kw| format!("{} kW", prefs.number(kw, 0))),
+    )
+    .marker(ChartMarker::Band {
+        from: start_soc.as_float(),
+        to: end_soc.as_float(),
+    });
+    let Some(spec) = spec else {
+        return chart;
+    };
+    let peak = limited_curve
+        .data_points
+        .iter()
+        .max_by_key(|point| point.charge_power);
+    let mut description = format!(
+        "{} charging from {}% to {}%",
+        spec.name,
+        prefs.number(start_soc.as_float(), 0),
+        prefs.number(end_soc.as_float(), 0)
+    );
+    if let Some(peak) = peak {
+        description += &format!(
+            ", peaking at {} at {}%",
+            prefs.power(peak.charge_power),
+            prefs.number(peak.state_of_charge.as_float(), 0)
+        );
+    }
+    description += if spec.charge_curve == *limited_curve {
+        "."
+    } else {
+        ", the charger can't deliver the vehicle's full curve."
+    };
+    let chart = chart.description(description).series(
+        ChartSeries::line(spec.name, points(limited_curve))
+            .color("#5373BB")
+            .width(4.0)
+            .filled(),
+    );
+    if spec.charge_curve == *limited_curve {
+        chart
+    } else {
+        chart.series(
+            ChartSeries::line("Limited by charger", points(&spec.charge_curve))
+                .color("#BB5353")
+                .width(0.0)
+                .filled_from(points(limited_curve)),
+        )
+    }
+}
+
+#[component]
+fn ChargeCurve(
+    #[prop(into)] spec: Signal<Option<&'static VehicleSpec>>,
+    /// the curve after applying the charger's limits, anything it cuts off is shaded
+    #[prop(into)]
+    limited_curve: Signal<ChargeCurve>,
+    #[prop(into)] start_soc: Signal<PercentFull>,
+    #[prop(into)] end_soc: Signal<PercentFull>,
+) -> impl IntoView {
+    let chart = Signal::derive(move || {
+        limited_curve.with(|curve| charge_curve_chart(spec(), curve, start_soc(), end_soc()))
+    });
+    view! {
+        <div class:collapse=move || spec().is_none() class="flex flex-col">
+            <LineChart chart id="chargecurve" height=450.0 />
+            <span>"Please note that the displayed curve may not be accurate."</span>
+            <span>"Assumes charger can match voltage of the vehicle and optimal battery temperature."</span>
+            <span class:collapse=move || limited_curve.with(|curve| spec().is_none_or(|spec| spec.charge_curve == *curve))>"The shaded area above the curve is power the charger can't deliver."</span>
         </div>
     }
 }
@@ -1032,14 +1637,22 @@ struct ChargingVehicle {
     allocated_power: Power,
     vehicle_id: usize,
     vehicle: Vehicle,
+    /// which of the charger's plugs it's using
+    plug: usize,
+    /// the charge it had when it plugged in
+    start_charge: Energy,
 }
 
 impl ChargingVehicle {
-    fn summary(&self) -> VehicleChargeFrame {
+    fn summary(&self, charger_id: usize) -> VehicleChargeFrame {
         let allocated_power = self.allocated_power;
         VehicleChargeFrame {
             allocated_power,
             vehicle_id: self.vehicle_id,
+            charger_id,
+            plug: self.plug,
+            soc: self.vehicle.soc(),
+            energy_added: self.vehicle.current_charge - self.start_charge,
         }
     }
 }
@@ -1056,28 +1669,104 @@ impl IntDivCeil for i32 {
     }
 }
 
+/// The lowest AC power a plug can offer, 6A at 240V is the least J1772 can signal
+const MIN_AC_POWER: Power = Power::from_kw(1.44);
+
+/// Whether a charger supplies DC to the battery or AC through the vehicle's onboard charger
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+enum ChargerKind {
+    #[default]
+    Dc,
+    /// Level 2 EVSEs, the grid connection is the circuit they share
+    Ac { phases: AcPhases },
+}
+
+impl ChargerKind {
+    /// The most a vehicle can take from a plug rated for `plug_max`, before its charge curve
+    fn vehicle_limit(self, spec: &VehicleSpec, plug_max: Power) -> Power {
+        match self {
+            ChargerKind::Dc => plug_max,
+            ChargerKind::Ac { phases } => spec.onboard_charger.ac_limit(phases, plug_max),
+        }
+    }
+
+    /// The power the vehicle will draw when offered `available` from a plug rated for `plug_max`
+    fn power_request(
+        self,
+        vehicle: &mut Vehicle,
+        available: Power,
+        plug_max: Power,
+    ) -> Option<Power> {
+        match self {
+            ChargerKind::Dc => vehicle.get_next_power_request(available),
+            ChargerKind::Ac { .. } => {
+                let limit = self.vehicle_limit(vehicle.spec_details(), plug_max);
+                let offered = available.min(limit).max(MIN_AC_POWER);
+                vehicle
+                    .get_next_power_request(offered)
+                    .map(|power| power.min(offered))
+            }
+        }
+    }
+}
+
+impl Display for ChargerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChargerKind::Dc => write!(f, "DC fast"),
+            ChargerKind::Ac { phases } => write!(f, "AC {phases}"),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq)]
 struct Charger {
     grid_connection: Power,
     strategy: LoadSharingStrategy,
+    kind: ChargerKind,
+    /// can send power back to the grid from V2G vehicles
+    bidirectional: bool,
+    /// the hardware it was set up from, it may have been tweaked since
+    preset: Option<PresetKey>,
     #[serde(skip)]
     currently_charging: Vec<ChargingVehicle>,
 }
 
 impl Charger {
-    fn new(grid_connection: Power, strategy: LoadSharingStrategy) -> Self {
+    fn new(grid_connection: Power, strategy: LoadSharingStrategy, kind: ChargerKind) -> Self {
         Self {
             grid_connection,
             strategy,
+            kind,
+            bidirectional: false,
+            preset: None,
             currently_charging: vec![],
         }
     }
 
+    /// describes what the charger is connected to
+    fn supply_label(&self, prefs: &Preferences) -> String {
+        match self.kind {
+            ChargerKind::Dc => format!("Grid power: {}", prefs.power(self.grid_connection)),
+            ChargerKind::Ac { phases } => format!(
+                "AC {phases} circuit: {:.0}A ({})",
+                phases.amps(self.grid_connection),
+                prefs.power(self.grid_connection)
+            ),
+        }
+    }
+
     fn add_vehicle(&mut self, vehicle: Vehicle, id: usize) {
+        // the lowest numbered plug nobody is using
+        let plug = (0..)
+            .find(|plug| self.currently_charging.iter().all(|c| c.plug != *plug))
+            .unwrap_or_default();
         self.currently_charging.push(ChargingVehicle {
             allocated_power: Power::from_kw(0.0),
+            start_charge: vehicle.current_charge,
             vehicle,
             vehicle_id: id,
+            plug,
         });
     }
 
@@ -1092,6 +1781,34 @@ impl Charger {
         }
     }
 
+    /// The most a single plug can deliver when the others leave it room
+    fn max_per_plug(&self) -> Power {
+        match self.strategy {
+            LoadSharingStrategy::None => self.grid_connection,
+            LoadSharingStrategy::Paired { number_of_plugs } => {
+                (self.grid_connection / number_of_plugs.max(1) * 2).min(self.grid_connection)
+            }
+            LoadSharingStrategy::Split { number_of_plugs } => {
+                self.grid_connection / number_of_plugs.max(1)
+            }
+            LoadSharingStrategy::Granular { max_per_plug, .. } => {
+                max_per_plug.min(self.grid_connection)
+            }
+        }
+    }
+
+    /// Whether the simulation can run this charger. Without a plug nothing can charge, and
+    /// power steps of nothing or more than the charger has would never hand any power out.
+    fn is_valid(&self) -> bool {
+        let power_step_fits = match self.strategy {
+            LoadSharingStrategy::Granular { power_step, .. } => {
+                power_step.watts > 0 && power_step <= self.grid_connection
+            }
+            _ => true,
+        };
+        self.num_plugs() > 0 && power_step_fits
+    }
+
     fn has_free_plug(&self) -> bool {
         self.num_plugs() > self.currently_charging.len() as u32
     }
@@ -1104,9 +1821,16 @@ impl Charger {
     }
 
     fn update_power_requests(&mut self) {
+        let kind = self.kind;
+        // vehicles that discharged last step start back from nothing
+        for c in &mut self.currently_charging {
+            c.allocated_power = c.allocated_power.max(Power::default());
+        }
         match self.strategy {
             LoadSharingStrategy::None => self.currently_charging.retain_mut(|c| {
-                if let Some(next) = c.vehicle.get_next_power_request(self.grid_connection) {
+                if let Some(next) =
+                    kind.power_request(&mut c.vehicle, self.grid_connection, self.grid_connection)
+                {
                     c.allocated_power = next;
                     true
                 } else {
@@ -1124,7 +1848,7 @@ impl Charger {
                     } else {
                         power_per_plug
                     };
-                    if let Some(next) = c.vehicle.get_next_power_request(power) {
+                    if let Some(next) = kind.power_request(&mut c.vehicle, power, power) {
                         c.allocated_power = next;
                         true
                     } else {
@@ -1135,7 +1859,9 @@ impl Charger {
             LoadSharingStrategy::Split { number_of_plugs } => {
                 let power_per_plug = self.grid_connection / number_of_plugs;
                 self.currently_charging.retain_mut(|c| {
-                    if let Some(next) = c.vehicle.get_next_power_request(power_per_plug) {
+                    if let Some(next) =
+                        kind.power_request(&mut c.vehicle, power_per_plug, power_per_plug)
+                    {
                         c.allocated_power = next;
                         true
                     } else {
@@ -1154,14 +1880,18 @@ impl Charger {
                     .iter()
                     .map(|c| c.allocated_power.watts.div_up(power_step.watts))
                     .sum::<i32>();
-                
+
                 self.currently_charging.retain_mut(|c| {
                     let is_valid = (total_steps - power_steps_allocated > 0) as i32;
-                    let available_power = c.allocated_power + (power_step * is_valid).min(max_per_plug);
-                    if let Some(power) = c.vehicle.get_next_power_request(available_power) {
+                    let available_power =
+                        c.allocated_power + (power_step * is_valid).min(max_per_plug);
+                    if let Some(power) =
+                        kind.power_request(&mut c.vehicle, available_power, max_per_plug)
+                    {
                         let old_power_steps = c.allocated_power.watts.div_up(power_step.watts);
                         let new_power_steps = power.watts.div_up(power_step.watts);
-                        let next_power_steps = power_steps_allocated + new_power_steps - old_power_steps;
+                        let next_power_steps =
+                            power_steps_allocated + new_power_steps - old_power_steps;
                         if total_steps < next_power_steps {
                             return true;
                         }
@@ -1186,9 +1916,71 @@ impl Charger {
     }
 }
 
+/// How the site manages its total demand
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Default)]
+enum SitePolicy {
+    #[default]
+    None,
+    /// Discharge V2G vehicles on bidirectional chargers to keep the site's draw under `threshold`
+    PeakShaving { threshold: Power },
+}
+
+/// When the simulation clock starts, giving it a time of day
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+struct ScenarioStart {
+    /// 1 is January 1st
+    day_of_year: u16,
+    /// minutes after midnight
+    minute_of_day: u16,
+}
+
+impl Default for ScenarioStart {
+    fn default() -> Self {
+        // a summer morning
+        Self {
+            day_of_year: 172,
+            minute_of_day: 8 * 60,
+        }
+    }
+}
+
+impl ScenarioStart {
+    /// hours since midnight on January 1st, `elapsed` into the simulation
+    fn hour_of_year(&self, elapsed: Duration) -> f64 {
+        self.day_of_year.saturating_sub(1) as f64 * 24.0
+            + self.minute_of_day as f64 / 60.0
+            + elapsed.as_secs_f64() / 60.0 / 60.0
+    }
+}
+
+/// How much time each step of the simulator covers
+const SIM_STEP: Duration = Duration::from_secs(1);
+
+/// Step for runs over more than a day. A week at `SIM_STEP` is hundreds of thousands of frames,
+/// recomputed on every edit, and the chart can't show seconds across days anyway.
+const MULTI_DAY_SIM_STEP: Duration = Duration::from_secs(30);
+
+/// The step a run of `days` takes
+fn sim_step(days: u16) -> Duration {
+    if days > 1 {
+        MULTI_DAY_SIM_STEP
+    } else {
+        SIM_STEP
+    }
+}
+
+/// Days a run may go on past the last day of arrivals, enough for any queue to clear on a site
+/// that can charge at all, and a stop for one that can't
+const MAX_OVERRUN_DAYS: u16 = 3;
+
+/// The longest the clock jumps forward while the site is empty, waiting for the next arrival
+const IDLE_STEP: Duration = Duration::from_secs(5 * 60);
+
 struct Sim {
     /// all of the vehicles that are waiting to be charged
     vehicles: VecDeque<Vehicle>,
+    /// vehicles that haven't arrived yet and when they do, in order of arrival
+    arrivals: VecDeque<(Duration, Vehicle)>,
     current_id: usize,
     /// all of the chargers in the simulation
     chargers: Vec<Charger>,
@@ -1196,31 +1988,175 @@ struct Sim {
     simulation_step_time: Duration,
     /// Total duration the simulation has simulated
     simulation_time: Duration,
+    site_policy: SitePolicy,
+    start: ScenarioStart,
+    solar: Option<SolarArray>,
+    /// the run stops here even if vehicles are still plugged in or waiting
+    time_limit: Duration,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 struct VehicleChargeFrame {
     allocated_power: Power,
     vehicle_id: usize,
+    charger_id: usize,
+    plug: usize,
+    soc: PercentFull,
+    /// since it plugged in, negative after V2G has taken more than it was given
+    energy_added: Energy,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 struct ChargerFrame {
     charger_id: usize,
     active_power: Power,
     unused_power: Power,
 }
 
-#[derive(Clone)]
+/// Totals for a completed simulation run
+#[derive(Clone, Copy)]
+struct SimSummary {
+    energy_dispensed: Energy,
+    /// the most the whole site drew at once
+    peak_demand: Power,
+    solar_generated: Energy,
+    /// solar that went into vehicles
+    solar_used: Energy,
+    grid_import: Energy,
+    duration: Duration,
+    vehicles: usize,
+    chargers: usize,
+    /// what all the chargers could draw together
+    charger_capacity: Power,
+    simulation_step_time: Duration,
+}
+
+impl SimSummary {
+    fn new(
+        steps: &[SimFrame],
+        vehicles: usize,
+        chargers: &[Charger],
+        simulation_step_time: Duration,
+    ) -> Self {
+        // frames can cover more than one step while the site sits idle
+        let step_times = steps
+            .iter()
+            .scan(Duration::default(), |previous, s| {
+                let step_time = s.duration.saturating_sub(*previous);
+                *previous = s.duration;
+                Some(step_time)
+            })
+            .collect::<Vec<_>>();
+        Self {
+            energy_dispensed: steps.iter().map(|s| s.energy_dispensed).sum(),
+            peak_demand: steps
+                .iter()
+                .map(|s| s.chargers.iter().map(|c| c.active_power).sum())
+                .max()
+                .unwrap_or_default(),
+            solar_generated: steps
+                .iter()
+                .zip(&step_times)
+                .map(|(s, step_time)| s.solar_power * *step_time)
+                .sum(),
+            solar_used: steps
+                .iter()
+                .zip(&step_times)
+                .map(|(s, step_time)| {
+                    let demand: Power = s.chargers.iter().map(|c| c.active_power).sum();
+                    (demand - s.grid_power) * *step_time
+                })
+                .sum(),
+            grid_import: steps
+                .iter()
+                .zip(&step_times)
+                .map(|(s, step_time)| s.grid_power.max(Power::default()) * *step_time)
+                .sum(),
+            duration: steps.last().map(|s| s.duration).unwrap_or_default(),
+            vehicles,
+            chargers: chargers.len(),
+            charger_capacity: chargers.iter().map(|c| c.grid_connection).sum(),
+            simulation_step_time,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 struct SimFrame {
     energy_dispensed: Energy,
+    /// generated on site, whether or not it was used
+    solar_power: Power,
+    /// drawn from the grid after solar, negative when V2G sends power back
+    grid_power: Power,
     chargers: Vec<ChargerFrame>,
     vehicles_charging: Vec<VehicleChargeFrame>,
+    /// vehicles that have arrived and are waiting for a plug
+    queue_length: usize,
     duration: Duration,
 }
 
 impl Sim {
+    /// A run where each of `vehicles` arrives once a day for `days` days
+    fn new(
+        vehicles: &VecDeque<Vehicle>,
+        chargers: Vec<Charger>,
+        site_policy: SitePolicy,
+        start: ScenarioStart,
+        days: u16,
+        solar: Option<SolarArray>,
+        simulation_step_time: Duration,
+    ) -> Self {
+        Self {
+            vehicles: VecDeque::new(),
+            arrivals: daily_arrivals(vehicles, start, days),
+            chargers,
+            simulation_step_time,
+            simulation_time: Duration::default(),
+            current_id: 0,
+            site_policy,
+            start,
+            solar,
+            time_limit: Duration::from_secs(
+                (days.clamp(1, MAX_DAYS) + MAX_OVERRUN_DAYS) as u64 * 24 * 60 * 60,
+            ),
+        }
+    }
+
+    /// Specs of the vehicles yet to arrive. Ids are handed out as vehicles plug in,
+    /// which follows the order they arrive, so before running this lines up with the ids.
+    fn arriving_specs(&self) -> Vec<&'static VehicleSpec> {
+        self.arrivals
+            .iter()
+            .flat_map(|(_, v)| v.spec.borrow().try_into())
+            .collect()
+    }
+
+    /// Steps until every vehicle is done or the time limit, nothing if the scenario can't run
+    fn run(&mut self) -> Vec<SimFrame> {
+        self.run_while(|| true)
+    }
+
+    /// Like [`Sim::run`], also stopping as soon as `keep_going` returns false
+    fn run_while(&mut self, mut keep_going: impl FnMut() -> bool) -> Vec<SimFrame> {
+        let mut steps = vec![];
+        if self.is_valid() {
+            while !self.is_done() && self.simulation_time < self.time_limit && keep_going() {
+                steps.push(self.step());
+            }
+        }
+        steps
+    }
+
     fn step(&mut self) -> SimFrame {
+        // vehicles join the queue once they arrive
+        while self
+            .arrivals
+            .front()
+            .is_some_and(|(at, _)| *at <= self.simulation_time)
+        {
+            self.vehicles
+                .extend(self.arrivals.pop_front().map(|(_, v)| v));
+        }
         // start charging any vehicles we can
         if !self.vehicles.is_empty() {
             for charger in self.chargers.iter_mut().filter(|c| c.has_free_plug()) {
@@ -1234,12 +2170,32 @@ impl Sim {
         for charger in &mut self.chargers {
             charger.update_power_requests();
         }
+        let solar_power = self
+            .solar
+            .as_ref()
+            .map(|solar| solar.output_at(self.start, self.simulation_time))
+            .unwrap_or_default();
+        if let SitePolicy::PeakShaving { threshold } = self.site_policy {
+            self.shave_peak(threshold + solar_power);
+        }
+        // skip ahead towards the next arrival while nothing is plugged in
+        let idle = self.vehicles.is_empty()
+            && self
+                .chargers
+                .iter()
+                .all(|c| c.currently_charging.is_empty());
+        let step_time = match self.arrivals.front() {
+            Some((at, _)) if idle => (*at - self.simulation_time)
+                .min(IDLE_STEP)
+                .max(self.simulation_step_time),
+            _ => self.simulation_step_time,
+        };
         let energy_dispensed = self
             .chargers
             .iter_mut()
-            .map(|c| c.charge_vehicles(self.simulation_step_time))
+            .map(|c| c.charge_vehicles(step_time))
             .sum::<Energy>();
-        self.simulation_time += self.simulation_step_time;
+        self.simulation_time += step_time;
         let chargers = self
             .chargers
             .iter()
@@ -1249,28 +2205,87 @@ impl Sim {
                 ChargerFrame {
                     charger_id,
                     active_power,
-                    unused_power: (charger.grid_connection - active_power),
+                    unused_power: (charger.grid_connection - active_power.max(Power::default())),
                 }
             })
             .collect();
+        let demand: Power = self
+            .chargers
+            .iter()
+            .map(|c| c.total_allocated_power())
+            .sum();
         SimFrame {
             energy_dispensed,
+            solar_power,
+            grid_power: demand - solar_power.min(demand.max(Power::default())),
             vehicles_charging: self
                 .chargers
                 .iter()
-                .flat_map(|c| c.currently_charging.iter().map(|c| c.summary()))
+                .enumerate()
+                .flat_map(|(charger_id, c)| {
+                    c.currently_charging
+                        .iter()
+                        .map(move |c| c.summary(charger_id))
+                })
                 .collect(),
+            queue_length: self.vehicles.len(),
             duration: self.simulation_time,
             chargers,
         }
     }
 
+    /// Turns V2G vehicles on bidirectional chargers down, then over to discharging, until the
+    /// chargers draw no more than `threshold`. Vehicles with the most energy above their reserve go first.
+    fn shave_peak(&mut self, threshold: Power) {
+        // a positive threshold keeps energy flowing into the site, so the run still finishes
+        let threshold = threshold.max(MIN_POWER_REQUEST);
+        let demand: Power = self
+            .chargers
+            .iter()
+            .map(|c| c.total_allocated_power())
+            .sum();
+        let mut excess = demand - threshold;
+        if excess <= Power::default() {
+            return;
+        }
+        let step_hours = self.simulation_step_time.as_secs_f64() / 60.0 / 60.0;
+        let mut candidates = self
+            .chargers
+            .iter_mut()
+            .filter(|c| c.bidirectional)
+            .flat_map(|charger| {
+                let (kind, plug_max) = (charger.kind, charger.max_per_plug());
+                charger.currently_charging.iter_mut().filter_map(move |c| {
+                    let spare =
+                        c.vehicle.current_charge.watt_hours - c.vehicle.v2g_reserve?.watt_hours;
+                    // never take it below the reserve within this step
+                    let limit = kind
+                        .vehicle_limit(c.vehicle.spec_details(), plug_max)
+                        .min(Power::from_kw(spare / 1000.0 / step_hours));
+                    (spare > 0.0).then_some((spare, limit, c))
+                })
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, limit, charging) in candidates {
+            if excess <= Power::default() {
+                break;
+            }
+            let allocated = (charging.allocated_power - excess).max(Power::default() - limit);
+            excess = excess - (charging.allocated_power - allocated);
+            charging.allocated_power = allocated;
+        }
+    }
+
     fn is_valid(&self) -> bool {
-        !self.chargers.is_empty() && !self.vehicles.is_empty()
+        !self.chargers.is_empty()
+            && self.chargers.iter().all(Charger::is_valid)
+            && !(self.vehicles.is_empty() && self.arrivals.is_empty())
     }
 
     fn is_done(&self) -> bool {
         self.vehicles.is_empty()
+            && self.arrivals.is_empty()
             && self
                 .chargers
                 .iter()
@@ -1283,6 +2298,10 @@ struct SimVehicleSeriesData {
     spec: &'static VehicleSpec,
     id: usize,
     data: Vec<Vec<f64>>,
+    /// state of charge in percent
+    soc: Vec<Vec<f64>>,
+    /// kWh added since plugging in
+    energy: Vec<Vec<f64>>,
 }
 
 struct SimChargerSeriesData {
@@ -1302,10 +2321,12 @@ fn get_charge_data_from_vehicles(
             spec,
             id,
             data: vec![],
+            soc: vec![],
+            energy: vec![],
         })
         .collect::<Vec<_>>();
     let mut chargers: Vec<_> = data
-        .get(0)
+        .first()
         .map(|c| {
             c.chargers
                 .iter()
@@ -1321,9 +2342,16 @@ fn get_charge_data_from_vehicles(
     for sim_frame in data {
         let time_mins = sim_frame.duration.as_secs_f64() / 60.0;
         for vehicle_frame in &sim_frame.vehicles_charging {
-            vehicles[vehicle_frame.vehicle_id]
+            let vehicle = &mut vehicles[vehicle_frame.vehicle_id];
+            vehicle
                 .data
                 .push(vec![time_mins, vehicle_frame.allocated_power.as_kw()]);
+            vehicle
+                .soc
+                .push(vec![time_mins, vehicle_frame.soc.as_float()]);
+            vehicle
+                .energy
+                .push(vec![time_mins, vehicle_frame.energy_added.as_kwh()]);
         }
         for charger in &sim_frame.chargers {
             chargers[charger.charger_id]
@@ -1337,215 +2365,291 @@ fn get_charge_data_from_vehicles(
     (vehicles, chargers)
 }
 
-#[component]
-fn SimulationChart(
-    vehicles: Signal<Vec<&'static VehicleSpec>>,
-    data: Signal<Vec<SimFrame>>,
-    prefers_dark: Signal<bool>,
-) -> impl IntoView {
-    let node = NodeRef::<html::Div>::new();
-    let UseElementSizeReturn { width, height } = use_element_size_with_options(
-        node,
-        UseElementSizeOptions::default().initial_size(Size {
-            width: 1000.0,
-            height: 500.0,
-        }),
-    );
-    let is_large = use_media_query("(min-width: 728px)");
-    Effect::new(move |_| {
-        let desired_width = (width() - 10.0).max(100.0) as u32;
-        let desired_height = (height() - 10.0).max(100.0) as u32;
-        let vehicles = vehicles();
-        let prefers_dark = prefers_dark();
-        if data.with(|d| d.is_empty()) {
-            return;
+/// Minutes between time axis ticks, whichever keeps about five on the chart
+const TIME_STEPS: &[f64] = &[
+    5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 180.0, 240.0, 360.0, 720.0, 1440.0, 2880.0,
+];
+
+/// What the simulation chart plots against time
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SimChartMode {
+    Power,
+    StateOfCharge,
+}
+
+impl SimChartMode {
+    const ALL: [SimChartMode; 2] = [SimChartMode::Power, SimChartMode::StateOfCharge];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SimChartMode::Power => "Power and energy",
+            SimChartMode::StateOfCharge => "Vehicle SOC",
         }
+    }
+}
 
-        let (vehicle_curves, chargers) =
-            data.with(|sim_data| get_charge_data_from_vehicles(vehicles, sim_data));
-        #[cfg(feature = "hydrate")]
-        {
-            use charming::{
-                component::{
-                    Axis, Feature, Grid, Legend, LegendType, Restore, SaveAsImage, Title, Toolbox,
-                    ToolboxDataZoom,
-                },
-                element::{AxisLabel, AxisType, NameLocation, Orient, Tooltip, Trigger, LineStyle, MarkArea},
-                series::Line,
-                WasmRenderer,
-            };
-            let mut chart = charming::Chart::new()
-                .title(Title::new().text("Charging Simulation"))
-                .grid(
-                    Grid::new()
-                        .right(if is_large() { 300 } else { 100 })
-                        .left(62.0)
-                        .top(62.0)
-                        .bottom(50.0),
-                )
-                .x_axis(
-                    Axis::new()
-                        .name("Time elapsed (minutes)")
-                        .name_location(NameLocation::Center)
-                        .name_gap(25.0)
-                        .axis_label(AxisLabel::new().show(true).formatter("{value} min"))
-                        .type_(AxisType::Value),
-                )
-                .y_axis(
-                    Axis::new()
-                        .name("Charge Power (KW)")
-                        .type_(AxisType::Value)
-                        .name_location(NameLocation::Center)
-                        .name_gap(50.0)
-                        .axis_label(AxisLabel::new().formatter("{value} kw").show(true))
-                        .boundary_gap(("0%", "0%")),
-                )
-                .y_axis(
-                    Axis::new()
-                        .name("Energy Dispensed (kwh)")
-                        .type_(AxisType::Value)
-                        .name_location(NameLocation::Center)
-                        .name_gap(60.0)
-                        .axis_label(AxisLabel::new().formatter("{value} kwH").show(true)),
+/// The run over time in minutes, also drawn on the server for link previews
+fn simulation_chart(
+    vehicles: Vec<&'static VehicleSpec>,
+    frames: &Vec<SimFrame>,
+    start: ScenarioStart,
+    mode: SimChartMode,
+) -> LineChartData {
+    let (vehicle_curves, chargers) = get_charge_data_from_vehicles(vehicles, frames);
+    let minutes = |duration: Duration| duration.as_secs_f64() / 60.0;
+    let prefs = preferences();
+    let time_axis = ChartAxis::new("Time of day")
+        .steps(TIME_STEPS)
+        .label(move |minutes| start.label_at(Duration::from_secs_f64(minutes.max(0.0) * 60.0)));
+    let mut chart = match mode {
+        SimChartMode::Power => LineChartData::new(
+            "Charging Simulation",
+            time_axis,
+            ChartAxis::new("Charge Power (kW)")
+                .label(move |kw| format!("{} kW", prefs.number(kw, 0))),
+        )
+        .right_axis(
+            ChartAxis::new("Energy Dispensed (kWh)")
+                .label(move |kwh| format!("{} kWh", prefs.number(kwh, 0))),
+        ),
+        SimChartMode::StateOfCharge => LineChartData::new(
+            "Charging Simulation",
+            time_axis,
+            ChartAxis::new("State of charge (%)")
+                .range(0.0, 100.0)
+                .label(move |soc| format!("{}%", prefs.number(soc, 0))),
+        ),
+    };
+    // a line at each midnight the run passes, labelled with the new day's date
+    let end = frames.last().map(|s| s.duration).unwrap_or_default();
+    for midnight in start
+        .midnights(end.as_secs() / (24 * 60 * 60) + 1)
+        .take_while(|midnight| *midnight <= end)
+    {
+        chart = chart.marker(ChartMarker::VerticalLine {
+            x: minutes(midnight),
+            label: start.label_at(midnight),
+        });
+    }
+    let vehicle_count = vehicle_curves.len();
+    let description = match mode {
+        SimChartMode::Power => {
+            let peak = frames
+                .iter()
+                .map(|s| s.chargers.iter().map(|c| c.active_power).sum::<Power>())
+                .max()
+                .unwrap_or_default();
+            let dispensed: Energy = frames.iter().map(|s| s.energy_dispensed).sum();
+            format!(
+                "{vehicle_count} vehicles charging from {} to {}, the site drew at most {} and dispensed {}.",
+                start.label_at(Duration::default()),
+                start.label_at(end),
+                prefs.power(peak),
+                prefs.energy(dispensed)
+            )
+        }
+        SimChartMode::StateOfCharge => {
+            let (lowest, highest) = vehicle_curves
+                .iter()
+                .filter_map(|vehicle| vehicle.soc.last().map(|point| point[1]))
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), soc| {
+                    (min.min(soc), max.max(soc))
+                });
+            if lowest.is_finite() {
+                format!(
+                    "State of charge of {vehicle_count} vehicles while plugged in, they unplugged with between {}% and {}%.",
+                    prefs.number(lowest, 0),
+                    prefs.number(highest, 0)
                 )
-                .legend(if is_large() {
-                    Legend::new()
-                        .type_(LegendType::Scroll)
-                        .orient(Orient::Vertical)
-                        .right(0.0)
-                        .top("center")
-                } else {
-                    Legend::new()
-                        .type_(LegendType::Scroll)
-                        .orient(Orient::Horizontal)
-                        .top(25.0)
-                })
-                .tooltip(Tooltip::new().trigger(Trigger::Axis))
-                .toolbox(
-                    Toolbox::new().show(true).feature(
-                        Feature::new()
-                            .data_zoom(ToolboxDataZoom::new().y_axis_index("none"))
-                            .restore(Restore::new())
-                            .save_as_image(SaveAsImage::new().show(true)),
-                    ),
-                );
-            for car in vehicle_curves {
-                let SimVehicleSeriesData { spec, id, data } = car;
-                chart = chart.series(
-                    Line::new()
-                        .name(format!("#{} {}", id + 1, spec.name))
-                        .data(data)
-                        .smooth(0.5)
-                        .show_symbol(false),
-                );
+            } else {
+                format!("State of charge of {vehicle_count} vehicles while plugged in.")
             }
-            for charger in chargers {
-                let SimChargerSeriesData {
-                    id, unused_power, ..
-                } = charger;
-                chart = chart.series(
-                    Line::new()
-                        .name(format!("#{} charger unused power", id + 1))
-                        .data(unused_power)
-                        .smooth(0.5)
-                        .mark_area(MarkArea::new())
-                        .line_style(LineStyle::new().width(1.0))
-                        .show_symbol(false),
+        }
+    };
+    chart = chart.description(description);
+    let points = |data: &[Vec<f64>], value: fn(f64) -> f64| {
+        data.iter()
+            .map(|point| (point[0], value(point[1])))
+            .collect::<Vec<_>>()
+    };
+    if mode == SimChartMode::StateOfCharge {
+        for SimVehicleSeriesData { spec, id, soc, .. } in vehicle_curves {
+            let mut series =
+                ChartSeries::line(format!("#{} {}", id + 1, spec.name), points(&soc, |v| v));
+            // a pin where the vehicle unplugged, at the state of charge it left with
+            if let Some(last) = soc.last() {
+                series = series.marker(
+                    last[0],
+                    last[1],
+                    format!("unplugged {}%", prefs.number(last[1], 0)),
                 );
             }
-            let energy_dispensed = data.with(|d| {
-                let mut sum = Energy::from_kwh(0.0);
-                d.iter()
-                    .map(|s| {
-                        sum += s.energy_dispensed;
-                        vec![s.duration.as_secs_f64() / 60.0, sum.as_kwh()]
-                    })
-                    .collect::<Vec<_>>()
-            });
+            chart = chart.series(series);
+        }
+        return chart;
+    }
+    for SimVehicleSeriesData { spec, id, data, .. } in vehicle_curves {
+        chart = chart.series(ChartSeries::line(
+            format!("#{} {}", id + 1, spec.name),
+            points(&data, |kw| kw.max(0.0)),
+        ));
+        // V2G discharge gets its own red series below the axis
+        if data.iter().any(|point| point[1] < 0.0) {
             chart = chart.series(
-                Line::new()
-                    .name("Energy Dispensed (kwH)")
-                    .data(energy_dispensed)
-                    .smooth(0.5)
-                    .show_symbol(false)
-                    .y_axis_index(1),
+                ChartSeries::line(
+                    format!("#{} {} V2G discharge", id + 1, spec.name),
+                    points(&data, |kw| kw.min(0.0)),
+                )
+                .color("#dc2626")
+                .dashed()
+                .filled(),
             );
-
-            let html = WasmRenderer::new(desired_width, desired_height);
-            html.theme(match prefers_dark {
-                true => charming::theme::Theme::Dark,
-                false => charming::theme::Theme::Chalk,
-            })
-            .render("simchart", &chart)
-            .unwrap();
         }
+    }
+    for SimChargerSeriesData {
+        id, unused_power, ..
+    } in chargers
+    {
+        chart = chart.series(
+            ChartSeries::line(
+                format!("#{} charger unused power", id + 1),
+                points(&unused_power, |kw| kw),
+            )
+            .width(1.0),
+        );
+    }
+    // solar only shows up when the scenario has some, alongside what the grid covered
+    if frames.iter().any(|s| s.solar_power > Power::default()) {
+        let (solar, grid) = frames
+            .iter()
+            .map(|s| {
+                let time = minutes(s.duration);
+                ((time, s.solar_power.as_kw()), (time, s.grid_power.as_kw()))
+            })
+            .unzip();
+        chart = chart
+            .series(
+                ChartSeries::line("Solar (kW)", solar)
+                    .color("#eab308")
+                    .filled(),
+            )
+            .series(ChartSeries::line("Grid import (kW)", grid).dotted());
+    }
+    let mut sum = Energy::from_kwh(0.0);
+    let energy_dispensed = frames
+        .iter()
+        .map(|s| {
+            sum += s.energy_dispensed;
+            (minutes(s.duration), sum.as_kwh())
+        })
+        .collect();
+    chart.series(ChartSeries::line("Energy Dispensed (kWh)", energy_dispensed).right_axis())
+}
+
+#[component]
+fn SimulationChart(
+    vehicles: Signal<Vec<&'static VehicleSpec>>,
+    data: Signal<Vec<SimFrame>>,
+    #[prop(into)] start: Signal<ScenarioStart>,
+) -> impl IntoView {
+    let (mode, set_mode) = signal(SimChartMode::Power);
+    let chart = Signal::derive(move || {
+        data.with(|frames| simulation_chart(vehicles(), frames, start(), mode()))
     });
-    view! {<div class="w-full h-screen md:h-[750px]" node_ref=node>
-        {move || {
-            prefers_dark();
-            let _ = width();
-            let _ = height();
-            view! { <div class:invisible=move || data.with(|d| d.is_empty()) id="simchart"></div> }
-        }}
+    let btn_active =
+        "rounded-lg bg-amber-100 dark:bg-teal-900 p-1.5 border border-amber-400 dark:border-teal-500 font-medium";
+    let btn_inactive = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors";
+    view! {
+    <div class="flex flex-col gap-1" class:collapse=move || data.with(|d| d.is_empty())>
+        <div class="flex flex-row flex-wrap gap-1">
+            {SimChartMode::ALL.into_iter().map(|m| view! {
+                <button class=move || if mode() == m { btn_active } else { btn_inactive } on:click=move |_| set_mode(m)>{m.label()}</button>
+            }).collect_view()}
+        </div>
+        <LineChart chart id="simchart" height=650.0 />
     </div>
     }
 }
 
+#[component]
+fn SitePolicyEditor(
+    #[prop(into)] site_policy: Signal<SitePolicy>,
+    set_site_policy: SignalSetter<SitePolicy>,
+) -> impl IntoView {
+    let threshold = move || match site_policy() {
+        SitePolicy::None => None,
+        SitePolicy::PeakShaving { threshold } => Some(threshold),
+    };
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 items-center">
+            <label class="flex flex-row gap-1 items-center">
+                <input type="checkbox" class="accent-amber-500 dark:accent-teal-500" prop:checked=move || threshold().is_some()
+                    on:change=move |e| set_site_policy(if event_target_checked(&e) {
+                        SitePolicy::PeakShaving { threshold: Power::from_kw(150.0) }
+                    } else {
+                        SitePolicy::None
+                    }) />
+                "Shave peaks with V2G vehicles on bidirectional chargers, site limit kW"
+            </label>
+            <input class="dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors"
+                class:collapse=move || threshold().is_none()
+                prop:value=move || threshold().map(|threshold| threshold.as_kw().to_string()).unwrap_or_default()
+                on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                    set_site_policy(SitePolicy::PeakShaving { threshold: Power::from_kw(value.max(MIN_POWER_REQUEST.as_kw())) });
+                } } />
+        </div>
+    }
+}
+
 #[component]
 fn Simulation(
     #[prop(into)] vehicles: Signal<VecDeque<Vehicle>>,
     #[prop(into)] chargers: Signal<Vec<Charger>>,
-    sim_step: ReadSignal<Duration>,
+    #[prop(into)] site_policy: Signal<SitePolicy>,
+    #[prop(into)] start: Signal<ScenarioStart>,
+    #[prop(into)] days: Signal<u16>,
+    #[prop(into)] solar: Signal<Option<SolarArray>>,
+    #[prop(into)] forecast: Signal<Forecast>,
+    set_forecast: SignalSetter<Forecast>,
 ) -> impl IntoView {
-    let prefers_dark = leptos_use::use_preferred_dark();
     {
         move || {
-            let v = vehicles();
-            let (vehicles_signal, _) = signal(
-                v.iter()
-                    .flat_map(|v| v.spec.borrow().try_into())
-                    .collect::<Vec<_>>(),
-            );
-            let c = chargers();
-            let simulation_step_time = sim_step();
-            let mut sim = Sim {
-                vehicles: v,
-                chargers: c,
+            let simulation_step_time = sim_step(days());
+            let mut sim = Sim::new(
+                &vehicles(),
+                chargers(),
+                site_policy(),
+                start(),
+                days(),
+                solar(),
                 simulation_step_time,
-                simulation_time: Duration::default(),
-                current_id: 0,
-            };
-            let mut steps = vec![];
-            if sim.is_valid() {
-                while !sim.is_done() {
-                    steps.push(sim.step());
-                }
-                let total_energy_dispensed =
-                    steps.iter().map(|s| s.energy_dispensed).sum::<Energy>();
-                let total_time_spent = steps.last().map(|s| s.duration).unwrap_or_default();
+            );
+            let (vehicles_signal, _) = signal(sim.arriving_specs());
+            let vehicle_count = sim.arrivals.len();
+            let steps = sim.run();
+            if !steps.is_empty() {
+                let summary =
+                    SimSummary::new(&steps, vehicle_count, &sim.chargers, simulation_step_time);
                 let (steps_signal, _) = signal(steps.clone());
                 view!{
-                    <SimulationChart vehicles=vehicles_signal.into() data=steps_signal.into() prefers_dark />
+                    <SimulationChart vehicles=vehicles_signal.into() data=steps_signal.into() start />
+                    <PlugOccupancyChart vehicles=vehicles_signal.into() data=steps_signal.into() start />
                     <div class="flex flex-row flex-wrap gap-4 text-md">
-                        <div>"energy dispensed: "{total_energy_dispensed.to_string()}</div>
-                        <div>"minutes running: "{total_time_spent.as_secs()/60}</div>
-                        <div>"vehicles: "{vehicles.with(|v| v.len())}</div>
-                        <div>"chargers: "{chargers.with(|c| c.len())}</div>
-                        <div>{simulation_step_time.as_secs().to_string()}" second simulation interval"</div>
+                        <div>"energy dispensed: "{move || preferences().energy(summary.energy_dispensed)}</div>
+                        <div>"peak site demand: "{move || preferences().power(summary.peak_demand)}</div>
+                        <div>"grid import: "{move || preferences().energy(summary.grid_import)}</div>
+                        {(summary.solar_generated.watt_hours > 0.0).then(|| view! {
+                            <div>"solar used: "{move || preferences().energy(summary.solar_used)}" of "{move || preferences().energy(summary.solar_generated)}
+                                {format!(" ({:.0}% self-consumption)", summary.solar_used.watt_hours / summary.solar_generated.watt_hours * 100.0)}</div>
+                        })}
+                        <div>"minutes running: "{summary.duration.as_secs()/60}</div>
+                        <div>"finished "{start.with_untracked(|start| start.label_at(summary.duration))}</div>
+                        <div>"vehicles: "{summary.vehicles}</div>
+                        <div>"chargers: "{summary.chargers}</div>
+                        <div>{summary.simulation_step_time.as_secs().to_string()}" second simulation interval"</div>
                     </div>
-                // <div class="grid grid-cols-4">
-                //     <div>"minutes"</div><div>"energy dispensed"</div><div>"vehicles charging"</div><div>"plugs unused"</div>
-                //     {steps.into_iter().enumerate().filter(|(i, _b)| i % 60 == 1).map(|(_, b)| b).map(|s| view!{
-                //         <div>{format!("{:.2}", s.duration.as_secs_f64() / 60.0)}</div>
-                //         <div>{s.energy_dispensed.to_string()}</div>
-                //         <div>{s.vehicles_charging.len()}" total"
-                //             <ul>
-                //                 {s.vehicles_charging.into_iter().map(|summary| view!{ <li>{summary.allocated_power.to_string()}"@"{summary.soc.to_string()}", "{summary.spec.name.to_string()}</li>}).collect_view()}
-                //             </ul>
-                //         </div>
-                //         <div>{s.plugs_unused}</div>
-                //     }).collect_view()}
-                // </div>
+                    <ForecastPanel summary days=days.get_untracked() forecast set_forecast />
+                    <SimulationExport vehicles=vehicles_signal.into() data=steps_signal.into() summary />
             }.into_any()
             } else {
                 view! { "Add chargers and vehicles to get started" }.into_any()
@@ -1554,49 +2658,118 @@ fn Simulation(
     }
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Default, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
 struct Query {
     chargers: Vec<Charger>,
     vehicles: VecDeque<Vehicle>,
+    site_policy: SitePolicy,
+    start: ScenarioStart,
+    /// how many days the vehicle list repeats for
+    days: u16,
+    solar: Option<SolarArray>,
+    forecast: Forecast,
 }
 
-fn create_compressed_query<T: DeserializeOwned + Serialize + PartialEq + Default + Send + Sync>(
-) -> (Memo<T>, SignalSetter<T>) {
+impl Default for Query {
+    fn default() -> Self {
+        Self {
+            chargers: vec![],
+            vehicles: VecDeque::new(),
+            site_policy: SitePolicy::default(),
+            start: ScenarioStart::default(),
+            days: 1,
+            solar: None,
+            forecast: Forecast::default(),
+        }
+    }
+}
+
+/// Reads the scenario out of the compressed query string, returning the decode error if it is unreadable
+fn decode_compressed_query<T: Versioned>(query_string: &str) -> Result<T, ScenarioError> {
+    let str = general_purpose::URL_SAFE.decode(query_string)?;
+    let mut decompress_out = Vec::new();
+    // read one byte past the limit to tell a scenario that fits from one that doesn't
+    let mut decoder = DeflateDecoder::new(Cursor::new(str)).take(MAX_SCENARIO_BYTES as u64 + 1);
+    decoder.read_to_end(&mut decompress_out)?;
+    if decompress_out.len() > MAX_SCENARIO_BYTES {
+        return Err(ScenarioError::TooLarge);
+    }
+    let query_string = String::from_utf8(decompress_out)?;
+    from_versioned_json(&query_string)
+}
+
+/// Encodes the scenario as versioned, deflated JSON for the query string
+fn encode_compressed_query<T: Versioned>(query: &T) -> String {
+    let query = to_versioned_json(query).unwrap().to_string();
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = DeflateEncoder::new(&mut bytes, Compression::new(9));
+        encoder.write_all(query.as_bytes()).unwrap();
+        encoder.flush().unwrap();
+    }
+    general_purpose::URL_SAFE.encode(bytes)
+}
+
+/// Stores `T` as versioned, deflated JSON in the query string.
+/// Returns the value, a writer that takes whether to replace the current history entry,
+/// and the error message if the current query string couldn't be read.
+fn create_compressed_query<T>() -> (
+    Memo<T>,
+    impl Fn(T, bool) + Clone + Send + Sync + 'static,
+    Memo<Option<String>>,
+)
+where
+    T: Versioned + Clone + PartialEq + Default + Send + Sync + 'static,
+{
     let location = use_location();
     let navigate = use_navigate();
     let search = location.search;
 
-    let get = Memo::new(move |_| {
-        match search.with::<Result<_, Box<dyn std::error::Error>>>(|query_string| {
-            let str = general_purpose::URL_SAFE.decode(query_string)?;
-            let mut decompress_out = Vec::new();
-            let mut decoder = DeflateDecoder::new(Cursor::new(str));
-            let _end = decoder.read_to_end(&mut decompress_out)?;
-            let query_string = String::from_utf8(decompress_out)?;
-            Ok(serde_json::from_str::<T>(&query_string)?)
-        }) {
-            Ok(query) => query,
-            Err(err) => {
-                log::error!("Error reading {err}");
-                T::default()
+    let decoded = Memo::new(move |_| {
+        search.with(|query_string| {
+            let (query_string, _) = split_query(query_string);
+            if query_string.is_empty() {
+                return Ok(T::default());
             }
-        }
+            decode_compressed_query::<T>(query_string).map_err(|err| {
+                log::error!("Error reading {err}");
+                err.to_string()
+            })
+        })
     });
-    let set = SignalSetter::map(move |query: T| {
-        let query = serde_json::to_string(&query).unwrap();
-        let mut bytes = Vec::new();
-        {
-            let mut encoder = DeflateEncoder::new(&mut bytes, Compression::new(9));
-            encoder.write_all(query.as_bytes()).unwrap();
-            encoder.flush().unwrap();
-        }
-        let query = general_purpose::URL_SAFE.encode(bytes);
+    let get = Memo::new(move |_| decoded.get().unwrap_or_default());
+    let error = Memo::new(move |_| decoded.with(|query| query.as_ref().err().cloned()));
+    let set = move |query: T, replace: bool| {
+        let query = encode_compressed_query(&query);
         let path = location.pathname.get_untracked();
         let hash = location.hash.get_untracked();
-        let url = [path.as_str(), "?", query.as_str(), hash.as_str()].concat();
-        navigate(&url, NavigateOptions::default());
-    });
-    (get, set)
+        // preference parameters on the link stay with it as the scenario is edited
+        let search = location.search.get_untracked();
+        let (_, preference_params) = split_query(&search);
+        let separator = if preference_params.is_empty() {
+            ""
+        } else {
+            "&"
+        };
+        let url = [
+            path.as_str(),
+            "?",
+            query.as_str(),
+            separator,
+            preference_params,
+            hash.as_str(),
+        ]
+        .concat();
+        navigate(
+            &url,
+            NavigateOptions {
+                replace,
+                scroll: !replace,
+                ..Default::default()
+            },
+        );
+    };
+    (get, set, error)
 }
 
 fn create_sub_slice<T, S, F, F2, O>(
@@ -1624,30 +2797,434 @@ where
 
 #[component]
 pub fn VehicleSim() -> impl IntoView {
-    let (query, set_query) = create_compressed_query::<Query>();
+    provide_preferences();
+    let (query, write_query, link_error) = create_compressed_query::<Query>();
+    let history = EditHistory::new(query, write_query);
+    let set_query = history.setter(EditKind::Commit);
+    let set_query_intermediate = history.setter(EditKind::Intermediate);
+    let (file_error, set_file_error) = signal(None::<String>);
+    let error = Signal::derive(move || file_error().or_else(link_error));
     let (chargers, set_chargers) =
         create_sub_slice(query, set_query, |q| &q.chargers, |q| &mut q.chargers);
     let (vehicles, set_vehicles) =
         create_sub_slice(query, set_query, |q| &q.vehicles, |q| &mut q.vehicles);
-    let (simulation_time, _) = signal(Duration::from_secs(1));
+    let (_, set_chargers_intermediate) = create_sub_slice(
+        query,
+        set_query_intermediate,
+        |q| &q.chargers,
+        |q| &mut q.chargers,
+    );
+    let (_, set_vehicles_intermediate) = create_sub_slice(
+        query,
+        set_query_intermediate,
+        |q| &q.vehicles,
+        |q| &mut q.vehicles,
+    );
+    let (site_policy, set_site_policy) =
+        create_sub_slice(query, set_query, |q| &q.site_policy, |q| &mut q.site_policy);
+    let (start, set_start) = create_sub_slice(query, set_query, |q| &q.start, |q| &mut q.start);
+    let (days, set_days) = create_sub_slice(query, set_query, |q| &q.days, |q| &mut q.days);
+    let (forecast, set_forecast) =
+        create_sub_slice(query, set_query, |q| &q.forecast, |q| &mut q.forecast);
+    let (solar, set_solar) = create_sub_slice(query, set_query, |q| &q.solar, |q| &mut q.solar);
+    let search = use_location().search;
     view! {
         <Title text="DC Fast Charger Sim" />
+        <ScenarioPreviewMeta query_string=search />
         <div class="flex flex-col gap-2">
             <div class="flex flex-col gap-1">
                 <h2 class="text-3xl font-bold gradient-text">"DC Fast Charging Simulator"</h2>
                 <span>"Simulate real charging time for electric vehicles in the real world with a variety of fast chargers."</span>
             </div>
+            <PreferencesEditor />
+            <ScenarioErrorBanner error />
+            <div class="flex flex-row flex-wrap gap-4 items-center">
+                <UndoRedo history />
+                <ScenarioFiles query set_query set_error=set_file_error />
+                <ShortLink query />
+            </div>
             <div class="flex flex-col gap-1">
-                <Simulation vehicles chargers sim_step=simulation_time />
+                <Simulation vehicles chargers site_policy start days solar forecast set_forecast />
+                <ScenarioStartEditor start set_start days set_days />
+                <SolarEditor solar set_solar />
+                <SitePolicyEditor site_policy set_site_policy />
             </div>
             <div class="flex flex-col md:flex-row gap-1">
-                <VehicleList vehicles set_vehicles/>
-                <ChargerList chargers set_chargers />
+                <VehicleList vehicles set_vehicles set_vehicles_intermediate />
+                <ChargerList chargers set_chargers set_chargers_intermediate />
             </div>
             <div class="flex flex-col gap-1">
                 <VehicleChooser vehicles set_vehicles />
                 <ChargerBuilder chargers set_chargers />
             </div>
+            <CurveComparison />
         </div>
     }
+    .into_any()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A vehicle arriving at 10% and leaving at 80%, its curve is over 200 kW at 10%
+    fn vehicle() -> Vehicle {
+        let spec = &VEHICLES[0];
+        let battery = spec.battery_max.watt_hours;
+        Vehicle::new(
+            spec,
+            Energy {
+                watt_hours: battery * 0.1,
+            },
+            Energy {
+                watt_hours: battery * 0.8,
+            },
+        )
+    }
+
+    /// `count` vehicles arriving at 10% and leaving at 80%, run in minute steps
+    fn sim(charger: Charger, count: usize) -> Sim {
+        Sim::new(
+            &VecDeque::from(vec![vehicle(); count]),
+            vec![charger],
+            SitePolicy::None,
+            ScenarioStart::default(),
+            1,
+            None,
+            Duration::from_secs(60),
+        )
+    }
+
+    fn granular(grid_connection: f64, power_step: f64, number_of_plugs: u32) -> Charger {
+        Charger::new(
+            Power::from_kw(grid_connection),
+            LoadSharingStrategy::Granular {
+                power_step: Power::from_kw(power_step),
+                number_of_plugs,
+                max_per_plug: Power::from_kw(grid_connection),
+            },
+            ChargerKind::Dc,
+        )
+    }
+
+    fn allocated(charger: &Charger) -> Vec<Power> {
+        charger
+            .currently_charging
+            .iter()
+            .map(|c| c.allocated_power)
+            .collect()
+    }
+
+    #[test]
+    fn power_times_a_count_scales_it() {
+        // this used to add the count in watts, 10 kW * 3 came out at 10.003 kW
+        assert_eq!(Power::from_kw(10.0) * 3, Power::from_kw(30.0));
+        assert_eq!(Power::from_kw(2.5) * 4, Power::from_kw(10.0));
+    }
+
+    #[test]
+    fn paired_plugs_get_twice_their_share_while_a_neighbour_is_free() {
+        let mut charger = Charger::new(
+            Power::from_kw(40.0),
+            LoadSharingStrategy::Paired { number_of_plugs: 4 },
+            ChargerKind::Dc,
+        );
+        charger.add_vehicle(vehicle(), 0);
+        charger.update_power_requests();
+        // adding gave 10 kW and 2 W
+        assert_eq!(allocated(&charger), [Power::from_kw(20.0)]);
+    }
+
+    #[test]
+    fn granular_chargers_hand_out_whole_steps() {
+        let mut charger = granular(40.0, 20.0, 2);
+        charger.add_vehicle(vehicle(), 0);
+        charger.update_power_requests();
+        assert_eq!(allocated(&charger), [Power::from_kw(20.0)]);
+        charger.update_power_requests();
+        assert_eq!(allocated(&charger), [Power::from_kw(40.0)]);
+
+        // two vehicles share the two steps, adding offered the first a step and a watt, which
+        // took both steps and left the second nothing
+        let mut charger = granular(40.0, 20.0, 2);
+        charger.add_vehicle(vehicle(), 0);
+        charger.add_vehicle(vehicle(), 1);
+        for _ in 0..3 {
+            charger.update_power_requests();
+            assert_eq!(allocated(&charger), [Power::from_kw(20.0); 2]);
+        }
+    }
+
+    #[test]
+    fn power_outside_the_curve_holds_its_ends() {
+        let curve = &VEHICLES[0].charge_curve;
+        let first = curve.data_points.first().unwrap().charge_power;
+        let last = curve.data_points.last().unwrap().charge_power;
+        assert_eq!(curve.power_at(PercentFull::new(-2.0)), first);
+        assert_eq!(curve.power_at(PercentFull::new(100.5)), last);
+        assert_eq!(
+            ChargeCurve::default().power_at(PercentFull::new(50.0)),
+            Power::default()
+        );
+    }
+
+    #[test]
+    fn soc_after_undoes_charge_time() {
+        for spec in VEHICLES {
+            let curve = &spec.charge_curve;
+            for (start, end) in [(10.0, 80.0), (3.0, 47.5), (55.0, 100.0)] {
+                let (start, end) = (PercentFull::new(start), PercentFull::new(end));
+                let time = curve.charge_time(spec.battery_max, start, end);
+                let reached = curve.soc_after(spec.battery_max, start, time);
+                assert!(
+                    (reached.as_float() - end.as_float()).abs() < 0.05,
+                    "{} reached {reached} not {end}",
+                    spec.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_flat_curve_charges_in_energy_over_power() {
+        let curve = ChargeCurve {
+            data_points: Cow::Owned(vec![
+                CurvePoint::new(0.0, 50.0),
+                CurvePoint::new(100.0, 50.0),
+            ]),
+        };
+        let battery = Energy {
+            watt_hours: 100_000.0,
+        };
+        // 50 kWh at 50 kW
+        let time = curve.charge_time(battery, PercentFull::new(10.0), PercentFull::new(60.0));
+        assert_eq!(time.as_secs(), 60 * 60);
+        let half_hour = Duration::from_secs(30 * 60);
+        let reached = curve.soc_after(battery, PercentFull::new(10.0), half_hour);
+        assert!((reached.as_float() - 35.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn chargers_that_cannot_hand_out_power_are_not_run() {
+        // a step bigger than the charger, no step at all and no plugs
+        for charger in [
+            granular(50.0, 100.0, 2),
+            granular(50.0, 0.0, 2),
+            granular(50.0, 10.0, 0),
+        ] {
+            assert!(!charger.is_valid());
+            assert!(sim(charger, 1).run().is_empty());
+        }
+        assert!(granular(50.0, 50.0, 2).is_valid());
+    }
+
+    #[test]
+    fn oversized_scenarios_are_not_decompressed() {
+        // a small link that inflates past the limit
+        let mut bytes = Vec::new();
+        let mut encoder = DeflateEncoder::new(&mut bytes, Compression::new(9));
+        encoder
+            .write_all(&vec![b' '; MAX_SCENARIO_BYTES + 1])
+            .unwrap();
+        encoder.finish().unwrap();
+        let link = general_purpose::URL_SAFE.encode(bytes);
+        assert!(link.len() < 4096);
+        assert!(matches!(
+            decode_compressed_query::<Query>(&link),
+            Err(ScenarioError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn run_stops_at_the_time_limit() {
+        // a day's worth of vehicles takes one slow plug more than a week
+        let mut sim = sim(
+            Charger::new(
+                Power::from_kw(5.0),
+                LoadSharingStrategy::None,
+                ChargerKind::Dc,
+            ),
+            20,
+        );
+        let frames = sim.run();
+        assert!(!sim.is_done());
+        assert_eq!(
+            frames.last().map(|frame| frame.duration),
+            Some(Duration::from_secs(
+                (1 + MAX_OVERRUN_DAYS) as u64 * 24 * 60 * 60
+            ))
+        );
+    }
+
+    #[test]
+    fn multi_day_runs_take_coarser_steps() {
+        assert_eq!(sim_step(1), SIM_STEP);
+        let days = MAX_DAYS;
+        let mut sim = Sim::new(
+            &VecDeque::from(vec![vehicle(); 4]),
+            vec![granular(150.0, 25.0, 2)],
+            SitePolicy::None,
+            ScenarioStart::default(),
+            days,
+            None,
+            sim_step(days),
+        );
+        let frames = sim.run();
+        assert!(sim.is_done());
+        let most = (days + MAX_OVERRUN_DAYS) as u64 * 24 * 60 * 60 / MULTI_DAY_SIM_STEP.as_secs();
+        assert!(frames.len() as u64 <= most);
+    }
+
+    #[test]
+    fn items_move_to_either_end() {
+        let mut items = VecDeque::from([1, 2, 3, 4]);
+        move_item(&mut items, 0, 3);
+        assert_eq!(items, [2, 3, 4, 1]);
+        move_item(&mut items, 3, 0);
+        assert_eq!(items, [1, 2, 3, 4]);
+        // moving the last item down or past the end leaves it last
+        move_item(&mut items, 3, 4);
+        assert_eq!(items, [1, 2, 3, 4]);
+        move_item(&mut items, 0, 10);
+        assert_eq!(items, [2, 3, 4, 1]);
+        move_item(&mut items, 7, 0);
+        assert_eq!(items, [2, 3, 4, 1]);
+    }
+
+    #[test]
+    fn copies_go_right_after_the_original() {
+        let items = vec![1, 2, 3];
+        assert_eq!(with_copies::<_, Vec<_>>(&items, 0, 1), [1, 1, 2, 3]);
+        assert_eq!(with_copies::<_, Vec<_>>(&items, 2, 1), [1, 2, 3, 3]);
+        let copies: VecDeque<_> = with_copies(&items, 1, 10);
+        assert_eq!(copies.len(), 13);
+        assert_eq!(copies.iter().filter(|item| **item == 2).count(), 11);
+        assert_eq!((copies[0], copies[12]), (1, 3));
+        // nothing to copy past the end
+        assert_eq!(with_copies::<_, Vec<_>>(&items, 3, 10), items);
+    }
+
+    #[test]
+    fn ac_chargers_are_held_to_the_onboard_charger() {
+        // the EV6 takes 10.9 kW on AC, 150 kW would be well within its DC curve
+        let onboard = vehicle().spec_details().onboard_charger.max_power;
+        let ac = ChargerKind::Ac {
+            phases: AcPhases::Single,
+        };
+        let plug = Power::from_kw(19.2);
+        assert_eq!(ac.power_request(&mut vehicle(), plug, plug), Some(onboard));
+        let plug = Power::from_kw(150.0);
+        assert_eq!(
+            ChargerKind::Dc.power_request(&mut vehicle(), plug, plug),
+            Some(plug)
+        );
+
+        let mut charger = Charger::new(Power::from_kw(19.2), LoadSharingStrategy::None, ac);
+        charger.add_vehicle(vehicle(), 0);
+        charger.update_power_requests();
+        assert_eq!(allocated(&charger), [onboard]);
+        let mut charger = Charger::new(
+            Power::from_kw(150.0),
+            LoadSharingStrategy::None,
+            ChargerKind::Dc,
+        );
+        charger.add_vehicle(vehicle(), 0);
+        charger.update_power_requests();
+        assert_eq!(allocated(&charger), [Power::from_kw(150.0)]);
+    }
+
+    /// A V2G vehicle at `soc` that will discharge down to `reserve`, both as fractions
+    fn v2g_vehicle(soc: f64, reserve: f64) -> Vehicle {
+        let mut vehicle = vehicle();
+        let battery = vehicle.spec_details().battery_max.watt_hours;
+        vehicle.current_charge = Energy {
+            watt_hours: battery * soc,
+        };
+        vehicle.v2g_reserve = Some(Energy {
+            watt_hours: battery * reserve,
+        });
+        vehicle
+    }
+
+    /// Peak shaving over 150 kW DC chargers in minute steps, vehicles plug into them in order
+    fn peak_shaving(threshold: f64, bidirectional: &[bool], vehicles: Vec<Vehicle>) -> Sim {
+        let chargers = bidirectional
+            .iter()
+            .map(|&bidirectional| Charger {
+                bidirectional,
+                ..Charger::new(
+                    Power::from_kw(150.0),
+                    LoadSharingStrategy::None,
+                    ChargerKind::Dc,
+                )
+            })
+            .collect();
+        Sim::new(
+            &VecDeque::from(vehicles),
+            chargers,
+            SitePolicy::PeakShaving {
+                threshold: Power::from_kw(threshold),
+            },
+            ScenarioStart::default(),
+            1,
+            None,
+            Duration::from_secs(60),
+        )
+    }
+
+    #[test]
+    fn peak_shaving_holds_the_grid_at_the_threshold() {
+        let mut sim = peak_shaving(
+            100.0,
+            &[true, false],
+            vec![v2g_vehicle(0.5, 0.2), vehicle()],
+        );
+        for _ in 0..10 {
+            let frame = sim.step();
+            assert_eq!(frame.grid_power, Power::from_kw(100.0));
+        }
+        // the other vehicle still charges at full power, the V2G one covers the rest
+        assert_eq!(allocated(&sim.chargers[1]), [Power::from_kw(150.0)]);
+        assert_eq!(allocated(&sim.chargers[0]), [Power::from_kw(-50.0)]);
+    }
+
+    #[test]
+    fn peak_shaving_stops_at_the_reserve() {
+        let mut sim = peak_shaving(0.0, &[true, false], vec![v2g_vehicle(0.21, 0.2), vehicle()]);
+        let reserve = v2g_vehicle(0.21, 0.2).v2g_reserve.unwrap();
+        let mut lowest = v2g_vehicle(0.21, 0.2).current_charge;
+        for _ in 0..10 {
+            sim.step();
+            let charge = sim.chargers[0].currently_charging[0].vehicle.current_charge;
+            lowest = Energy {
+                watt_hours: lowest.watt_hours.min(charge.watt_hours),
+            };
+        }
+        // it gave up what it had above the reserve, and no more
+        assert!((lowest.watt_hours - reserve.watt_hours).abs() < 0.001);
+    }
+
+    #[test]
+    fn one_way_chargers_never_discharge() {
+        let mut sim = peak_shaving(0.0, &[false, false], vec![v2g_vehicle(0.5, 0.2), vehicle()]);
+        let frame = sim.step();
+        assert!(sim
+            .chargers
+            .iter()
+            .flat_map(allocated)
+            .all(|power| power > Power::default()));
+        assert_eq!(frame.grid_power, Power::from_kw(300.0));
+    }
+
+    #[test]
+    fn run_while_stops_when_told() {
+        let mut sim = sim(granular(150.0, 25.0, 2), 4);
+        let mut budget = 10;
+        let frames = sim.run_while(|| {
+            budget -= 1;
+            budget >= 0
+        });
+        assert_eq!(frames.len(), 10);
+        assert!(!sim.is_done());
+    }
 }
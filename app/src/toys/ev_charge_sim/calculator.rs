@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use leptos::prelude::*;
+use leptos_meta::Title;
+
+use super::{
+    preferences, provide_preferences, PercentFull, PreferencesEditor, VehicleDropdown, VehicleSpec,
+};
+
+fn format_minutes(duration: Duration) -> String {
+    format!(
+        "{} mins",
+        preferences().number(duration.as_secs_f64() / 60.0, 1)
+    )
+}
+
+/// Answers "how long to X" questions for a single vehicle charging on an unlimited charger
+#[component]
+pub fn ChargeTimeCalculator() -> impl IntoView {
+    provide_preferences();
+    let (vehicle_spec, set_vehicle_spec) = signal::<Option<&'static VehicleSpec>>(None);
+    let spec = Memo::new(move |_| vehicle_spec().cloned().unwrap_or_default());
+    let (start_soc, set_start_soc) = signal(10.0_f64);
+    let (target_soc, set_target_soc) = signal(80.0_f64);
+    let (minutes, set_minutes) = signal(20.0_f64);
+    // kept in miles, shown and typed in the preferred unit
+    let (miles, set_miles) = signal(150.0_f64);
+    let start = move || PercentFull::new(start_soc().clamp(0.0, 100.0));
+
+    let time_to_target = move || {
+        let target = PercentFull::new(target_soc().clamp(0.0, 100.0));
+        spec.with(|spec| {
+            spec.charge_curve
+                .charge_time(spec.battery_max, start(), target)
+        })
+    };
+    let soc_after_minutes = move || {
+        let elapsed = Duration::try_from_secs_f64(minutes() * 60.0).unwrap_or_default();
+        spec.with(|spec| {
+            let soc = spec
+                .charge_curve
+                .soc_after(spec.battery_max, start(), elapsed);
+            let added = (soc - start()) * spec.battery_max;
+            let prefs = preferences();
+            format!(
+                "reaches {}, adding {} ({})",
+                prefs.percent(soc),
+                prefs.energy(added),
+                prefs.distance(spec.epa_miles_for(added))
+            )
+        })
+    };
+    let time_to_add_miles = move || {
+        spec.with(|spec| {
+            let energy = spec.energy_for_epa_miles(miles());
+            match spec
+                .charge_curve
+                .time_to_add(spec.battery_max, start(), energy)
+            {
+                Some(time) => format!(
+                    "takes {}, adding {}",
+                    format_minutes(time),
+                    preferences().energy(energy)
+                ),
+                None => "won't fit in the battery".to_string(),
+            }
+        })
+    };
+
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-36 transition-colors";
+    view! {
+        <Title text="EV Charge Time Calculator" />
+        <div class="flex flex-col gap-2">
+            <div class="flex flex-col gap-1">
+                <h2 class="text-3xl font-bold gradient-text">"Charge Time Calculator"</h2>
+                <span>"Integrates the charge curve of a vehicle to answer how long a charging stop takes."</span>
+            </div>
+            <PreferencesEditor />
+            <VehicleDropdown current_vehicle=vehicle_spec set_vehicle=set_vehicle_spec />
+            <div class="grid grid-cols-[auto_auto_1fr] gap-2 items-center" class:collapse=move || vehicle_spec.with(|spec| spec.is_none())>
+                <span>"Starting at battery%"</span>
+                <input class=input prop:value=start_soc
+                    on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_start_soc(value) } />
+                <span>{move || spec.with(|spec| preferences().energy(start() * spec.battery_max))}</span>
+                <span>"Charge to battery%"</span>
+                <input class=input prop:value=target_soc
+                    on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_target_soc(value) } />
+                <span>"takes "{move || format_minutes(time_to_target())}</span>
+                <span>"Charge for minutes"</span>
+                <input class=input prop:value=minutes
+                    on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_minutes(value) } />
+                <span>{soc_after_minutes}</span>
+                <span>{move || format!("Add EPA {}", preferences().distance_unit())}</span>
+                <input class=input prop:value=move || preferences().distance_value(miles()).round()
+                    on:input=move |e| if let Ok(value) = event_target_value(&e).parse() { set_miles(preferences().miles_from(value)) } />
+                <span>{time_to_add_miles}</span>
+            </div>
+            <span>"Assumes the charger can always meet the curve, at optimal battery temperature."</span>
+        </div>
+    }
+}
@@ -0,0 +1,320 @@
+use std::{cmp::Ordering, collections::VecDeque, time::Duration};
+
+use leptos::prelude::*;
+use leptos_meta::Title;
+
+use super::{
+    encode_compressed_query, preferences, preferences::Preferences, provide_preferences,
+    ChargeCurve, PercentFull, Power, PreferencesEditor, Query, Vehicle, VehicleSpec, VEHICLES,
+    VEHICLE_DATA_VERSION,
+};
+
+/// The session the table's times and rates are for, and vehicles are sent to the simulator with
+const SESSION_START: PercentFull = PercentFull::new(10.0);
+const SESSION_END: PercentFull = PercentFull::new(80.0);
+
+/// A vehicle with the figures the table compares, worked out once
+#[derive(Clone, Copy, PartialEq)]
+struct CatalogRow {
+    spec: &'static VehicleSpec,
+    average_power: Power,
+    peak_power: Power,
+    session_time: Duration,
+    /// EPA miles added per minute over the session
+    miles_per_minute: f64,
+}
+
+impl CatalogRow {
+    fn new(spec: &'static VehicleSpec) -> Self {
+        let curve = &spec.charge_curve;
+        let session_time = curve.charge_time(spec.battery_max, SESSION_START, SESSION_END);
+        let miles = spec.epa_miles_for((SESSION_END - SESSION_START) * spec.battery_max);
+        Self {
+            spec,
+            average_power: curve.average_power(),
+            peak_power: curve
+                .data_points
+                .iter()
+                .map(|point| point.charge_power)
+                .max()
+                .unwrap_or_default(),
+            session_time,
+            miles_per_minute: miles / (session_time.as_secs_f64() / 60.0).max(f64::EPSILON),
+        }
+    }
+}
+
+/// The columns the table can be sorted by
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CatalogColumn {
+    Name,
+    Battery,
+    AveragePower,
+    SessionTime,
+    PeakPower,
+    Range,
+    Efficiency,
+    MilesPerMinute,
+}
+
+impl CatalogColumn {
+    const ALL: [CatalogColumn; 8] = [
+        CatalogColumn::Name,
+        CatalogColumn::Battery,
+        CatalogColumn::AveragePower,
+        CatalogColumn::SessionTime,
+        CatalogColumn::PeakPower,
+        CatalogColumn::Range,
+        CatalogColumn::Efficiency,
+        CatalogColumn::MilesPerMinute,
+    ];
+
+    fn label(&self, prefs: &Preferences) -> String {
+        match self {
+            CatalogColumn::Name => "Vehicle".to_string(),
+            CatalogColumn::Battery => "Battery".to_string(),
+            CatalogColumn::AveragePower => "Avg power".to_string(),
+            CatalogColumn::SessionTime => "10->80% time".to_string(),
+            CatalogColumn::PeakPower => "Peak power".to_string(),
+            CatalogColumn::Range => "EPA range".to_string(),
+            CatalogColumn::Efficiency => "Efficiency".to_string(),
+            CatalogColumn::MilesPerMinute => format!("{} per minute", prefs.distance_unit()),
+        }
+    }
+
+    fn compare(&self, a: &CatalogRow, b: &CatalogRow) -> Ordering {
+        match self {
+            CatalogColumn::Name => a.spec.name.cmp(b.spec.name),
+            CatalogColumn::Battery => a
+                .spec
+                .battery_max
+                .watt_hours
+                .total_cmp(&b.spec.battery_max.watt_hours),
+            CatalogColumn::AveragePower => a.average_power.cmp(&b.average_power),
+            CatalogColumn::SessionTime => a.session_time.cmp(&b.session_time),
+            CatalogColumn::PeakPower => a.peak_power.cmp(&b.peak_power),
+            CatalogColumn::Range => a.spec.epa_miles.total_cmp(&b.spec.epa_miles),
+            // energy per mile, so ascending lists the most efficient first in either unit system
+            CatalogColumn::Efficiency => (a.spec.battery_max.watt_hours / a.spec.epa_miles)
+                .total_cmp(&(b.spec.battery_max.watt_hours / b.spec.epa_miles)),
+            CatalogColumn::MilesPerMinute => a.miles_per_minute.total_cmp(&b.miles_per_minute),
+        }
+    }
+
+    fn value(&self, row: &CatalogRow, prefs: &Preferences) -> String {
+        match self {
+            CatalogColumn::Name => row.spec.name.to_string(),
+            CatalogColumn::Battery => prefs.energy(row.spec.battery_max),
+            CatalogColumn::AveragePower => prefs.power(row.average_power),
+            CatalogColumn::SessionTime => {
+                format!(
+                    "{} mins",
+                    prefs.number(row.session_time.as_secs_f64() / 60.0, 1)
+                )
+            }
+            CatalogColumn::PeakPower => prefs.power(row.peak_power),
+            CatalogColumn::Range => prefs.distance(row.spec.epa_miles),
+            CatalogColumn::Efficiency => prefs.efficiency(row.spec.epa_miles, row.spec.battery_max),
+            CatalogColumn::MilesPerMinute => {
+                prefs.number(prefs.distance_value(row.miles_per_minute), 1)
+            }
+        }
+    }
+}
+
+/// Which vehicles the table shows
+#[derive(Clone, Default, PartialEq)]
+struct CatalogFilter {
+    /// matched against the name and trim, ignoring case
+    search: String,
+    /// the pack voltage class, `None` for any
+    pack_voltage: Option<u16>,
+    /// EPA miles
+    min_range: f64,
+}
+
+impl CatalogFilter {
+    fn matches(&self, row: &CatalogRow) -> bool {
+        let search = self.search.trim().to_lowercase();
+        let spec = row.spec;
+        (search.is_empty()
+            || spec.name.to_lowercase().contains(&search)
+            || spec.trim.to_lowercase().contains(&search))
+            && self
+                .pack_voltage
+                .is_none_or(|volts| spec.pack_voltage == volts)
+            && spec.epa_miles >= self.min_range
+    }
+}
+
+/// Simulator link with one of each vehicle arriving at 10% and unplugging at 80%
+fn simulator_link(specs: &[&'static VehicleSpec]) -> String {
+    let vehicles = specs
+        .iter()
+        .map(|spec| {
+            Vehicle::new(
+                spec,
+                SESSION_START * spec.battery_max,
+                SESSION_END * spec.battery_max,
+            )
+        })
+        .collect::<VecDeque<_>>();
+    let query = Query {
+        vehicles,
+        ..Default::default()
+    };
+    format!("/toys/ev-charger-sim?{}", encode_compressed_query(&query))
+}
+
+/// Every vehicle the simulator knows, to compare, chart and send to the simulator
+#[component]
+pub fn VehicleCatalog() -> impl IntoView {
+    provide_preferences();
+    let rows = VEHICLES.iter().map(CatalogRow::new).collect::<Vec<_>>();
+    let (filter, set_filter) = signal(CatalogFilter::default());
+    let (sort, set_sort) = signal((CatalogColumn::Name, false));
+    let (selected, set_selected) = signal(Vec::<&'static VehicleSpec>::new());
+    let (opened, set_opened) = signal(None::<&'static VehicleSpec>);
+    let shown = Memo::new(move |_| {
+        let (column, descending) = sort();
+        let mut shown = filter.with(|filter| {
+            rows.iter()
+                .filter(|row| filter.matches(row))
+                .copied()
+                .collect::<Vec<_>>()
+        });
+        shown.sort_by(|a, b| column.compare(a, b));
+        if descending {
+            shown.reverse();
+        }
+        shown
+    });
+    let mut voltages = VEHICLES
+        .iter()
+        .map(|spec| spec.pack_voltage)
+        .collect::<Vec<_>>();
+    voltages.sort();
+    voltages.dedup();
+    let curve = Signal::derive(move || {
+        opened()
+            .map(|spec| spec.charge_curve.clone())
+            .unwrap_or_default()
+    });
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 transition-colors";
+    let headings = CatalogColumn::ALL
+        .into_iter()
+        .map(|column| {
+            let aria_sort = move || match sort() {
+                (sorted, false) if sorted == column => "ascending",
+                (sorted, true) if sorted == column => "descending",
+                _ => "none",
+            };
+            let arrow = move || match sort() {
+                (sorted, false) if sorted == column => " ▲",
+                (sorted, true) if sorted == column => " ▼",
+                _ => "",
+            };
+            view! {
+                <th scope="col" class="px-2 text-left" aria-sort=aria_sort>
+                    <button class="font-medium hover:text-amber-600 dark:hover:text-teal-400"
+                        on:click=move |_| set_sort.update(|(sorted, descending)| {
+                            *descending = *sorted == column && !*descending;
+                            *sorted = column;
+                        })>
+                        {move || column.label(&preferences())}{arrow}
+                    </button>
+                </th>
+            }
+        })
+        .collect_view();
+    let table_rows = move || {
+        let prefs = preferences();
+        shown()
+            .into_iter()
+            .map(|row| {
+                let spec = row.spec;
+                let is_selected = move || selected.with(|selected| selected.contains(&spec));
+                view! {
+                    <tr class="cursor-pointer even:bg-slate-50 dark:even:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600"
+                        class:font-bold=move || opened() == Some(spec)
+                        on:click=move |_| set_opened(Some(spec))>
+                        <td class="px-2">
+                            <input type="checkbox" class="accent-amber-500 dark:accent-teal-500" aria-label=format!("Select {}", spec.name)
+                                prop:checked=is_selected
+                                on:click=|e| e.stop_propagation()
+                                on:change=move |e| {
+                                    let checked = event_target_checked(&e);
+                                    set_selected.update(|selected| {
+                                        selected.retain(|s| *s != spec);
+                                        if checked {
+                                            selected.push(spec);
+                                        }
+                                    });
+                                } />
+                        </td>
+                        <th scope="row" class="px-2 text-left font-normal">
+                            <button class="text-left hover:underline" on:click=move |_| set_opened(Some(spec))>{spec.name}</button>
+                            <div class="text-sm text-slate-500 dark:text-slate-400">{spec.model_year}" "{spec.trim}</div>
+                        </th>
+                        {CatalogColumn::ALL[1..].iter().map(|column| view! { <td class="px-2 whitespace-nowrap">{column.value(&row, &prefs)}</td> }).collect_view()}
+                    </tr>
+                }
+            })
+            .collect_view()
+    };
+    view! {
+        <Title text="EV Catalog" />
+        <div class="flex flex-col gap-2">
+            <div class="flex flex-col gap-1">
+                <h2 class="text-3xl font-bold gradient-text">"EV Catalog"</h2>
+                <span>"Every vehicle the charging simulator knows, times and rates are for charging from 10% to 80% on a charger that can always meet the curve."</span>
+            </div>
+            <PreferencesEditor />
+            <div class="flex flex-row flex-wrap gap-2 items-center">
+                <input class=input placeholder="Search vehicles" aria-label="Search vehicles"
+                    prop:value=move || filter.with(|filter| filter.search.clone())
+                    on:input=move |e| set_filter.update(|filter| filter.search = event_target_value(&e)) />
+                <label class="flex flex-row gap-1 items-center">
+                    "Pack"
+                    <select class=input on:change=move |e| set_filter.update(|filter| filter.pack_voltage = event_target_value(&e).parse().ok())>
+                        <option value="" selected=true>"any voltage"</option>
+                        {voltages.into_iter().map(|volts| view! { <option value=volts.to_string()>{volts}" V class"</option> }).collect_view()}
+                    </select>
+                </label>
+                <label class="flex flex-row gap-1 items-center">
+                    {move || format!("At least EPA {}", preferences().distance_unit())}
+                    <input class=format!("{input} w-24") prop:value=move || filter.with(|filter| preferences().distance_value(filter.min_range).round().to_string())
+                        on:change=move |e| { if let Ok(distance) = event_target_value(&e).parse::<f64>() {
+                            set_filter.update(|filter| filter.min_range = preferences().miles_from(distance.max(0.0)));
+                        } } />
+                </label>
+                <div class="grow"></div>
+                <a class="bg-amber-500 dark:bg-teal-600 text-white p-2 border border-amber-600 dark:border-teal-500 hover:bg-amber-600 dark:hover:bg-teal-500 rounded-lg font-medium transition-colors"
+                    class:hidden=move || selected.with(|selected| selected.is_empty())
+                    href=move || selected.with(|selected| simulator_link(selected))>
+                    {move || format!("Simulate {} selected", selected.with(|selected| selected.len()))}
+                </a>
+            </div>
+            <div class="overflow-x-auto">
+                <table class="w-full">
+                    <caption class="sr-only">"Vehicles, select rows to simulate them and click one to chart its curve"</caption>
+                    <thead>
+                        <tr>
+                            <th scope="col" class="px-2"><span class="sr-only">"Selected"</span></th>
+                            {headings}
+                        </tr>
+                    </thead>
+                    <tbody>{table_rows}</tbody>
+                </table>
+            </div>
+            <span class:hidden=move || !shown.with(|shown| shown.is_empty())>"No vehicles match."</span>
+            <div class="flex flex-col gap-1" class:hidden=move || opened().is_none()>
+                <h3 class="text-xl">{move || opened().map(|spec| spec.name)}</h3>
+                <span class="text-sm">{move || opened().map(|spec| spec.notes)}</span>
+                <ChargeCurve spec=opened limited_curve=curve start_soc=SESSION_START end_soc=SESSION_END />
+            </div>
+            <span class="text-sm">{format!("Vehicle data v{VEHICLE_DATA_VERSION}")}</span>
+        </div>
+    }
+    .into_any()
+}
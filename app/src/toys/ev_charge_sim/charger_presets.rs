@@ -0,0 +1,234 @@
+use std::borrow::Cow;
+
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{preferences, AcPhases, Charger, ChargerKind, LoadSharingStrategy, Power};
+use crate::components::Select;
+
+/// Charger hardware as sold, with how its cabinet shares power between plugs.
+/// Picking one fills in the charger builder, which can be tweaked from there.
+#[derive(PartialEq)]
+pub(super) struct ChargerPreset {
+    /// unique, scenarios refer to presets by name
+    name: &'static str,
+    /// what the cabinet can draw, shared between its plugs
+    cabinet_power: Power,
+    kind: ChargerKind,
+    strategy: LoadSharingStrategy,
+    /// the most a plug delivers, for reference, the simulator only limits power
+    max_voltage: u16,
+    max_current: u16,
+    bidirectional: bool,
+    notes: &'static str,
+}
+
+impl Eq for ChargerPreset {}
+
+impl ChargerPreset {
+    fn plugs(&self) -> u32 {
+        match self.strategy {
+            LoadSharingStrategy::None => 1,
+            LoadSharingStrategy::Paired { number_of_plugs }
+            | LoadSharingStrategy::Split { number_of_plugs }
+            | LoadSharingStrategy::Granular {
+                number_of_plugs, ..
+            } => number_of_plugs,
+        }
+    }
+
+    /// A charger set up like the preset
+    pub(super) fn charger(&'static self) -> Charger {
+        let mut charger = Charger::new(self.cabinet_power, self.strategy, self.kind);
+        charger.bidirectional = self.bidirectional;
+        charger.preset = Some(self.into());
+        charger
+    }
+
+    /// Whether the charger still has the supply, sharing and direction the preset fills in
+    pub(super) fn describes(&self, charger: &Charger) -> bool {
+        charger.grid_connection == self.cabinet_power
+            && charger.strategy == self.strategy
+            && charger.kind == self.kind
+            && charger.bidirectional == self.bidirectional
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub(super) struct PresetKey {
+    pub(super) name: Cow<'static, str>,
+}
+
+impl PresetKey {
+    /// The preset's notes, empty if this page doesn't know the preset
+    pub(super) fn notes(&self) -> &'static str {
+        <&ChargerPreset>::try_from(self)
+            .map(|preset| preset.notes)
+            .unwrap_or_default()
+    }
+
+    /// False once the charger has been edited away from the preset, a preset this page
+    /// doesn't know is kept as is
+    pub(super) fn describes(&self, charger: &Charger) -> bool {
+        <&ChargerPreset>::try_from(self).map_or(true, |preset| preset.describes(charger))
+    }
+}
+
+#[derive(Debug, Error)]
+pub(super) enum PresetLookupError {
+    #[error("Unable to find charger preset by name {0}")]
+    NotFound(Cow<'static, str>),
+}
+
+impl TryFrom<&PresetKey> for &'static ChargerPreset {
+    type Error = PresetLookupError;
+
+    fn try_from(value: &PresetKey) -> Result<Self, Self::Error> {
+        CHARGER_PRESETS
+            .iter()
+            .find(|p| p.name == value.name)
+            .ok_or_else(|| PresetLookupError::NotFound(value.name.clone()))
+    }
+}
+
+impl From<&'static ChargerPreset> for PresetKey {
+    fn from(value: &'static ChargerPreset) -> Self {
+        Self {
+            name: Cow::Borrowed(value.name),
+        }
+    }
+}
+
+/// Ratings are the manufacturers' published figures, rounded
+pub(super) static CHARGER_PRESETS: &[ChargerPreset] = &[
+    ChargerPreset {
+        name: "ABB Terra 360",
+        cabinet_power: Power::from_kw(360.0),
+        kind: ChargerKind::Dc,
+        strategy: LoadSharingStrategy::Granular {
+            power_step: Power::from_kw(30.0),
+            number_of_plugs: 2,
+            max_per_plug: Power::from_kw(360.0),
+        },
+        max_voltage: 920,
+        max_current: 500,
+        bidirectional: false,
+        notes: "One cabinet, power is shared dynamically between its two cables.",
+    },
+    ChargerPreset {
+        name: "Tesla V3 Supercharger (4 posts)",
+        cabinet_power: Power::from_kw(1000.0),
+        kind: ChargerKind::Dc,
+        strategy: LoadSharingStrategy::Granular {
+            power_step: Power::from_kw(25.0),
+            number_of_plugs: 4,
+            max_per_plug: Power::from_kw(250.0),
+        },
+        max_voltage: 500,
+        max_current: 615,
+        bidirectional: false,
+        notes: "A 1 MW cabinet feeds four posts, each can draw its full 250 kW at once.",
+    },
+    ChargerPreset {
+        name: "Tesla V2 Supercharger (A/B pair)",
+        cabinet_power: Power::from_kw(150.0),
+        kind: ChargerKind::Dc,
+        strategy: LoadSharingStrategy::Granular {
+            power_step: Power::from_kw(5.0),
+            number_of_plugs: 2,
+            max_per_plug: Power::from_kw(150.0),
+        },
+        max_voltage: 410,
+        max_current: 330,
+        bidirectional: false,
+        notes: "Stalls are paired on one cabinet, a second vehicle on the pair takes a share of its power.",
+    },
+    ChargerPreset {
+        name: "350 kW DC, one plug",
+        cabinet_power: Power::from_kw(350.0),
+        kind: ChargerKind::Dc,
+        strategy: LoadSharingStrategy::None,
+        max_voltage: 920,
+        max_current: 500,
+        bidirectional: false,
+        notes: "A typical highway dispenser with its own power.",
+    },
+    ChargerPreset {
+        name: "50 kW DC, one plug",
+        cabinet_power: Power::from_kw(50.0),
+        kind: ChargerKind::Dc,
+        strategy: LoadSharingStrategy::None,
+        max_voltage: 500,
+        max_current: 125,
+        bidirectional: false,
+        notes: "Older CCS and CHAdeMO units.",
+    },
+    ChargerPreset {
+        name: "Level 2 48 A (Tesla Wall Connector)",
+        cabinet_power: Power::from_kw(11.52),
+        kind: ChargerKind::Ac {
+            phases: AcPhases::Single,
+        },
+        strategy: LoadSharingStrategy::None,
+        max_voltage: 240,
+        max_current: 48,
+        bidirectional: false,
+        notes: "On a 60 A circuit.",
+    },
+    ChargerPreset {
+        name: "22 kW three phase AC",
+        cabinet_power: Power::from_kw(22.08),
+        kind: ChargerKind::Ac {
+            phases: AcPhases::Three,
+        },
+        strategy: LoadSharingStrategy::None,
+        max_voltage: 400,
+        max_current: 32,
+        bidirectional: false,
+        notes: "32 A per phase, single phase vehicles only get a third of it.",
+    },
+];
+
+#[component]
+pub(super) fn ChargerPresetDropdown(
+    #[prop(into)] current_preset: Signal<Option<&'static ChargerPreset>>,
+    #[prop(into)] set_preset: SignalSetter<Option<&'static ChargerPreset>>,
+) -> impl IntoView {
+    let presets = RwSignal::new(CHARGER_PRESETS.iter().collect::<Vec<_>>());
+    view! {
+        <Select items=presets.into() as_label=move |p| p.name.to_string() choice=current_preset set_choice=set_preset let:preset>
+            <div class="flex flex-row flex-wrap gap-2">
+                <span>{move || preferences().power(preset.cabinet_power)}</span>
+                <span>"plugs: "{preset.plugs()}</span>
+                <span>{preset.max_voltage}" V "{preset.max_current}" A"</span>
+                <span class="text-sm text-slate-500 dark:text-slate-400">{preset.notes}</span>
+            </div>
+        </Select>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edited_chargers_no_longer_match_their_preset() {
+        let preset = &CHARGER_PRESETS[0];
+        let mut charger = preset.charger();
+        let key = charger.preset.clone().unwrap();
+        assert!(key.describes(&charger));
+
+        charger.grid_connection = Power::from_kw(50.0);
+        assert!(!key.describes(&charger));
+
+        let mut charger = preset.charger();
+        charger.strategy = LoadSharingStrategy::None;
+        assert!(!key.describes(&charger));
+
+        let unknown = PresetKey {
+            name: Cow::Borrowed("A charger from a newer page"),
+        };
+        assert!(unknown.describes(&charger));
+    }
+}
@@ -0,0 +1,133 @@
+use std::{collections::VecDeque, time::Duration};
+
+use leptos::prelude::*;
+
+use super::{preferences, ScenarioStart, Vehicle};
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+
+/// The longest run the simulator offers, each day repeats the vehicle list
+pub(super) const MAX_DAYS: u16 = 7;
+
+const MONTHS: [(&str, u16); 12] = [
+    ("Jan", 31),
+    ("Feb", 28),
+    ("Mar", 31),
+    ("Apr", 30),
+    ("May", 31),
+    ("Jun", 30),
+    ("Jul", 31),
+    ("Aug", 31),
+    ("Sep", 30),
+    ("Oct", 31),
+    ("Nov", 30),
+    ("Dec", 31),
+];
+
+/// "Jun 21" for day 172, wrapping into the next year after day 365
+pub(super) fn format_day_of_year(day_of_year: u16) -> String {
+    let mut day = day_of_year.saturating_sub(1) % 365;
+    for (month, days) in MONTHS {
+        if day < days {
+            return format!("{month} {}", day + 1);
+        }
+        day -= days;
+    }
+    unreachable!("the months add up to 365 days")
+}
+
+/// "08:05" for 485 minutes after midnight, as time inputs take it
+pub(super) fn format_minute_of_day(minute_of_day: u16) -> String {
+    format!("{:02}:{:02}", minute_of_day / 60, minute_of_day % 60)
+}
+
+/// Reads an "HH:MM" time input into minutes after midnight
+pub(super) fn parse_minute_of_day(value: &str) -> Option<u16> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u16>().ok()?, minutes.parse::<u16>().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+impl ScenarioStart {
+    /// The date and time of day `elapsed` into the simulation, like "Jun 22 06:30" on the
+    /// preferred clock
+    pub(super) fn label_at(&self, elapsed: Duration) -> String {
+        let minutes = self.minute_of_day as u64 + elapsed.as_secs() / 60;
+        format!(
+            "{} {}",
+            format_day_of_year(self.day_of_year + (minutes / MINUTES_PER_DAY) as u16),
+            preferences().time_of_day((minutes % MINUTES_PER_DAY) as u16)
+        )
+    }
+
+    /// How long after the start each midnight falls, for the first `days` days of the run
+    pub(super) fn midnights(&self, days: u64) -> impl Iterator<Item = Duration> {
+        let first = MINUTES_PER_DAY - self.minute_of_day as u64;
+        (0..days).map(move |day| Duration::from_secs((first + day * MINUTES_PER_DAY) * 60))
+    }
+}
+
+/// Every vehicle arrives once a day for `days` days, at its arrival time or when the day
+/// starts if it has none. Returned in the order they arrive, ties keep the list order.
+pub(super) fn daily_arrivals(
+    vehicles: &VecDeque<Vehicle>,
+    start: ScenarioStart,
+    days: u16,
+) -> VecDeque<(Duration, Vehicle)> {
+    let mut arrivals = (0..days.clamp(1, MAX_DAYS) as u64)
+        .flat_map(|day| {
+            vehicles.iter().map(move |vehicle| {
+                let offset = vehicle
+                    .arrival
+                    .map(|arrival| {
+                        (arrival as u64 + MINUTES_PER_DAY - start.minute_of_day as u64)
+                            % MINUTES_PER_DAY
+                    })
+                    .unwrap_or_default();
+                let minutes = day * MINUTES_PER_DAY + offset;
+                (Duration::from_secs(minutes * 60), vehicle.clone())
+            })
+        })
+        .collect::<Vec<_>>();
+    arrivals.sort_by_key(|(at, _)| *at);
+    arrivals.into()
+}
+
+#[component]
+pub(super) fn ScenarioStartEditor(
+    #[prop(into)] start: Signal<ScenarioStart>,
+    set_start: SignalSetter<ScenarioStart>,
+    #[prop(into)] days: Signal<u16>,
+    set_days: SignalSetter<u16>,
+) -> impl IntoView {
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors";
+    let update = move |f: &dyn Fn(&mut ScenarioStart)| {
+        let mut start = start.get_untracked();
+        f(&mut start);
+        set_start(start);
+    };
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 items-center">
+            "Starts at"
+            <input type="time" class=input
+                prop:value=move || format_minute_of_day(start().minute_of_day)
+                on:change=move |e| {
+                    if let Some(minute_of_day) = parse_minute_of_day(&event_target_value(&e)) {
+                        update(&|start| start.minute_of_day = minute_of_day);
+                    }
+                } />
+            "on day of the year"
+            <input class=input prop:value=move || start().day_of_year
+                on:change=move |e| { if let Ok(day) = event_target_value(&e).parse::<u16>() {
+                    update(&|start| start.day_of_year = day.clamp(1, 365));
+                } } />
+            <span>{move || format!("({})", format_day_of_year(start().day_of_year))}</span>
+            "running for"
+            <input type="number" min="1" max=MAX_DAYS class=input prop:value=days
+                on:change=move |e| { if let Ok(days) = event_target_value(&e).parse::<u16>() {
+                    set_days(days.clamp(1, MAX_DAYS));
+                } } />
+            "days, vehicles arrive again each day"
+        </div>
+    }
+}
@@ -0,0 +1,166 @@
+use itertools::Itertools;
+use leptos::prelude::*;
+
+use crate::components::{ChartAxis, ChartSeries, LineChart, LineChartData};
+
+use super::{preferences, preferences::Preferences, PercentFull, VehicleSpec, VEHICLES};
+
+/// What the comparison chart plots for each vehicle
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CompareMode {
+    PowerVsSoc,
+    PowerVsTime,
+    EnergyVsTime,
+    RangeVsTime,
+}
+
+impl CompareMode {
+    const ALL: [CompareMode; 4] = [
+        CompareMode::PowerVsSoc,
+        CompareMode::PowerVsTime,
+        CompareMode::EnergyVsTime,
+        CompareMode::RangeVsTime,
+    ];
+
+    fn label(&self, prefs: &Preferences) -> String {
+        match self {
+            CompareMode::PowerVsSoc => "Power vs SOC".to_string(),
+            CompareMode::PowerVsTime => "Power vs time".to_string(),
+            CompareMode::EnergyVsTime => "kWh added".to_string(),
+            CompareMode::RangeVsTime => format!("{} added", prefs.distance_unit()),
+        }
+    }
+
+    /// What the chart shows, for its summary
+    fn description(&self) -> &'static str {
+        match self {
+            CompareMode::PowerVsSoc => "Charge power at each state of charge",
+            CompareMode::PowerVsTime => "Charge power over a session from 10% to 100%",
+            CompareMode::EnergyVsTime => "Energy added over a session from 10% to 100%",
+            CompareMode::RangeVsTime => "EPA range added over a session from 10% to 100%",
+        }
+    }
+
+    /// The chart's axes, labelled in the preferred units
+    fn axes(&self, prefs: Preferences) -> (ChartAxis, ChartAxis) {
+        let minutes = ChartAxis::new("Minutes charging from 10%")
+            .label(move |minutes| format!("{} min", prefs.number(minutes, 0)));
+        match self {
+            CompareMode::PowerVsSoc => (
+                ChartAxis::new("Battery SOC%")
+                    .range(0.0, 100.0)
+                    .label(move |soc| format!("{}%", prefs.number(soc, 0))),
+                ChartAxis::new("Charge Power (kW)")
+                    .label(move |kw| format!("{} kW", prefs.number(kw, 0))),
+            ),
+            CompareMode::PowerVsTime => (
+                minutes,
+                ChartAxis::new("Charge Power (kW)")
+                    .label(move |kw| format!("{} kW", prefs.number(kw, 0))),
+            ),
+            CompareMode::EnergyVsTime => (
+                minutes,
+                ChartAxis::new("Energy Added (kWh)")
+                    .label(move |kwh| format!("{} kWh", prefs.number(kwh, 0))),
+            ),
+            CompareMode::RangeVsTime => (
+                minutes,
+                ChartAxis::new(format!("EPA {} Added", prefs.distance_unit())).label(
+                    move |distance| {
+                        format!("{} {}", prefs.number(distance, 0), prefs.distance_unit())
+                    },
+                ),
+            ),
+        }
+    }
+
+    fn series(&self, spec: &VehicleSpec, prefs: &Preferences) -> Vec<(f64, f64)> {
+        if *self == CompareMode::PowerVsSoc {
+            return spec
+                .charge_curve
+                .data_points
+                .iter()
+                .map(|point| (point.state_of_charge.as_float(), point.charge_power.as_kw()))
+                .collect();
+        }
+        spec.charge_curve
+            .charge_session(
+                spec.battery_max,
+                PercentFull::new(10.0),
+                PercentFull::new(100.0),
+            )
+            .into_iter()
+            .map(|point| {
+                let minutes = point.elapsed.as_secs_f64() / 60.0;
+                let value = match self {
+                    CompareMode::EnergyVsTime => point.energy_added.as_kwh(),
+                    CompareMode::RangeVsTime => {
+                        prefs.distance_value(spec.epa_miles_for(point.energy_added))
+                    }
+                    _ => point.power.as_kw(),
+                };
+                (minutes, value)
+            })
+            .collect()
+    }
+}
+
+/// Overlays the charge curves of several vehicles
+#[component]
+pub(super) fn CurveComparison() -> impl IntoView {
+    let selected = RwSignal::new(Vec::<&'static VehicleSpec>::new());
+    let (mode, set_mode) = signal(CompareMode::PowerVsSoc);
+    let chart = Signal::derive(move || {
+        let mode = mode();
+        let prefs = preferences();
+        let (x_axis, y_axis) = mode.axes(prefs);
+        selected.with(|selected| {
+            let description = format!(
+                "{} for {}.",
+                mode.description(),
+                selected.iter().map(|spec| spec.name).join(", ")
+            );
+            selected.iter().fold(
+                LineChartData::new("Charge Curve Comparison", x_axis, y_axis)
+                    .description(description),
+                |chart, spec| chart.series(ChartSeries::line(spec.name, mode.series(spec, &prefs))),
+            )
+        })
+    });
+    let btn_active =
+        "rounded-lg bg-amber-100 dark:bg-teal-900 p-1.5 border border-amber-400 dark:border-teal-500 font-medium";
+    let btn_inactive = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors";
+    view! {
+        <details class="flex flex-col gap-1">
+            <summary class="text-xl cursor-pointer">"Compare charge curves"</summary>
+            <div class="flex flex-row flex-wrap gap-2">
+                {VEHICLES.iter().map(|spec| view! {
+                    <label class="flex flex-row gap-1 items-center">
+                        <input type="checkbox" class="accent-amber-500 dark:accent-teal-500"
+                            prop:checked=move || selected.with(|s| s.contains(&spec))
+                            on:change=move |e| {
+                                let checked = event_target_checked(&e);
+                                selected.update(|s| {
+                                    s.retain(|s| *s != spec);
+                                    if checked {
+                                        s.push(spec);
+                                    }
+                                });
+                            } />
+                        {spec.name}
+                    </label>
+                }).collect_view()}
+            </div>
+            <div class="flex flex-row flex-wrap gap-1">
+                {CompareMode::ALL.into_iter().map(|m| view! {
+                    <button class=move || if mode() == m { btn_active } else { btn_inactive } on:click=move |_| set_mode(m)>{move || m.label(&preferences())}</button>
+                }).collect_view()}
+            </div>
+            <span class:collapse=move || !selected.with(|s| s.is_empty())>"Pick vehicles to compare."</span>
+            <div class:collapse=move || selected.with(|s| s.is_empty())>
+                <LineChart chart id="curvecompare" />
+            </div>
+            <span>"Time based modes charge from 10% to 100% assuming the charger can always meet the curve."</span>
+        </details>
+    }
+}
@@ -0,0 +1,795 @@
+use std::time::Duration;
+
+use itertools::Itertools;
+use leptos::prelude::*;
+use leptos_meta::Title;
+use thiserror::Error;
+
+use super::{
+    create_sub_slice, format_minute_of_day, parse_minute_of_day, preferences, provide_preferences,
+    AcPhases, ChargeCurve, Charger, ChargerBuilder, ChargerFrame, ChargerKind, ChargerList, Energy,
+    LoadSharingStrategy, PercentFull, PlugOccupancyChart, Power, PreferencesEditor, ScenarioStart,
+    SimFrame, SimulationChart, Vehicle, VehicleChargeFrame, VehicleDropdown, VehicleSpec, VEHICLES,
+};
+
+/// Depot schedules run overnight, so times of day are kept as an offset from noon
+const DAY_START: Duration = Duration::from_secs(12 * 60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Length of each step of the schedule
+const SLOT: Duration = Duration::from_secs(60);
+
+/// How much slack a vehicle keeps before it stops waiting for cheaper power,
+/// tried smallest first so vehicles wait as long as every deadline allows
+const HOLD_BACK: [Duration; 6] = [
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(30 * 60),
+    Duration::from_secs(60 * 60),
+    Duration::from_secs(2 * 60 * 60),
+    Duration::from_secs(4 * 60 * 60),
+    Duration::from_secs(8 * 60 * 60),
+];
+
+/// The offset from noon of a time `minute_of_day` minutes after midnight
+const fn from_minute_of_day(minute_of_day: u16) -> Duration {
+    let since_midnight = minute_of_day as u64 * 60;
+    Duration::from_secs((since_midnight + DAY.as_secs() - DAY_START.as_secs()) % DAY.as_secs())
+}
+
+/// The offset from noon of a time of day
+const fn clock(hours: u16, minutes: u16) -> Duration {
+    from_minute_of_day(hours * 60 + minutes)
+}
+
+/// Minutes after midnight of an offset from noon
+fn minute_of_day(offset: Duration) -> u16 {
+    ((offset + DAY_START).as_secs() / 60 % (24 * 60)) as u16
+}
+
+fn slot_of(time: Duration) -> usize {
+    (time.as_secs() / SLOT.as_secs()) as usize
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+enum DepotObjective {
+    #[default]
+    PeakDemand,
+    EnergyCost,
+}
+
+/// Time of use pricing with a single off peak window
+#[derive(Clone, Copy, PartialEq)]
+struct Tariff {
+    /// dollars per kWh
+    peak_price: f64,
+    off_peak_price: f64,
+    off_peak_start: Duration,
+    off_peak_end: Duration,
+}
+
+impl Tariff {
+    fn price_at(&self, time: Duration) -> f64 {
+        let off_peak = if self.off_peak_start <= self.off_peak_end {
+            self.off_peak_start <= time && time < self.off_peak_end
+        } else {
+            time >= self.off_peak_start || time < self.off_peak_end
+        };
+        if off_peak {
+            self.off_peak_price
+        } else {
+            self.peak_price
+        }
+    }
+}
+
+/// A fleet vehicle's stay at the depot, it charges toward its unplug SOC until it leaves
+#[derive(Clone, PartialEq)]
+struct DepotVehicle {
+    vehicle: Vehicle,
+    arrive: Duration,
+    depart: Duration,
+}
+
+#[derive(Clone, PartialEq)]
+struct Depot {
+    vehicles: Vec<DepotVehicle>,
+    chargers: Vec<Charger>,
+    /// the most the whole site may draw at once
+    site_cap: Power,
+    tariff: Tariff,
+    objective: DepotObjective,
+}
+
+impl DepotVehicle {
+    /// A stay of the named vehicle from the list, charging from `soc` to `target` percent
+    fn new(name: &str, arrive: Duration, soc: f64, target: f64, depart: Duration) -> Self {
+        let spec = VEHICLES
+            .iter()
+            .find(|spec| spec.name == name)
+            .expect("vehicle is in the list");
+        let mut vehicle = Vehicle::new(spec, Energy::default(), Energy::default());
+        vehicle.set_soc(PercentFull::new(soc));
+        vehicle.set_unplug_soc(PercentFull::new(target));
+        Self {
+            vehicle,
+            arrive,
+            depart,
+        }
+    }
+}
+
+impl Default for Depot {
+    fn default() -> Self {
+        let vehicle = DepotVehicle::new;
+        let level_2 = Charger::new(
+            AcPhases::Single.power(48.0),
+            LoadSharingStrategy::None,
+            ChargerKind::Ac {
+                phases: AcPhases::Single,
+            },
+        );
+        Self {
+            vehicles: vec![
+                vehicle("Chevy Bolt 2022", clock(17, 30), 25.0, 90.0, clock(6, 0)),
+                vehicle("Chevy Bolt 2022", clock(18, 0), 20.0, 90.0, clock(6, 30)),
+                vehicle(
+                    "Tesla Model 3 LR AWD 2021",
+                    clock(18, 15),
+                    30.0,
+                    80.0,
+                    clock(7, 0),
+                ),
+                vehicle(
+                    "Tesla Model 3 LR AWD 2021",
+                    clock(19, 0),
+                    15.0,
+                    80.0,
+                    clock(5, 30),
+                ),
+                vehicle(
+                    "Rivian R1S Standard Pack",
+                    clock(18, 45),
+                    35.0,
+                    85.0,
+                    clock(6, 0),
+                ),
+                vehicle(
+                    "KIA EV6 Long Range AWD",
+                    clock(20, 0),
+                    10.0,
+                    90.0,
+                    clock(6, 0),
+                ),
+                vehicle(
+                    "GMC Hummer EV Pickup",
+                    clock(19, 30),
+                    20.0,
+                    80.0,
+                    clock(6, 0),
+                ),
+            ],
+            chargers: vec![level_2; 7],
+            site_cap: Power::from_kw(60.0),
+            tariff: Tariff {
+                peak_price: 0.32,
+                off_peak_price: 0.14,
+                off_peak_start: clock(22, 0),
+                off_peak_end: clock(6, 0),
+            },
+            objective: DepotObjective::PeakDemand,
+        }
+    }
+}
+
+/// Why a vehicle won't be ready when it leaves
+#[derive(Debug, Error, Clone, Copy, PartialEq)]
+enum DepotIssue {
+    #[error("leaves before it arrives")]
+    NoStay,
+    #[error("no plug is free when it arrives")]
+    NoFreePlug,
+    #[error("only reaches {0} before it leaves")]
+    MissesTarget(PercentFull),
+}
+
+fn reached_target(vehicle: &Vehicle) -> bool {
+    vehicle.current_charge.watt_hours + 1.0 >= vehicle.unplug_at.watt_hours
+}
+
+/// Where a vehicle is plugged in
+#[derive(Clone, Copy, PartialEq)]
+struct PlugAssignment {
+    charger: usize,
+    plug: usize,
+}
+
+/// Gives each vehicle, in order of arrival, the first charger with a plug free when it arrives.
+/// Vehicles stay plugged in until they leave.
+fn assign_plugs(depot: &Depot) -> Vec<Option<PlugAssignment>> {
+    // when the vehicle on each plug leaves
+    let mut occupied = depot
+        .chargers
+        .iter()
+        .map(|charger| vec![Duration::ZERO; charger.num_plugs() as usize])
+        .collect::<Vec<_>>();
+    let mut plugs = vec![None; depot.vehicles.len()];
+    for (index, stay) in depot
+        .vehicles
+        .iter()
+        .enumerate()
+        .sorted_by_key(|(_, stay)| stay.arrive)
+    {
+        if stay.depart <= stay.arrive {
+            continue;
+        }
+        for (charger, departures) in occupied.iter_mut().enumerate() {
+            if let Some(plug) = departures.iter().position(|depart| *depart <= stay.arrive) {
+                departures[plug] = stay.depart;
+                plugs[index] = Some(PlugAssignment { charger, plug });
+                break;
+            }
+        }
+    }
+    plugs
+}
+
+/// A vehicle asking for power during one slot
+struct SlotRequest {
+    vehicle: usize,
+    plug: PlugAssignment,
+    power: Power,
+    /// time to spare if it charged flat out from now on
+    slack: Duration,
+    waits: bool,
+}
+
+/// The depot run through the night with one power cap
+struct DepotRun {
+    frames: Vec<SimFrame>,
+    /// each vehicle as it leaves
+    vehicles: Vec<Vehicle>,
+    peak: Power,
+    cost: f64,
+}
+
+impl DepotRun {
+    fn missed(&self) -> usize {
+        self.vehicles.iter().filter(|v| !reached_target(v)).count()
+    }
+}
+
+/// Steps through the night a slot at a time, sharing out at most `cap` across the site.
+/// Vehicles closest to missing their departure are served first. With `hold_back`,
+/// vehicles with more slack than it wait while cheaper power is coming before they leave.
+fn run_depot(
+    depot: &Depot,
+    plugs: &[Option<PlugAssignment>],
+    cap: Power,
+    hold_back: Option<Duration>,
+) -> DepotRun {
+    let mut vehicles = depot
+        .vehicles
+        .iter()
+        .map(|stay| stay.vehicle.clone())
+        .collect::<Vec<_>>();
+    // the fastest each vehicle could charge on its plug, for working out its slack
+    let curves = depot
+        .vehicles
+        .iter()
+        .zip(plugs)
+        .map(|(stay, plug)| {
+            plug.map(|plug| {
+                let charger = &depot.chargers[plug.charger];
+                let spec = stay.vehicle.spec_details();
+                let limit = charger.kind.vehicle_limit(spec, charger.max_per_plug());
+                spec.charge_curve.limited(Some(limit), None)
+            })
+        })
+        .collect::<Vec<Option<ChargeCurve>>>();
+    let first = slot_of(
+        depot
+            .vehicles
+            .iter()
+            .map(|s| s.arrive)
+            .min()
+            .unwrap_or_default(),
+    );
+    let last = slot_of(
+        depot
+            .vehicles
+            .iter()
+            .map(|s| s.depart)
+            .max()
+            .unwrap_or_default(),
+    );
+    let prices = (first..last)
+        .map(|slot| depot.tariff.price_at(SLOT * slot as u32))
+        .collect::<Vec<_>>();
+    // the next slot with cheaper power, waiting only pays off if it comes before departure
+    let mut next_cheaper = vec![usize::MAX; prices.len()];
+    let mut pending: Vec<usize> = vec![];
+    for (step, price) in prices.iter().enumerate() {
+        while let Some(earlier) = pending.pop_if(|earlier| prices[*earlier] > *price) {
+            next_cheaper[earlier] = step;
+        }
+        pending.push(step);
+    }
+    let slot_hours = SLOT.as_secs_f64() / 60.0 / 60.0;
+    let mut frames = vec![];
+    let (mut peak, mut cost) = (Power::default(), 0.0);
+    for (step, price) in prices.iter().enumerate() {
+        let slot = first + step;
+        let now = SLOT * slot as u32;
+        let mut requests = vec![];
+        for (index, (stay, plug)) in depot.vehicles.iter().zip(plugs).enumerate() {
+            let (Some(plug), Some(curve)) = (*plug, &curves[index]) else {
+                continue;
+            };
+            if slot < slot_of(stay.arrive) || slot >= slot_of(stay.depart) {
+                continue;
+            }
+            let charger = &depot.chargers[plug.charger];
+            let vehicle = &mut vehicles[index];
+            let plug_max = charger.max_per_plug();
+            let request = charger
+                .kind
+                .power_request(vehicle, plug_max, plug_max)
+                .unwrap_or_default();
+            let remaining = (vehicle.unplug_at.watt_hours - vehicle.current_charge.watt_hours)
+                .max(0.0)
+                / 1000.0
+                / slot_hours;
+            let needed = curve.charge_time(
+                vehicle.spec_details().battery_max,
+                vehicle.soc(),
+                vehicle.unplug_at_soc(),
+            );
+            let slack = stay.depart.saturating_sub(now).saturating_sub(needed);
+            let waits = hold_back.is_some_and(|hold| {
+                slack > hold && next_cheaper[step] < slot_of(stay.depart) - first
+            });
+            requests.push(SlotRequest {
+                vehicle: index,
+                plug,
+                power: request.min(Power::from_kw(remaining)),
+                slack,
+                waits,
+            });
+        }
+        requests.sort_by_key(|request| request.slack);
+        let mut site_left = cap;
+        let mut charger_used = vec![Power::default(); depot.chargers.len()];
+        let mut vehicles_charging = vec![];
+        let mut energy_dispensed = Energy::default();
+        for request in requests {
+            let charger = request.plug.charger;
+            let charger_left = depot.chargers[charger].grid_connection - charger_used[charger];
+            let power = if request.waits {
+                Power::default()
+            } else {
+                request
+                    .power
+                    .min(site_left)
+                    .min(charger_left)
+                    .max(Power::default())
+            };
+            site_left = site_left - power;
+            charger_used[charger] += power;
+            let vehicle = &mut vehicles[request.vehicle];
+            energy_dispensed += vehicle.charge(power, SLOT);
+            vehicles_charging.push(VehicleChargeFrame {
+                allocated_power: power,
+                vehicle_id: request.vehicle,
+                charger_id: charger,
+                plug: request.plug.plug,
+                soc: vehicle.soc(),
+                energy_added: vehicle.current_charge
+                    - depot.vehicles[request.vehicle].vehicle.current_charge,
+            });
+        }
+        let total = cap - site_left;
+        peak = peak.max(total);
+        cost += energy_dispensed.as_kwh() * price;
+        frames.push(SimFrame {
+            energy_dispensed,
+            solar_power: Power::default(),
+            grid_power: total,
+            chargers: charger_used
+                .iter()
+                .zip(&depot.chargers)
+                .enumerate()
+                .map(|(charger_id, (active_power, charger))| ChargerFrame {
+                    charger_id,
+                    active_power: *active_power,
+                    unused_power: charger.grid_connection - *active_power,
+                })
+                .collect(),
+            vehicles_charging,
+            queue_length: 0,
+            duration: SLOT * (step + 1) as u32,
+        });
+    }
+    DepotRun {
+        frames,
+        vehicles,
+        peak,
+        cost,
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct DepotSchedule {
+    /// starting when the first vehicle arrives
+    frames: Vec<SimFrame>,
+    start: Duration,
+    /// the charger each vehicle is plugged into
+    plugs: Vec<Option<PlugAssignment>>,
+    depart_soc: Vec<PercentFull>,
+    issues: Vec<Option<DepotIssue>>,
+    peak: Power,
+    cost: f64,
+    energy: Energy,
+}
+
+/// Plans the night so every vehicle that can make its departure does,
+/// then lowers the site's peak demand or its energy bill.
+fn schedule_depot(depot: &Depot) -> DepotSchedule {
+    let plugs = assign_plugs(depot);
+    let best_effort = run_depot(depot, &plugs, depot.site_cap, None);
+    let missed = best_effort.missed();
+    let run = match depot.objective {
+        DepotObjective::PeakDemand => {
+            // the lowest cap that doesn't leave any more vehicles short
+            let (mut low, mut high) = (0, depot.site_cap.watts);
+            let mut best = best_effort;
+            while high - low > 100 {
+                let middle = low + (high - low) / 2;
+                let run = run_depot(depot, &plugs, Power { watts: middle }, None);
+                if run.missed() <= missed {
+                    high = middle;
+                    best = run;
+                } else {
+                    low = middle;
+                }
+            }
+            best
+        }
+        DepotObjective::EnergyCost => HOLD_BACK
+            .iter()
+            .map(|hold| run_depot(depot, &plugs, depot.site_cap, Some(*hold)))
+            .find(|run| run.missed() <= missed)
+            .unwrap_or(best_effort),
+    };
+    let issues = depot
+        .vehicles
+        .iter()
+        .zip(&plugs)
+        .zip(&run.vehicles)
+        .map(|((stay, plug), vehicle)| {
+            if stay.depart <= stay.arrive {
+                Some(DepotIssue::NoStay)
+            } else if plug.is_none() {
+                Some(DepotIssue::NoFreePlug)
+            } else if !reached_target(vehicle) {
+                Some(DepotIssue::MissesTarget(vehicle.soc()))
+            } else {
+                None
+            }
+        })
+        .collect();
+    DepotSchedule {
+        energy: run.frames.iter().map(|f| f.energy_dispensed).sum(),
+        start: SLOT
+            * slot_of(
+                depot
+                    .vehicles
+                    .iter()
+                    .map(|s| s.arrive)
+                    .min()
+                    .unwrap_or_default(),
+            ) as u32,
+        depart_soc: run.vehicles.iter().map(Vehicle::soc).collect(),
+        frames: run.frames,
+        plugs,
+        issues,
+        peak: run.peak,
+        cost: run.cost,
+    }
+}
+
+#[component]
+fn DepotVehicleBuilder(depot: RwSignal<Depot>) -> impl IntoView {
+    let (vehicle_spec, set_vehicle_spec) = signal::<Option<&'static VehicleSpec>>(None);
+    let (arrive, set_arrive) = signal(clock(18, 0));
+    let (depart, set_depart) = signal(clock(6, 0));
+    let (soc, set_soc) = signal(20.0_f64);
+    let (target, set_target) = signal(90.0_f64);
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors";
+    view! {
+        <div class="flex flex-col gap-1">
+            <h4 class="text-xl">"Add Vehicle: "</h4>
+            <VehicleDropdown current_vehicle=vehicle_spec set_vehicle=set_vehicle_spec />
+            <div class="flex flex-row flex-wrap gap-2 items-center">
+                <span>"Arrives"</span>
+                <input type="time" class=input prop:value=move || format_minute_of_day(minute_of_day(arrive()))
+                    on:change=move |e| { if let Some(time) = parse_minute_of_day(&event_target_value(&e)).map(from_minute_of_day) { set_arrive(time) } } />
+                <span>"with battery%"</span>
+                <input class=input prop:value=soc
+                    on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() { set_soc(value.clamp(0.0, 100.0)) } } />
+                <span>"Leaves"</span>
+                <input type="time" class=input prop:value=move || format_minute_of_day(minute_of_day(depart()))
+                    on:change=move |e| { if let Some(time) = parse_minute_of_day(&event_target_value(&e)).map(from_minute_of_day) { set_depart(time) } } />
+                <span>"needing battery%"</span>
+                <input class=input prop:value=target
+                    on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() { set_target(value.clamp(0.0, 100.0)) } } />
+                <button class="bg-amber-500 dark:bg-teal-600 text-white hover:bg-amber-600 dark:hover:bg-teal-500 rounded-lg p-2 font-medium transition-colors disabled:opacity-50"
+                    disabled=move || vehicle_spec.with(|spec| spec.is_none())
+                    on:click=move |_| {
+                        let Some(spec) = vehicle_spec.get_untracked() else {
+                            return;
+                        };
+                        let mut vehicle = Vehicle::new(spec, Energy::default(), Energy::default());
+                        vehicle.set_soc(PercentFull::new(soc.get_untracked()));
+                        vehicle.set_unplug_soc(PercentFull::new(target.get_untracked()));
+                        depot.update(|depot| depot.vehicles.push(DepotVehicle { vehicle, arrive: arrive.get_untracked(), depart: depart.get_untracked() }));
+                    }>"Add vehicle +"</button>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn DepotVehicleTable(depot: RwSignal<Depot>, schedule: Memo<DepotSchedule>) -> impl IntoView {
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors";
+    let update = move |index: usize, f: &dyn Fn(&mut DepotVehicle)| {
+        depot.update(|depot| {
+            if let Some(stay) = depot.vehicles.get_mut(index) {
+                f(stay)
+            }
+        })
+    };
+    let stay = move |index: usize| depot.with(|depot| depot.vehicles.get(index).cloned());
+    view! {
+        <table class="w-fit">
+            <thead>
+                <tr class="text-left">
+                    <th class="pr-4">"#"</th><th class="pr-4">"Vehicle"</th><th class="pr-4">"Arrives"</th><th class="pr-4">"with"</th>
+                    <th class="pr-4">"Leaves"</th><th class="pr-4">"needing"</th><th class="pr-4">"Charger"</th><th class="pr-4">"Leaves with"</th><th></th>
+                </tr>
+            </thead>
+            <tbody>
+                {move || (0..depot.with(|depot| depot.vehicles.len())).map(|index| {
+                    let issue = move || schedule.with(|s| s.issues.get(index).copied().flatten());
+                    view! {
+                        <tr class:text-red-600=move || issue().is_some()>
+                            <td class="pr-4">{index + 1}</td>
+                            <td class="pr-4">{move || stay(index).map(|stay| stay.vehicle.spec_details().name)}</td>
+                            <td class="pr-4"><input type="time" class=input prop:value=move || stay(index).map(|stay| format_minute_of_day(minute_of_day(stay.arrive)))
+                                on:change=move |e| { if let Some(time) = parse_minute_of_day(&event_target_value(&e)).map(from_minute_of_day) { update(index, &|stay| stay.arrive = time) } } /></td>
+                            <td class="pr-4"><input class=input prop:value=move || stay(index).map(|stay| stay.vehicle.soc().as_float())
+                                on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() { update(index, &|stay| stay.vehicle.set_soc(PercentFull::new(value.clamp(0.0, 100.0)))) } } /></td>
+                            <td class="pr-4"><input type="time" class=input prop:value=move || stay(index).map(|stay| format_minute_of_day(minute_of_day(stay.depart)))
+                                on:change=move |e| { if let Some(time) = parse_minute_of_day(&event_target_value(&e)).map(from_minute_of_day) { update(index, &|stay| stay.depart = time) } } /></td>
+                            <td class="pr-4"><input class=input prop:value=move || stay(index).map(|stay| stay.vehicle.unplug_at_soc().as_float())
+                                on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() { update(index, &|stay| stay.vehicle.set_unplug_soc(PercentFull::new(value.clamp(0.0, 100.0)))) } } /></td>
+                            <td class="pr-4">{move || schedule.with(|s| s.plugs.get(index).copied().flatten().map(|plug| format!("#{} plug {}", plug.charger + 1, plug.plug + 1)).unwrap_or_default())}</td>
+                            <td class="pr-4">{move || issue().map(|issue| issue.to_string()).or_else(|| schedule.with(|s| s.depart_soc.get(index).map(|soc| preferences().percent(*soc))))}</td>
+                            <td><button class="hover:bg-red-500 bg-red-600 rounded-lg w-10 border border-red-700 text-white transition-colors"
+                                on:click=move |_| depot.update(|depot| { depot.vehicles.remove(index); })>"X"</button></td>
+                        </tr>
+                    }
+                }).collect_view()}
+            </tbody>
+        </table>
+    }
+}
+
+/// Schedules overnight charging for a fleet depot so vehicles are ready to leave in the morning
+#[component]
+pub fn DepotScheduler() -> impl IntoView {
+    provide_preferences();
+    let depot = RwSignal::new(Depot::default());
+    let schedule = Memo::new(move |_| depot.with(schedule_depot));
+    let set_depot = SignalSetter::map(move |value| depot.set(value));
+    let (chargers, set_chargers) =
+        create_sub_slice(depot, set_depot, |d| &d.chargers, |d| &mut d.chargers);
+    let vehicles = Signal::derive(move || {
+        depot.with(|depot| {
+            depot
+                .vehicles
+                .iter()
+                .map(|stay| stay.vehicle.spec_details())
+                .collect::<Vec<_>>()
+        })
+    });
+    let frames = Signal::derive(move || schedule.with(|s| s.frames.clone()));
+    // the depot plans a typical night rather than a date, so only the time of day is set
+    let chart_start = Signal::derive(move || {
+        schedule.with(|s| ScenarioStart {
+            minute_of_day: minute_of_day(s.start),
+            ..Default::default()
+        })
+    });
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors";
+    let number_input = move |get: fn(&Depot) -> f64, set: fn(&mut Depot, f64)| {
+        view! {
+            <input class=input prop:value=move || depot.with(get)
+                on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                    depot.update(|depot| set(depot, value));
+                } } />
+        }
+    };
+    let clock_input = move |get: fn(&Depot) -> Duration, set: fn(&mut Depot, Duration)| {
+        view! {
+            <input type="time" class=input prop:value=move || format_minute_of_day(minute_of_day(depot.with(get)))
+                on:change=move |e| { if let Some(time) = parse_minute_of_day(&event_target_value(&e)).map(from_minute_of_day) {
+                    depot.update(|depot| set(depot, time));
+                } } />
+        }
+    };
+    let objective_button = move |objective: DepotObjective, label: &'static str| {
+        view! {
+            <button class="rounded-lg p-1 border border-slate-300 dark:border-slate-500 transition-colors"
+                class=("bg-amber-500", move || depot.with(|d| d.objective == objective))
+                class=("dark:bg-teal-600", move || depot.with(|d| d.objective == objective))
+                on:click=move |_| depot.update(|depot| depot.objective = objective)>{label}</button>
+        }
+    };
+    view! {
+        <Title text="EV Fleet Depot Scheduler" />
+        <div class="flex flex-col gap-2">
+            <div class="flex flex-col gap-1">
+                <h2 class="text-3xl font-bold gradient-text">"Fleet Depot Scheduler"</h2>
+                <span>"Plans overnight charging so every vehicle reaches its target before it leaves, while keeping the site's peak demand or energy bill down."</span>
+            </div>
+            <PreferencesEditor />
+            <div class="grid grid-cols-[auto_auto] gap-1 items-center w-fit">
+                <span>"Optimize for"</span>
+                <div class="flex flex-row gap-1">
+                    {objective_button(DepotObjective::PeakDemand, "lowest peak demand")}
+                    {objective_button(DepotObjective::EnergyCost, "lowest energy cost")}
+                </div>
+                <span>"Site power cap kW"</span>
+                {number_input(|depot| depot.site_cap.as_kw(), |depot, value| depot.site_cap = Power::from_kw(value.max(0.0)))}
+                <span>"Peak $/kWh"</span>
+                {number_input(|depot| depot.tariff.peak_price, |depot, value| depot.tariff.peak_price = value.max(0.0))}
+                <span>"Off peak $/kWh"</span>
+                {number_input(|depot| depot.tariff.off_peak_price, |depot, value| depot.tariff.off_peak_price = value.max(0.0))}
+                <span>"Off peak from"</span>
+                {clock_input(|depot| depot.tariff.off_peak_start, |depot, time| depot.tariff.off_peak_start = time)}
+                <span>"Off peak until"</span>
+                {clock_input(|depot| depot.tariff.off_peak_end, |depot, time| depot.tariff.off_peak_end = time)}
+            </div>
+            {move || schedule.with(|schedule| {
+                let short = schedule.issues.iter().flatten().count();
+                let (peak, energy) = (schedule.peak, schedule.energy);
+                view! {
+                    <div class="flex flex-row flex-wrap gap-4 text-md">
+                        <div>"peak demand: "{move || preferences().power(peak)}</div>
+                        <div>"energy dispensed: "{move || preferences().energy(energy)}</div>
+                        <div>{format!("energy cost: ${}", preferences().number(schedule.cost, 2))}</div>
+                    </div>
+                    <span role="alert" class="text-red-600" class:collapse=move || short == 0>
+                        {short}" vehicle(s) can't be ready in time, the schedule charges them as far as it can"
+                    </span>
+                }
+            })}
+            <SimulationChart vehicles data=frames start=chart_start />
+            <PlugOccupancyChart vehicles data=frames start=chart_start />
+            <DepotVehicleTable depot schedule />
+            <div class="flex flex-col md:flex-row gap-4">
+                <DepotVehicleBuilder depot />
+                <div class="flex flex-col gap-1">
+                    <ChargerList chargers set_chargers set_chargers_intermediate=set_chargers />
+                    <ChargerBuilder chargers set_chargers />
+                </div>
+            </div>
+            <span>"Vehicles take the first free plug when they arrive and stay plugged in until they leave. Load sharing chargers are limited to their total power."</span>
+        </div>
+    }
+    .into_any()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depot(vehicles: Vec<DepotVehicle>, chargers: Vec<Charger>, site_cap: f64) -> Depot {
+        Depot {
+            vehicles,
+            chargers,
+            site_cap: Power::from_kw(site_cap),
+            ..Default::default()
+        }
+    }
+
+    fn dc_fast(kw: f64) -> Charger {
+        Charger::new(
+            Power::from_kw(kw),
+            LoadSharingStrategy::None,
+            ChargerKind::Dc,
+        )
+    }
+
+    #[test]
+    fn times_of_day_are_offsets_from_noon() {
+        assert_eq!(clock(12, 0), Duration::ZERO);
+        assert_eq!(clock(6, 30), Duration::from_secs((18 * 60 + 30) * 60));
+        for time in ["00:00", "06:30", "12:00", "23:59"] {
+            let offset = parse_minute_of_day(time).map(from_minute_of_day).unwrap();
+            assert_eq!(format_minute_of_day(minute_of_day(offset)), time);
+        }
+    }
+
+    #[test]
+    fn every_vehicle_that_fits_is_ready_under_a_lowered_peak() {
+        let depot = depot(
+            vec![
+                DepotVehicle::new("Chevy Bolt 2022", clock(18, 0), 20.0, 80.0, clock(6, 0)),
+                DepotVehicle::new("Chevy Bolt 2022", clock(19, 0), 30.0, 90.0, clock(7, 0)),
+            ],
+            vec![dc_fast(50.0); 2],
+            100.0,
+        );
+        let schedule = schedule_depot(&depot);
+        assert!(schedule.issues.iter().all(Option::is_none));
+        assert_eq!(schedule.start, clock(18, 0));
+        // nobody is in a hurry overnight, so the peak drops well under the site's cap
+        assert!(schedule.peak < Power::from_kw(20.0));
+        assert!(schedule
+            .frames
+            .iter()
+            .all(|frame| frame.grid_power <= schedule.peak));
+    }
+
+    #[test]
+    fn vehicles_that_cannot_make_their_deadline_are_flagged() {
+        let level_2 = Depot::default().chargers[0].clone();
+        let depot = depot(
+            vec![
+                // an hour on a level 2 charger is nowhere near enough
+                DepotVehicle::new("Chevy Bolt 2022", clock(18, 0), 10.0, 90.0, clock(19, 0)),
+                // the only plug is taken
+                DepotVehicle::new("Chevy Bolt 2022", clock(18, 30), 10.0, 90.0, clock(6, 0)),
+                DepotVehicle::new("Chevy Bolt 2022", clock(18, 0), 10.0, 90.0, clock(18, 0)),
+            ],
+            vec![level_2],
+            60.0,
+        );
+        let schedule = schedule_depot(&depot);
+        let Some(DepotIssue::MissesTarget(reached)) = schedule.issues[0] else {
+            panic!("the first vehicle can't be ready");
+        };
+        assert!(PercentFull::new(10.0) < reached && reached < PercentFull::new(90.0));
+        assert_eq!(schedule.issues[1], Some(DepotIssue::NoFreePlug));
+        assert_eq!(schedule.issues[2], Some(DepotIssue::NoStay));
+    }
+
+    #[test]
+    fn the_vehicle_leaving_first_gets_the_capped_power_first() {
+        let depot = depot(
+            vec![
+                DepotVehicle::new("Chevy Bolt 2022", clock(18, 0), 20.0, 80.0, clock(6, 0)),
+                DepotVehicle::new("Chevy Bolt 2022", clock(18, 0), 20.0, 60.0, clock(19, 0)),
+            ],
+            vec![dc_fast(50.0); 2],
+            40.0,
+        );
+        let schedule = schedule_depot(&depot);
+        assert!(schedule.issues.iter().all(Option::is_none));
+        assert!(schedule.peak <= Power::from_kw(40.0));
+        let first = &schedule.frames[0].vehicles_charging;
+        let power = |id| {
+            first
+                .iter()
+                .find(|v| v.vehicle_id == id)
+                .map(|v| v.allocated_power)
+                .unwrap_or_default()
+        };
+        assert!(power(1) > power(0));
+        assert!(schedule
+            .frames
+            .iter()
+            .all(|frame| frame.grid_power <= Power::from_kw(40.0)));
+    }
+}
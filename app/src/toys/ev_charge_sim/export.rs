@@ -0,0 +1,394 @@
+use leptos::prelude::*;
+use serde::Serialize;
+use std::iter;
+use web_sys::{
+    js_sys::Array, wasm_bindgen::JsCast, wasm_bindgen::JsValue, Blob, BlobPropertyBag,
+    HtmlAnchorElement, Url,
+};
+
+use super::{
+    get_charge_data_from_vehicles, Energy, SimChargerSeriesData, SimFrame, SimSummary,
+    SimVehicleSeriesData, VehicleSpec,
+};
+
+/// A single named column of the exported timeline, `None` where the series has no sample
+#[derive(Serialize)]
+struct Column {
+    name: String,
+    values: Vec<Option<f64>>,
+}
+
+/// Column oriented copy of a simulation run, one row per [`SimFrame`]
+struct SimTable {
+    time_minutes: Vec<f64>,
+    columns: Vec<Column>,
+}
+
+impl SimTable {
+    fn new(vehicles: Vec<&'static VehicleSpec>, frames: &Vec<SimFrame>) -> Self {
+        let (vehicle_curves, chargers) = get_charge_data_from_vehicles(vehicles, frames);
+        let time_minutes = frames
+            .iter()
+            .map(|frame| frame.duration.as_secs_f64() / 60.0)
+            .collect::<Vec<_>>();
+        let rows = time_minutes.len();
+        // series only carry samples for the frames they were active in, so line them back up by time
+        let align = |series: &[Vec<f64>]| {
+            let mut values = vec![None; rows];
+            for point in series {
+                if let [time, value] = point.as_slice() {
+                    if let Ok(row) = time_minutes.binary_search_by(|t| t.total_cmp(time)) {
+                        values[row] = Some(*value);
+                    }
+                }
+            }
+            values
+        };
+        let mut columns = vec![];
+        let mut sum = Energy::default();
+        columns.push(Column {
+            name: "energy_dispensed_kwh".to_string(),
+            values: frames
+                .iter()
+                .map(|frame| {
+                    sum += frame.energy_dispensed;
+                    Some(sum.as_kwh())
+                })
+                .collect(),
+        });
+        columns.push(Column {
+            name: "grid_kw".to_string(),
+            values: frames
+                .iter()
+                .map(|frame| Some(frame.grid_power.as_kw()))
+                .collect(),
+        });
+        columns.push(Column {
+            name: "solar_kw".to_string(),
+            values: frames
+                .iter()
+                .map(|frame| Some(frame.solar_power.as_kw()))
+                .collect(),
+        });
+        for SimVehicleSeriesData {
+            spec,
+            id,
+            data,
+            soc,
+            energy,
+        } in vehicle_curves
+        {
+            columns.push(Column {
+                name: format!("#{} {} allocated_kw", id + 1, spec.name),
+                values: align(&data),
+            });
+            columns.push(Column {
+                name: format!("#{} {} soc_percent", id + 1, spec.name),
+                values: align(&soc),
+            });
+            columns.push(Column {
+                name: format!("#{} {} energy_added_kwh", id + 1, spec.name),
+                values: align(&energy),
+            });
+        }
+        for SimChargerSeriesData {
+            id,
+            used_power,
+            unused_power,
+        } in chargers
+        {
+            columns.push(Column {
+                name: format!("#{} charger active_kw", id + 1),
+                values: align(&used_power),
+            });
+            columns.push(Column {
+                name: format!("#{} charger unused_kw", id + 1),
+                values: align(&unused_power),
+            });
+        }
+        Self {
+            time_minutes,
+            columns,
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv =
+            csv_row(iter::once("time_minutes").chain(self.columns.iter().map(|c| c.name.as_str())));
+        for (row, time) in self.time_minutes.iter().enumerate() {
+            let values = self.columns.iter().map(|c| {
+                c.values[row]
+                    .map(|value| format!("{value:.3}"))
+                    .unwrap_or_default()
+            });
+            csv += &csv_row(iter::once(format!("{time:.3}")).chain(values));
+        }
+        csv
+    }
+}
+
+/// Joins the cells into a single CSV line, quoting any that need it
+fn csv_row<S: AsRef<str>>(cells: impl Iterator<Item = S>) -> String {
+    let mut line = cells
+        .map(|cell| {
+            let cell = cell.as_ref();
+            if cell.contains([',', '"', '\n']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+/// Flattened version of [`SimSummary`] with plain units for spreadsheets
+#[derive(Serialize)]
+struct SummaryExport {
+    energy_dispensed_kwh: f64,
+    peak_demand_kw: f64,
+    grid_import_kwh: f64,
+    solar_generated_kwh: f64,
+    solar_used_kwh: f64,
+    minutes_running: f64,
+    vehicles: usize,
+    chargers: usize,
+    simulation_interval_seconds: f64,
+}
+
+impl From<&SimSummary> for SummaryExport {
+    fn from(summary: &SimSummary) -> Self {
+        Self {
+            energy_dispensed_kwh: summary.energy_dispensed.as_kwh(),
+            peak_demand_kw: summary.peak_demand.as_kw(),
+            grid_import_kwh: summary.grid_import.as_kwh(),
+            solar_generated_kwh: summary.solar_generated.as_kwh(),
+            solar_used_kwh: summary.solar_used.as_kwh(),
+            minutes_running: summary.duration.as_secs_f64() / 60.0,
+            vehicles: summary.vehicles,
+            chargers: summary.chargers,
+            simulation_interval_seconds: summary.simulation_step_time.as_secs_f64(),
+        }
+    }
+}
+
+impl SummaryExport {
+    fn to_csv(&self) -> String {
+        csv_row(
+            [
+                "energy_dispensed_kwh",
+                "peak_demand_kw",
+                "grid_import_kwh",
+                "solar_generated_kwh",
+                "solar_used_kwh",
+                "minutes_running",
+                "vehicles",
+                "chargers",
+                "simulation_interval_seconds",
+            ]
+            .into_iter(),
+        ) + &csv_row(
+            [
+                self.energy_dispensed_kwh.to_string(),
+                self.peak_demand_kw.to_string(),
+                self.grid_import_kwh.to_string(),
+                self.solar_generated_kwh.to_string(),
+                self.solar_used_kwh.to_string(),
+                self.minutes_running.to_string(),
+                self.vehicles.to_string(),
+                self.chargers.to_string(),
+                self.simulation_interval_seconds.to_string(),
+            ]
+            .into_iter(),
+        )
+    }
+}
+
+/// JSON export, the timeline is stored by column so each series can be loaded straight into a dataframe
+#[derive(Serialize)]
+struct JsonExport {
+    summary: SummaryExport,
+    timeline: Vec<Column>,
+}
+
+impl JsonExport {
+    fn new(summary: &SimSummary, table: SimTable) -> Self {
+        let time = Column {
+            name: "time_minutes".to_string(),
+            values: table.time_minutes.into_iter().map(Some).collect(),
+        };
+        let timeline = iter::once(time).chain(table.columns).collect();
+        Self {
+            summary: summary.into(),
+            timeline,
+        }
+    }
+}
+
+/// Hands the given text to the browser as a file download
+pub(super) fn download_file(file_name: &str, mime_type: &str, contents: &str) {
+    let parts = Array::of1(&JsValue::from_str(contents));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let Ok(blob) = Blob::new_with_str_sequence_and_options(&parts, &options) else {
+        log::error!("unable to create {file_name}");
+        return;
+    };
+    let Ok(url) = Url::create_object_url_with_blob(&blob) else {
+        return;
+    };
+    if let Some(anchor) = document()
+        .create_element("a")
+        .ok()
+        .and_then(|e| e.dyn_into::<HtmlAnchorElement>().ok())
+    {
+        anchor.set_href(&url);
+        anchor.set_download(file_name);
+        anchor.click();
+    }
+    let _ = Url::revoke_object_url(&url);
+}
+
+#[component]
+pub(super) fn SimulationExport(
+    vehicles: Signal<Vec<&'static VehicleSpec>>,
+    data: Signal<Vec<SimFrame>>,
+    summary: SimSummary,
+) -> impl IntoView {
+    let button = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors";
+    let table = move || data.with(|frames| SimTable::new(vehicles.get_untracked(), frames));
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 items-center">
+            "Export results:"
+            <button class=button on:click=move |_| {
+                download_file("ev-sim-timeline.csv", "text/csv", &table().to_csv());
+            }>"Timeline CSV"</button>
+            <button class=button on:click=move |_| {
+                download_file("ev-sim-summary.csv", "text/csv", &SummaryExport::from(&summary).to_csv());
+            }>"Summary CSV"</button>
+            <button class=button on:click=move |_| {
+                let export = JsonExport::new(&summary, table());
+                match serde_json::to_string_pretty(&export) {
+                    Ok(json) => download_file("ev-sim-results.json", "application/json", &json),
+                    Err(e) => log::error!("unable to serialize export {e}"),
+                }
+            }>"JSON"</button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        Charger, ChargerKind, LoadSharingStrategy, Power, ScenarioStart, Sim, SitePolicy, Vehicle,
+        VEHICLES,
+    };
+    use super::*;
+    use std::{collections::VecDeque, time::Duration};
+
+    /// Splits one CSV line back into cells, undoing the quoting from [`csv_row`]
+    fn parse_row(line: &str) -> Vec<String> {
+        let mut cells = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.trim_end_matches('\n').chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cells.last_mut().unwrap().push('"');
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => cells.push(String::new()),
+                c => cells.last_mut().unwrap().push(c),
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn names_with_commas_or_quotes_read_back() {
+        let table = SimTable {
+            time_minutes: vec![0.0, 1.5],
+            columns: vec![
+                Column {
+                    name: "#1 Kia \"EV6\", GT allocated_kw".to_string(),
+                    values: vec![Some(150.0), None],
+                },
+                Column {
+                    name: "plain".to_string(),
+                    values: vec![None, Some(0.25)],
+                },
+            ],
+        };
+        let csv = table.to_csv();
+        let rows = csv.lines().map(parse_row).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["time_minutes", "#1 Kia \"EV6\", GT allocated_kw", "plain"],
+                vec!["0.000", "150.000", ""],
+                vec!["1.500", "", "0.250"],
+            ]
+        );
+    }
+
+    #[test]
+    fn exports_cover_every_frame_of_a_run() {
+        let spec = &VEHICLES[0];
+        let vehicle = Vehicle::new(
+            spec,
+            Energy {
+                watt_hours: spec.battery_max.watt_hours * 0.1,
+            },
+            Energy {
+                watt_hours: spec.battery_max.watt_hours * 0.8,
+            },
+        );
+        let chargers = vec![Charger::new(
+            Power::from_kw(150.0),
+            LoadSharingStrategy::None,
+            ChargerKind::Dc,
+        )];
+        let step = Duration::from_secs(60);
+        let mut sim = Sim::new(
+            &VecDeque::from([vehicle]),
+            chargers.clone(),
+            SitePolicy::None,
+            ScenarioStart::default(),
+            1,
+            None,
+            step,
+        );
+        let frames = sim.run();
+        let summary = SimSummary::new(&frames, 1, &chargers, step);
+
+        let table = SimTable::new(vec![spec], &frames);
+        let csv = table.to_csv();
+        let rows = csv.lines().map(parse_row).collect::<Vec<_>>();
+        assert_eq!(rows.len(), frames.len() + 1);
+        assert!(rows.iter().all(|row| row.len() == rows[0].len()));
+        let dispensed = rows[0]
+            .iter()
+            .position(|name| name == "energy_dispensed_kwh")
+            .unwrap();
+        let last: f64 = rows.last().unwrap()[dispensed].parse().unwrap();
+        assert!((last - summary.energy_dispensed.as_kwh()).abs() < 0.001);
+
+        let summary_csv = SummaryExport::from(&summary).to_csv();
+        let summary_rows = summary_csv.lines().map(parse_row).collect::<Vec<_>>();
+        assert_eq!(summary_rows.len(), 2);
+        assert_eq!(summary_rows[0].len(), summary_rows[1].len());
+
+        let json = serde_json::to_value(JsonExport::new(&summary, table)).unwrap();
+        assert_eq!(json["summary"]["vehicles"], 1);
+        assert_eq!(json["timeline"][0]["name"], "time_minutes");
+        assert_eq!(json["timeline"].as_array().unwrap().len(), rows[0].len());
+        assert!(json["timeline"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|column| column["values"].as_array().unwrap().len() == frames.len()));
+    }
+}
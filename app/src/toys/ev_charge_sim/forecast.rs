@@ -0,0 +1,263 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{preferences, Energy, SimSummary};
+
+const DAYS_PER_YEAR: f64 = 365.0;
+const WEEKS_PER_YEAR: f64 = DAYS_PER_YEAR / 7.0;
+
+/// Seasons in the order of [`Forecast::seasons`], with how many days of the year each covers.
+/// Winter is December to February.
+const SEASONS: [(&str, f64); 4] = [
+    ("Winter", 90.0),
+    ("Spring", 92.0),
+    ("Summer", 92.0),
+    ("Autumn", 91.0),
+];
+
+/// How a simulated day scales up to a year of operation
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(super) struct Forecast {
+    /// demand in each season relative to the simulated day, 1.0 is the same
+    seasons: [f64; 4],
+    pricing: Option<Pricing>,
+}
+
+impl Default for Forecast {
+    fn default() -> Self {
+        Self {
+            seasons: [1.0; 4],
+            pricing: None,
+        }
+    }
+}
+
+/// What drivers pay, what the grid charges and what the chargers cost to install
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+struct Pricing {
+    /// $/kWh charged to drivers
+    price: f64,
+    /// $/kWh of grid import, solar is free
+    electricity_cost: f64,
+    /// installed cost of each charger
+    capex_per_charger: f64,
+}
+
+impl Default for Pricing {
+    fn default() -> Self {
+        Self {
+            price: 0.45,
+            electricity_cost: 0.15,
+            capex_per_charger: 150_000.0,
+        }
+    }
+}
+
+/// A simulated run scaled up to a week and a year
+#[derive(Clone, Copy, PartialEq)]
+struct Projection {
+    daily_energy: Energy,
+    weekly_energy: Energy,
+    annual_energy: Energy,
+    /// share of the chargers' capacity used over a whole year, 1.0 is flat out around the clock
+    utilization: f64,
+    revenue: Option<Revenue>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Revenue {
+    annual_revenue: f64,
+    annual_electricity_cost: f64,
+    capex: f64,
+    /// years until the margin pays back the chargers, `None` if it never does
+    payback_years: Option<f64>,
+}
+
+impl Forecast {
+    /// Treats each of the `days` simulated days as a representative one, scaled by the seasons
+    fn project(&self, summary: &SimSummary, days: u16) -> Projection {
+        let days = days.max(1) as f64;
+        // a day at the average season, weighted by how long each season lasts
+        let season_days = SEASONS
+            .iter()
+            .zip(self.seasons)
+            .map(|((_, length), multiplier)| length * multiplier.max(0.0))
+            .sum::<f64>();
+        let daily_kwh = summary.energy_dispensed.as_kwh() / days;
+        let annual_kwh = daily_kwh * season_days;
+        let annual_capacity = summary.charger_capacity.as_kw() * 24.0 * DAYS_PER_YEAR;
+        let revenue = self.pricing.map(|pricing| {
+            let annual_revenue = annual_kwh * pricing.price;
+            let annual_electricity_cost =
+                summary.grid_import.as_kwh() / days * season_days * pricing.electricity_cost;
+            let capex = pricing.capex_per_charger * summary.chargers as f64;
+            let margin = annual_revenue - annual_electricity_cost;
+            Revenue {
+                annual_revenue,
+                annual_electricity_cost,
+                capex,
+                payback_years: (margin > 0.0).then(|| capex / margin),
+            }
+        });
+        Projection {
+            daily_energy: Energy::from_kwh(daily_kwh),
+            weekly_energy: Energy::from_kwh(annual_kwh / WEEKS_PER_YEAR),
+            annual_energy: Energy::from_kwh(annual_kwh),
+            utilization: if annual_capacity > 0.0 {
+                annual_kwh / annual_capacity
+            } else {
+                0.0
+            },
+            revenue,
+        }
+    }
+}
+
+fn format_dollars(dollars: f64) -> String {
+    format!("${}", preferences().number(dollars, 0))
+}
+
+/// Scales the simulated days up to a year, with optional pricing for a payback estimate
+#[component]
+pub(super) fn ForecastPanel(
+    summary: SimSummary,
+    days: u16,
+    #[prop(into)] forecast: Signal<Forecast>,
+    set_forecast: SignalSetter<Forecast>,
+) -> impl IntoView {
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors";
+    let update = move |f: &dyn Fn(&mut Forecast)| {
+        let mut forecast = forecast.get_untracked();
+        f(&mut forecast);
+        set_forecast(forecast);
+    };
+    let projection = Memo::new(move |_| forecast.with(|f| f.project(&summary, days)));
+    let pricing = move || forecast.with(|f| f.pricing);
+    let pricing_input = move |get: fn(&Pricing) -> f64, set: fn(&mut Pricing, f64)| {
+        view! {
+            <input class=input prop:value=move || pricing().as_ref().map(get)
+                on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                    update(&|forecast| if let Some(pricing) = &mut forecast.pricing { set(pricing, value.max(0.0)) });
+                } } />
+        }
+    };
+    view! {
+        <div class="flex flex-col gap-1">
+            <h3 class="text-xl">"Forecast"</h3>
+            <span class="text-sm">{format!("Treats the {days} simulated day(s) as typical, scaled by how busy each season is.")}</span>
+            <div class="flex flex-row flex-wrap gap-2 items-center">
+                {SEASONS.iter().enumerate().map(|(i, (season, _))| view! {
+                    <span>{*season}" ×"</span>
+                    <input class=input prop:value=move || forecast.with(|f| f.seasons[i])
+                        on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                            update(&|forecast| forecast.seasons[i] = value.max(0.0));
+                        } } />
+                }).collect_view()}
+            </div>
+            <div class="flex flex-row flex-wrap gap-4 text-md">
+                <div>"per day: "{move || preferences().energy(projection().daily_energy)}</div>
+                <div>"per week: "{move || preferences().energy(projection().weekly_energy)}</div>
+                <div>"per year: "{move || preferences().energy(projection().annual_energy)}</div>
+                <div>{move || format!("utilization: {}%", preferences().number(projection().utilization * 100.0, 1))}</div>
+            </div>
+            <label class="flex flex-row gap-1 items-center">
+                <input type="checkbox" class="accent-amber-500 dark:accent-teal-500" prop:checked=move || pricing().is_some()
+                    on:change=move |e| {
+                        let pricing = event_target_checked(&e).then(Pricing::default);
+                        update(&|forecast| forecast.pricing = pricing);
+                    } />
+                "Estimate revenue and payback"
+            </label>
+            <div class="flex flex-row flex-wrap gap-2 items-center" class:collapse=move || pricing().is_none()>
+                <span>"Price $/kWh"</span>
+                {pricing_input(|p| p.price, |p, value| p.price = value)}
+                <span>"Electricity $/kWh"</span>
+                {pricing_input(|p| p.electricity_cost, |p, value| p.electricity_cost = value)}
+                <span>"Installed $ per charger"</span>
+                {pricing_input(|p| p.capex_per_charger, |p, value| p.capex_per_charger = value)}
+            </div>
+            {move || projection().revenue.map(|revenue| view! {
+                <div class="flex flex-row flex-wrap gap-4 text-md">
+                    <div>"revenue per year: "{format_dollars(revenue.annual_revenue)}</div>
+                    <div>"electricity per year: "{format_dollars(revenue.annual_electricity_cost)}</div>
+                    <div>"installed cost: "{format_dollars(revenue.capex)}</div>
+                    <div>"payback: "{revenue.payback_years.map(|years| format!("{} years", preferences().number(years, 1))).unwrap_or_else(|| "never, electricity costs more than it earns".to_string())}</div>
+                </div>
+            })}
+        </div>
+    }
+    .into_any()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Power;
+    use super::*;
+    use std::time::Duration;
+
+    /// Two days on two 150 kW chargers, 1000 kWh dispensed and 500 kWh from the grid each day
+    fn summary() -> SimSummary {
+        SimSummary {
+            energy_dispensed: Energy::from_kwh(2000.0),
+            peak_demand: Power::from_kw(300.0),
+            solar_generated: Energy::from_kwh(1000.0),
+            solar_used: Energy::from_kwh(1000.0),
+            grid_import: Energy::from_kwh(1000.0),
+            duration: Duration::from_secs(2 * 24 * 60 * 60),
+            vehicles: 20,
+            chargers: 2,
+            charger_capacity: Power::from_kw(300.0),
+            simulation_step_time: Duration::from_secs(30),
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn simulated_days_scale_up_to_a_year() {
+        let projection = Forecast::default().project(&summary(), 2);
+        assert!(close(projection.daily_energy.as_kwh(), 1000.0));
+        assert!(close(projection.weekly_energy.as_kwh(), 7000.0));
+        assert!(close(projection.annual_energy.as_kwh(), 365_000.0));
+        // 300 kW around the clock would be 2,628,000 kWh
+        assert!(close(projection.utilization, 365_000.0 / 2_628_000.0));
+        assert!(projection.revenue.is_none());
+
+        // a dead winter and a summer twice as busy
+        let forecast = Forecast {
+            seasons: [0.0, 1.0, 2.0, 1.0],
+            pricing: None,
+        };
+        let projection = forecast.project(&summary(), 2);
+        assert!(close(projection.annual_energy.as_kwh(), 367_000.0));
+    }
+
+    #[test]
+    fn payback_is_capex_over_the_yearly_margin() {
+        let forecast = Forecast {
+            pricing: Some(Pricing::default()),
+            ..Forecast::default()
+        };
+        let revenue = forecast.project(&summary(), 2).revenue.unwrap();
+        assert!(close(revenue.annual_revenue, 365_000.0 * 0.45));
+        assert!(close(revenue.annual_electricity_cost, 182_500.0 * 0.15));
+        assert!(close(revenue.capex, 300_000.0));
+        assert!(close(
+            revenue.payback_years.unwrap(),
+            300_000.0 / (164_250.0 - 27_375.0)
+        ));
+
+        // selling below cost never pays back
+        let forecast = Forecast {
+            pricing: Some(Pricing {
+                price: 0.05,
+                ..Pricing::default()
+            }),
+            ..Forecast::default()
+        };
+        let revenue = forecast.project(&summary(), 2).revenue.unwrap();
+        assert_eq!(revenue.payback_years, None);
+    }
+}
@@ -0,0 +1,243 @@
+use leptos::{ev, prelude::*};
+use leptos_use::{use_event_listener, use_window};
+use web_sys::{wasm_bindgen::JsCast, HtmlElement, HtmlInputElement};
+
+/// Number of edits that can be undone
+const MAX_UNDO: usize = 100;
+
+/// How an edit should be recorded in the undo stack and browser history
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum EditKind {
+    /// A finished edit, gets its own undo step and browser history entry
+    Commit,
+    /// Part of an edit that is still in progress, such as dragging a slider.
+    /// Consecutive intermediate edits and the commit that ends them share one undo step and
+    /// replace each other in the browser history.
+    Intermediate,
+}
+
+/// Undo/redo stack over edits to a value.
+/// `write` stores the value and is told whether the edit should replace the current browser
+/// history entry instead of adding a new one.
+pub(super) struct EditHistory<T: Send + Sync + 'static> {
+    value: Signal<T>,
+    write: Callback<(T, bool)>,
+    undo: RwSignal<Vec<T>>,
+    redo: RwSignal<Vec<T>>,
+    in_progress: StoredValue<bool>,
+}
+
+impl<T: Send + Sync + 'static> Clone for EditHistory<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Send + Sync + 'static> Copy for EditHistory<T> {}
+
+impl<T> EditHistory<T>
+where
+    T: Clone + PartialEq + Send + Sync + 'static,
+{
+    pub(super) fn new(
+        value: impl Into<Signal<T>>,
+        write: impl Fn(T, bool) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            value: value.into(),
+            write: Callback::new(move |(value, replace)| write(value, replace)),
+            undo: RwSignal::new(vec![]),
+            redo: RwSignal::new(vec![]),
+            in_progress: StoredValue::new(false),
+        }
+    }
+
+    pub(super) fn edit(&self, next: T, kind: EditKind) {
+        let current = self.value.get_untracked();
+        let continuing = self.in_progress.get_value();
+        self.in_progress.set_value(kind == EditKind::Intermediate);
+        if current == next {
+            return;
+        }
+        if !continuing {
+            self.undo.update(|undo| {
+                undo.push(current);
+                if undo.len() > MAX_UNDO {
+                    undo.remove(0);
+                }
+            });
+        }
+        self.redo.update(Vec::clear);
+        self.write.run((next, continuing));
+    }
+
+    /// Setter that records every write as the given kind of edit
+    pub(super) fn setter(self, kind: EditKind) -> SignalSetter<T> {
+        SignalSetter::map(move |next| self.edit(next, kind))
+    }
+
+    pub(super) fn undo(&self) {
+        if let Some(previous) = self.undo.try_update(Vec::pop).flatten() {
+            self.redo
+                .update(|redo| redo.push(self.value.get_untracked()));
+            self.in_progress.set_value(false);
+            self.write.run((previous, false));
+        }
+    }
+
+    pub(super) fn redo(&self) {
+        if let Some(next) = self.redo.try_update(Vec::pop).flatten() {
+            self.undo
+                .update(|undo| undo.push(self.value.get_untracked()));
+            self.in_progress.set_value(false);
+            self.write.run((next, false));
+        }
+    }
+
+    pub(super) fn can_undo(&self) -> bool {
+        self.undo.with(|undo| !undo.is_empty())
+    }
+
+    pub(super) fn can_redo(&self) -> bool {
+        self.redo.with(|redo| !redo.is_empty())
+    }
+}
+
+/// Undo and redo buttons, also handles ctrl+z, ctrl+shift+z and ctrl+y
+#[component]
+pub(super) fn UndoRedo<T>(history: EditHistory<T>) -> impl IntoView
+where
+    T: Clone + PartialEq + Send + Sync + 'static,
+{
+    let _ = use_event_listener(use_window(), ev::keydown, move |e| {
+        if !(e.ctrl_key() || e.meta_key()) {
+            return;
+        }
+        // leave text inputs with their own undo
+        let typing = e
+            .target()
+            .is_some_and(|t| match t.dyn_ref::<HtmlInputElement>() {
+                Some(input) => !matches!(input.type_().as_str(), "range" | "checkbox" | "file"),
+                None => t
+                    .dyn_ref::<HtmlElement>()
+                    .is_some_and(|t| t.tag_name() == "TEXTAREA"),
+            });
+        if typing {
+            return;
+        }
+        match e.key().to_lowercase().as_str() {
+            "z" if e.shift_key() => history.redo(),
+            "z" => history.undo(),
+            "y" => history.redo(),
+            _ => return,
+        }
+        e.prevent_default();
+    });
+    let button = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors disabled:opacity-50";
+    view! {
+        <div class="flex flex-row gap-2">
+            <button class=button title="Undo (ctrl+z)" disabled=move || !history.can_undo() on:click=move |_| history.undo()>"Undo"</button>
+            <button class=button title="Redo (ctrl+shift+z)" disabled=move || !history.can_redo() on:click=move |_| history.redo()>"Redo"</button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A history over a number, and whether each write replaced the browser history entry
+    fn history() -> (RwSignal<i32>, RwSignal<Vec<bool>>, EditHistory<i32>) {
+        let value = RwSignal::new(0);
+        let replaced = RwSignal::new(vec![]);
+        let history = EditHistory::new(value, move |next, replace| {
+            value.set(next);
+            replaced.update(|replaced| replaced.push(replace));
+        });
+        (value, replaced, history)
+    }
+
+    #[test]
+    fn undo_and_redo_step_through_edits() {
+        Owner::new().with(|| {
+            let (value, _, history) = history();
+            assert!(!history.can_undo() && !history.can_redo());
+            history.edit(1, EditKind::Commit);
+            history.edit(2, EditKind::Commit);
+            assert!(history.can_undo());
+            history.undo();
+            assert_eq!(value.get_untracked(), 1);
+            history.undo();
+            assert_eq!(value.get_untracked(), 0);
+            assert!(!history.can_undo());
+            // nothing left to undo changes nothing
+            history.undo();
+            assert_eq!(value.get_untracked(), 0);
+            history.redo();
+            history.redo();
+            assert_eq!(value.get_untracked(), 2);
+            assert!(!history.can_redo());
+        });
+    }
+
+    #[test]
+    fn a_new_edit_clears_redo() {
+        Owner::new().with(|| {
+            let (value, _, history) = history();
+            history.edit(1, EditKind::Commit);
+            history.edit(2, EditKind::Commit);
+            history.undo();
+            assert!(history.can_redo());
+            history.edit(3, EditKind::Commit);
+            assert!(!history.can_redo());
+            history.redo();
+            assert_eq!(value.get_untracked(), 3);
+            history.undo();
+            assert_eq!(value.get_untracked(), 1);
+        });
+    }
+
+    #[test]
+    fn only_the_latest_edits_can_be_undone() {
+        Owner::new().with(|| {
+            let (value, _, history) = history();
+            for next in 1..=MAX_UNDO as i32 + 10 {
+                history.edit(next, EditKind::Commit);
+            }
+            for _ in 0..MAX_UNDO + 10 {
+                history.undo();
+            }
+            assert_eq!(value.get_untracked(), 10);
+        });
+    }
+
+    #[test]
+    fn intermediate_edits_share_one_undo_step() {
+        Owner::new().with(|| {
+            let (value, replaced, history) = history();
+            history.edit(1, EditKind::Commit);
+            // dragging a slider from 1 to 4
+            history.edit(2, EditKind::Intermediate);
+            history.edit(3, EditKind::Intermediate);
+            history.edit(4, EditKind::Commit);
+            assert_eq!(replaced.get_untracked(), [false, false, true, true]);
+            history.undo();
+            assert_eq!(value.get_untracked(), 1);
+            // the next edit starts a step of its own
+            history.redo();
+            history.edit(5, EditKind::Commit);
+            history.undo();
+            assert_eq!(value.get_untracked(), 4);
+        });
+    }
+
+    #[test]
+    fn edits_to_the_same_value_are_not_recorded() {
+        Owner::new().with(|| {
+            let (_, replaced, history) = history();
+            history.edit(0, EditKind::Commit);
+            assert!(!history.can_undo());
+            assert!(replaced.get_untracked().is_empty());
+        });
+    }
+}
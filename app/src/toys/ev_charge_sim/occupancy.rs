@@ -0,0 +1,475 @@
+use std::time::Duration;
+
+use leptos::{html, prelude::*};
+use leptos_use::{use_element_size, use_preferred_dark, UseElementSizeReturn};
+
+use crate::components::{chart_ink, ChartAxis, ChartSeries, LineChart, LineChartData};
+
+use super::{preferences, ScenarioStart, SimFrame, VehicleSpec, TIME_STEPS};
+
+/// The most columns the chart is split into, longer runs use wider time buckets
+const MAX_BUCKETS: u64 = 720;
+
+/// Shades of power a bar is drawn in, neighbouring buckets in the same shade are one bar
+const POWER_LEVELS: f64 = 8.0;
+
+/// Width the chart is laid out at before the browser has measured it
+const DEFAULT_WIDTH: f64 = 1000.0;
+const ROW_HEIGHT: f64 = 22.0;
+const TIME_TICKS: usize = 6;
+const MARGIN_LEFT: f64 = 90.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 10.0;
+const MARGIN_BOTTOM: f64 = 30.0;
+const BAR_COLOR: &str = "#5470c6";
+
+/// One vehicle on one plug over a run of time buckets
+#[derive(Clone, PartialEq)]
+struct OccupancyBar {
+    row: usize,
+    /// first bucket
+    from: usize,
+    /// bucket after the last
+    to: usize,
+    /// the vehicle plugged in at the end of the bar
+    vehicle_id: usize,
+    /// energy delivered over the bar spread across all of it
+    average_power_kw: f64,
+}
+
+/// Which vehicle was on each plug and when, bucketed so long runs stay readable
+#[derive(Clone, PartialEq)]
+struct PlugOccupancy {
+    /// (charger id, plug) for each row, only plugs that were used
+    plugs: Vec<(usize, usize)>,
+    bucket: Duration,
+    buckets: usize,
+    bars: Vec<OccupancyBar>,
+    /// the highest average power of any bucket, bars are shaded against it
+    max_power_kw: f64,
+    /// the longest the queue got during each bucket
+    queue: Vec<usize>,
+}
+
+impl PlugOccupancy {
+    fn new(frames: &[SimFrame]) -> Self {
+        let mut plugs = frames
+            .iter()
+            .flat_map(|f| f.vehicles_charging.iter().map(|v| (v.charger_id, v.plug)))
+            .collect::<Vec<_>>();
+        plugs.sort_unstable();
+        plugs.dedup();
+        let end = frames.last().map(|f| f.duration).unwrap_or_default();
+        let bucket_minutes = (end.as_secs() / 60).div_ceil(MAX_BUCKETS).max(1);
+        let bucket = Duration::from_secs(bucket_minutes * 60);
+        let buckets = end.as_secs().div_ceil(bucket.as_secs()).max(1) as usize;
+        // energy in kWh and the last vehicle seen, for each bucket of each plug
+        let mut grid = vec![vec![None::<(f64, usize)>; buckets]; plugs.len()];
+        let mut queue = vec![0; buckets];
+        let mut previous = Duration::default();
+        for frame in frames {
+            let step_hours = frame.duration.saturating_sub(previous).as_secs_f64() / 60.0 / 60.0;
+            // frames are stamped at the end of their step
+            let index = (frame.duration.as_secs().saturating_sub(1) / bucket.as_secs()) as usize;
+            previous = frame.duration;
+            queue[index] = queue[index].max(frame.queue_length);
+            for vehicle in &frame.vehicles_charging {
+                let Ok(row) = plugs.binary_search(&(vehicle.charger_id, vehicle.plug)) else {
+                    continue;
+                };
+                let energy = vehicle.allocated_power.as_kw() * step_hours;
+                let cell = grid[row][index].get_or_insert((0.0, vehicle.vehicle_id));
+                *cell = (cell.0 + energy, vehicle.vehicle_id);
+            }
+        }
+        let bucket_hours = bucket.as_secs_f64() / 60.0 / 60.0;
+        let max_power_kw = grid
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|(energy, _)| energy / bucket_hours)
+            .fold(1.0, f64::max);
+        let level = |power_kw: f64| (power_kw / max_power_kw * POWER_LEVELS).round();
+        let mut bars = Vec::<OccupancyBar>::new();
+        for (row, cells) in grid.into_iter().enumerate() {
+            for (index, cell) in cells.into_iter().enumerate() {
+                let Some((energy, vehicle_id)) = cell else {
+                    continue;
+                };
+                let power_kw = energy / bucket_hours;
+                match bars.last_mut() {
+                    Some(bar)
+                        if bar.row == row
+                            && bar.to == index
+                            && bar.vehicle_id == vehicle_id
+                            && level(bar.average_power_kw) == level(power_kw) =>
+                    {
+                        let length = (bar.to - bar.from) as f64;
+                        bar.average_power_kw =
+                            (bar.average_power_kw * length + power_kw) / (length + 1.0);
+                        bar.to += 1;
+                    }
+                    _ => bars.push(OccupancyBar {
+                        row,
+                        from: index,
+                        to: index + 1,
+                        vehicle_id,
+                        average_power_kw: power_kw,
+                    }),
+                }
+            }
+        }
+        Self {
+            plugs,
+            bucket,
+            buckets,
+            bars,
+            max_power_kw,
+            queue,
+        }
+    }
+
+    fn plug_name(&self, row: usize) -> String {
+        let (charger, plug) = self.plugs[row];
+        format!("#{} plug {}", charger + 1, plug + 1)
+    }
+
+    /// How busy the plugs and the queue got, for anyone who can't see the chart
+    fn summary(&self, start: ScenarioStart) -> String {
+        let prefs = preferences();
+        let mut occupied = vec![0; self.plugs.len()];
+        for bar in &self.bars {
+            occupied[bar.row] += bar.to - bar.from;
+        }
+        let mut summary = format!("{} plug(s) were used.", self.plugs.len());
+        if let Some((row, buckets)) = occupied.iter().enumerate().max_by_key(|(_, b)| **b) {
+            let hours = (self.bucket * *buckets as u32).as_secs_f64() / 60.0 / 60.0;
+            summary += &format!(
+                " The busiest, {}, had a vehicle on it for {} hours.",
+                self.plug_name(row),
+                prefs.number(hours, 1)
+            );
+        }
+        // the first time it was at its longest
+        match self
+            .queue
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, length)| **length)
+        {
+            Some((bucket, length)) if *length > 0 => {
+                summary += &format!(
+                    " The queue peaked at {length} vehicle(s) around {}.",
+                    start.label_at(self.bucket * bucket as u32)
+                )
+            }
+            _ => summary += " No vehicle had to wait for a plug.",
+        }
+        summary
+    }
+}
+
+/// "#3 Chevy Bolt 2022", or just the number if the vehicle isn't known
+fn vehicle_name(vehicles: &[&'static VehicleSpec], vehicle_id: usize) -> String {
+    match vehicles.get(vehicle_id) {
+        Some(spec) => format!("#{} {}", vehicle_id + 1, spec.name),
+        None => format!("#{}", vehicle_id + 1),
+    }
+}
+
+/// A Gantt style view of the run, one row per plug with the vehicle on it shaded by the power
+/// it got, and the queue waiting underneath. Stays readable with far more vehicles than the power chart.
+#[component]
+pub(super) fn PlugOccupancyChart(
+    vehicles: Signal<Vec<&'static VehicleSpec>>,
+    data: Signal<Vec<SimFrame>>,
+    #[prop(into)] start: Signal<ScenarioStart>,
+) -> impl IntoView {
+    let node = NodeRef::<html::Div>::new();
+    let UseElementSizeReturn { width, .. } = use_element_size(node);
+    let dark = use_preferred_dark();
+    let width = Signal::derive(move || match width() {
+        width if width > 0.0 => width,
+        _ => DEFAULT_WIDTH,
+    });
+    let occupancy = Memo::new(move |_| data.with(|frames| PlugOccupancy::new(frames)));
+    // the bar under the pointer, described above the chart
+    let hovered = RwSignal::new(None::<usize>);
+    let height = move || {
+        occupancy.with(|occupancy| occupancy.plugs.len()) as f64 * ROW_HEIGHT
+            + MARGIN_TOP
+            + MARGIN_BOTTOM
+    };
+    let gantt = move || {
+        let width = width();
+        let (text, grid) = chart_ink(dark());
+        let start = start();
+        occupancy.with(|occupancy| {
+            let column = (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.0) / occupancy.buckets as f64;
+            let bottom = MARGIN_TOP + occupancy.plugs.len() as f64 * ROW_HEIGHT;
+            let rows = (0..occupancy.plugs.len())
+                .map(|row| {
+                    let y = MARGIN_TOP + row as f64 * ROW_HEIGHT;
+                    view! {
+                        <line x1=format!("{MARGIN_LEFT:.1}") y1=format!("{:.1}", y + ROW_HEIGHT) x2=format!("{:.1}", width - MARGIN_RIGHT) y2=format!("{:.1}", y + ROW_HEIGHT) stroke=grid />
+                        <text x=format!("{:.1}", MARGIN_LEFT - 6.0) y=format!("{:.1}", y + ROW_HEIGHT / 2.0 + 4.0) text-anchor="end" fill=text>{occupancy.plug_name(row)}</text>
+                    }
+                })
+                .collect_view();
+            let ticks = (0..occupancy.buckets)
+                .step_by(occupancy.buckets.div_ceil(TIME_TICKS).max(1))
+                .map(|bucket| {
+                    let x = MARGIN_LEFT + bucket as f64 * column;
+                    view! {
+                        <line x1=format!("{x:.1}") y1=format!("{MARGIN_TOP:.1}") x2=format!("{x:.1}") y2=format!("{:.1}", bottom + 5.0) stroke=grid />
+                        <text x=format!("{x:.1}") y=format!("{:.1}", bottom + 20.0) text-anchor="middle" fill=text>{start.label_at(occupancy.bucket * bucket as u32)}</text>
+                    }
+                })
+                .collect_view();
+            let bars = occupancy
+                .bars
+                .iter()
+                .enumerate()
+                .map(|(index, bar)| {
+                    let opacity = 0.15 + 0.85 * bar.average_power_kw / occupancy.max_power_kw;
+                    view! {
+                        <rect x=format!("{:.1}", MARGIN_LEFT + bar.from as f64 * column) y=format!("{:.1}", MARGIN_TOP + bar.row as f64 * ROW_HEIGHT + 2.0)
+                            width=format!("{:.1}", (bar.to - bar.from) as f64 * column) height=format!("{:.1}", ROW_HEIGHT - 4.0)
+                            fill=BAR_COLOR fill-opacity=format!("{opacity:.2}")
+                            stroke=move || (hovered() == Some(index)).then_some(text)
+                            on:mouseenter=move |_| hovered.set(Some(index)) />
+                    }
+                })
+                .collect_view();
+            view! { {rows} {ticks} {bars} }
+        })
+    };
+    let description = move || {
+        let prefs = preferences();
+        let Some(index) = hovered() else {
+            return format!(
+                "Each row is a plug, shaded by the average power its vehicle got, up to {} kW. Hover a bar to see which vehicle it was.",
+                occupancy.with(|occupancy| prefs.number(occupancy.max_power_kw, 0))
+            );
+        };
+        occupancy.with(|occupancy| {
+            let Some(bar) = occupancy.bars.get(index) else {
+                return String::new();
+            };
+            let start = start();
+            format!(
+                "{} on {} from {} to {}, averaging {} kW.",
+                vehicles.with(|vehicles| vehicle_name(vehicles, bar.vehicle_id)),
+                occupancy.plug_name(bar.row),
+                start.label_at(occupancy.bucket * bar.from as u32),
+                start.label_at(occupancy.bucket * bar.to as u32),
+                prefs.number(bar.average_power_kw, 1)
+            )
+        })
+    };
+    let queue = Signal::derive(move || {
+        let prefs = preferences();
+        let start = start();
+        occupancy.with(|occupancy| {
+            let bucket_minutes = occupancy.bucket.as_secs_f64() / 60.0;
+            let points = occupancy
+                .queue
+                .iter()
+                .enumerate()
+                .map(|(bucket, length)| (bucket as f64 * bucket_minutes, *length as f64))
+                .collect();
+            LineChartData::new(
+                "Queue",
+                ChartAxis::new("Time of day")
+                    .steps(TIME_STEPS)
+                    .label(move |minutes| {
+                        start.label_at(Duration::from_secs_f64(minutes.max(0.0) * 60.0))
+                    }),
+                ChartAxis::new("Vehicles waiting")
+                    .steps(&[1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0])
+                    .label(move |length| prefs.number(length, 0)),
+            )
+            .series(ChartSeries::line("Vehicles waiting", points))
+        })
+    });
+    view! {
+        <div class="flex flex-col gap-1" class:collapse=move || occupancy.with(|occupancy| occupancy.plugs.is_empty())>
+            <span class="text-xl">"Plug Occupancy"</span>
+            <span class="text-sm" aria-live="polite">{description}</span>
+            <div class="w-full" node_ref=node>
+                <svg xmlns="http://www.w3.org/2000/svg" width="100%" height=move || height().to_string() font-family="sans-serif" font-size="13"
+                    role="img" aria-label="Plug occupancy" aria-describedby="occupancy-summary" viewBox=move || format!("0 0 {:.0} {:.0}", width(), height())
+                    on:mouseleave=move |_| hovered.set(None)>
+                    {gantt}
+                </svg>
+            </div>
+            <OccupancyTable occupancy vehicles start />
+            <LineChart chart=queue id="occupancyqueue" height=250.0 />
+        </div>
+    }
+}
+
+/// The occupancy summary and each bar as a table row, the Gantt's counterpart to the line
+/// chart's text and table. Rows are only built once it's opened.
+#[component]
+fn OccupancyTable(
+    occupancy: Memo<PlugOccupancy>,
+    vehicles: Signal<Vec<&'static VehicleSpec>>,
+    start: Signal<ScenarioStart>,
+) -> impl IntoView {
+    let (open, set_open) = signal(false);
+    let table = move || {
+        open().then(|| {
+            let prefs = preferences();
+            let start = start();
+            let rows = occupancy.with(|occupancy| {
+                vehicles.with(|vehicles| {
+                    occupancy
+                        .bars
+                        .iter()
+                        .map(|bar| {
+                            view! {
+                                <tr class="even:bg-slate-50 dark:even:bg-slate-700">
+                                    <th scope="row" class="px-2 text-left font-normal whitespace-nowrap">{occupancy.plug_name(bar.row)}</th>
+                                    <td class="px-2">{vehicle_name(vehicles, bar.vehicle_id)}</td>
+                                    <td class="px-2 whitespace-nowrap">{start.label_at(occupancy.bucket * bar.from as u32)}</td>
+                                    <td class="px-2 whitespace-nowrap">{start.label_at(occupancy.bucket * bar.to as u32)}</td>
+                                    <td class="px-2">{format!("{} kW", prefs.number(bar.average_power_kw, 1))}</td>
+                                </tr>
+                            }
+                        })
+                        .collect_view()
+                })
+            });
+            view! {
+                <div tabindex="0" role="region" aria-label="Plug occupancy data"
+                    class="max-h-96 overflow-auto border border-slate-300 dark:border-slate-500 rounded-lg">
+                    <table class="text-sm">
+                        <caption class="sr-only">"Plug occupancy"</caption>
+                        <thead class="sticky top-0 bg-white dark:bg-slate-800">
+                            <tr>
+                                <th scope="col" class="px-2 text-left">"Plug"</th>
+                                <th scope="col" class="px-2 text-left">"Vehicle"</th>
+                                <th scope="col" class="px-2 text-left">"From"</th>
+                                <th scope="col" class="px-2 text-left">"To"</th>
+                                <th scope="col" class="px-2 text-left">"Average power"</th>
+                            </tr>
+                        </thead>
+                        <tbody>{rows}</tbody>
+                    </table>
+                </div>
+            }
+        })
+    };
+    view! {
+        <details class="flex flex-col gap-1 text-sm" on:toggle=move |e| set_open(event_target::<web_sys::HtmlDetailsElement>(&e).open())>
+            <summary class="cursor-pointer">"Chart as text and table"</summary>
+            <p id="occupancy-summary">{move || occupancy.with(|occupancy| occupancy.summary(start()))}</p>
+            {table}
+        </details>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Energy, PercentFull, Power, VehicleChargeFrame};
+    use super::*;
+
+    /// A frame ending at `minute` with `(charger, plug, vehicle, kW)` charging and `queue` waiting
+    fn frame(
+        minute: u64,
+        queue_length: usize,
+        charging: &[(usize, usize, usize, f64)],
+    ) -> SimFrame {
+        SimFrame {
+            energy_dispensed: Energy::default(),
+            solar_power: Power::default(),
+            grid_power: Power::default(),
+            chargers: vec![],
+            vehicles_charging: charging
+                .iter()
+                .map(|&(charger_id, plug, vehicle_id, kw)| VehicleChargeFrame {
+                    allocated_power: Power::from_kw(kw),
+                    vehicle_id,
+                    charger_id,
+                    plug,
+                    soc: PercentFull::new(50.0),
+                    energy_added: Energy::default(),
+                })
+                .collect(),
+            queue_length,
+            duration: Duration::from_secs(minute * 60),
+        }
+    }
+
+    fn spans(occupancy: &PlugOccupancy) -> Vec<(usize, usize, usize, usize, f64)> {
+        occupancy
+            .bars
+            .iter()
+            .map(|bar| {
+                (
+                    bar.row,
+                    bar.from,
+                    bar.to,
+                    bar.vehicle_id,
+                    bar.average_power_kw,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bars_follow_each_vehicle_on_its_plug() {
+        // vehicle 0 slows down halfway, the plug sits empty for a minute, then vehicle 1 takes it.
+        // vehicle 2 waits in the queue before getting the second charger.
+        let frames = (1..=10)
+            .map(|minute| {
+                let mut charging = vec![];
+                match minute {
+                    1..=2 => charging.push((0, 0, 0, 60.0)),
+                    3..=4 => charging.push((0, 0, 0, 20.0)),
+                    6.. => charging.push((0, 0, 1, 60.0)),
+                    _ => {}
+                }
+                if (3..=5).contains(&minute) {
+                    charging.push((1, 0, 2, 30.0));
+                }
+                frame(minute, (minute == 2) as usize, &charging)
+            })
+            .collect::<Vec<_>>();
+        let occupancy = PlugOccupancy::new(&frames);
+        assert_eq!(occupancy.plugs, [(0, 0), (1, 0)]);
+        assert_eq!(occupancy.bucket, Duration::from_secs(60));
+        assert_eq!(occupancy.buckets, 10);
+        assert_eq!(occupancy.max_power_kw, 60.0);
+        let spans = spans(&occupancy);
+        assert_eq!(spans.len(), 4);
+        for (span, expected) in spans.iter().zip([
+            (0, 0, 2, 0, 60.0),
+            (0, 2, 4, 0, 20.0),
+            (0, 5, 10, 1, 60.0),
+            (1, 2, 5, 2, 30.0),
+        ]) {
+            assert_eq!(
+                (span.0, span.1, span.2, span.3),
+                (expected.0, expected.1, expected.2, expected.3)
+            );
+            assert!((span.4 - expected.4).abs() < 1e-9);
+        }
+        assert_eq!(occupancy.queue, [0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn long_runs_use_wider_buckets() {
+        // a full day at a minute per bucket would be twice the columns allowed
+        let frames = [frame(1, 0, &[(0, 0, 0, 50.0)]), frame(24 * 60, 3, &[])];
+        let occupancy = PlugOccupancy::new(&frames);
+        assert_eq!(occupancy.bucket, Duration::from_secs(2 * 60));
+        assert_eq!(occupancy.buckets, MAX_BUCKETS as usize);
+        assert_eq!(occupancy.queue.last(), Some(&3));
+        // the minute of charging is spread over the two minute bucket
+        assert_eq!(spans(&occupancy).len(), 1);
+        assert!((occupancy.bars[0].average_power_kw - 25.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,418 @@
+use std::{fmt::Display, str::FromStr};
+
+use codee::string::FromToStringCodec;
+use leptos::{prelude::*, reactive::graph::Observer};
+use leptos_router::hooks::use_location;
+use leptos_use::storage::use_local_storage;
+
+use super::{Energy, PercentFull, Power};
+
+const STORAGE_KEY: &str = "ev-tools-preferences";
+const KM_PER_MILE: f64 = 1.609344;
+
+/// How distances, efficiency and speeds are written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) enum UnitSystem {
+    /// miles, mi/kWh and mph
+    #[default]
+    Imperial,
+    /// km, kWh/100km and km/h
+    Metric,
+}
+
+/// The thousands separator and decimal mark numbers are written with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) enum NumberFormat {
+    /// 1234.5
+    #[default]
+    Plain,
+    /// 1,234.5
+    Grouped,
+    /// 1.234,5
+    DecimalComma,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) enum ClockFormat {
+    /// 18:30
+    #[default]
+    TwentyFourHour,
+    /// 6:30 PM
+    TwelveHour,
+}
+
+/// Each preference is a query parameter, so a link can show the page with other settings
+/// than the reader's own, and the set is stored in local storage in the same form.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) struct Preferences {
+    pub(super) units: UnitSystem,
+    pub(super) numbers: NumberFormat,
+    pub(super) clock: ClockFormat,
+}
+
+/// A preference, its query parameter name and the values it accepts
+trait Preference: Copy + PartialEq + 'static {
+    const PARAM: &'static str;
+    const LABEL: &'static str;
+    const ALL: &'static [(Self, &'static str, &'static str)];
+
+    fn param_value(&self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(value, _, _)| value == self)
+            .map(|(_, param, _)| *param)
+            .unwrap_or_default()
+    }
+
+    fn from_param(param: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(_, value, _)| *value == param)
+            .map(|(value, _, _)| *value)
+    }
+}
+
+impl Preference for UnitSystem {
+    const PARAM: &'static str = "units";
+    const LABEL: &'static str = "Units";
+    const ALL: &'static [(Self, &'static str, &'static str)] = &[
+        (UnitSystem::Imperial, "imperial", "miles, mi/kWh"),
+        (UnitSystem::Metric, "metric", "km, kWh/100km"),
+    ];
+}
+
+impl Preference for NumberFormat {
+    const PARAM: &'static str = "numbers";
+    const LABEL: &'static str = "Numbers";
+    const ALL: &'static [(Self, &'static str, &'static str)] = &[
+        (NumberFormat::Plain, "plain", "1234.5"),
+        (NumberFormat::Grouped, "grouped", "1,234.5"),
+        (NumberFormat::DecimalComma, "decimal-comma", "1.234,5"),
+    ];
+}
+
+impl Preference for ClockFormat {
+    const PARAM: &'static str = "clock";
+    const LABEL: &'static str = "Clock";
+    const ALL: &'static [(Self, &'static str, &'static str)] = &[
+        (ClockFormat::TwentyFourHour, "24h", "18:30"),
+        (ClockFormat::TwelveHour, "12h", "6:30 PM"),
+    ];
+}
+
+const PARAMS: [&str; 3] = [UnitSystem::PARAM, NumberFormat::PARAM, ClockFormat::PARAM];
+
+/// Each `key=value` pair of the query string
+fn params(query_string: &str) -> impl Iterator<Item = (&str, &str)> {
+    query_string
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+}
+
+fn param<'a>(query_string: &'a str, key: &str) -> Option<&'a str> {
+    params(query_string).find_map(|(k, value)| (k == key).then_some(value))
+}
+
+/// Splits a page's query string into the encoded scenario and the preference parameters
+/// following it, as in `<scenario>&units=metric`. Either can be empty.
+pub(super) fn split_query(query_string: &str) -> (&str, &str) {
+    let (first, rest) = query_string.split_once('&').unwrap_or((query_string, ""));
+    match first.split_once('=') {
+        Some((key, _)) if PARAMS.contains(&key) => ("", query_string),
+        _ => (first, rest),
+    }
+}
+
+impl Preferences {
+    /// These preferences with any the query string sets replaced
+    fn with_params(mut self, query_string: &str) -> Self {
+        if let Some(units) = param(query_string, UnitSystem::PARAM).and_then(UnitSystem::from_param)
+        {
+            self.units = units;
+        }
+        if let Some(numbers) =
+            param(query_string, NumberFormat::PARAM).and_then(NumberFormat::from_param)
+        {
+            self.numbers = numbers;
+        }
+        if let Some(clock) =
+            param(query_string, ClockFormat::PARAM).and_then(ClockFormat::from_param)
+        {
+            self.clock = clock;
+        }
+        self
+    }
+
+    /// Writes `value` rounded to `decimals` places in the preferred number format
+    pub(super) fn number(&self, value: f64, decimals: usize) -> String {
+        let formatted = format!("{value:.decimals$}");
+        let (thousands, decimal_mark) = match self.numbers {
+            NumberFormat::Plain => (None, '.'),
+            NumberFormat::Grouped => (Some(','), '.'),
+            NumberFormat::DecimalComma => (Some('.'), ','),
+        };
+        let (sign, digits) = match formatted.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", formatted.as_str()),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let mut out = String::from(sign);
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                out.extend(thousands);
+            }
+            out.push(digit);
+        }
+        if !fraction.is_empty() {
+            out.push(decimal_mark);
+            out.push_str(fraction);
+        }
+        out
+    }
+
+    /// "150.0 kW" in the preferred number format. `Display` stays fixed for exports and keys.
+    pub(super) fn power(&self, power: Power) -> String {
+        format!("{} kW", self.number(power.as_kw(), 1))
+    }
+
+    /// "77.4 kWh" in the preferred number format
+    pub(super) fn energy(&self, energy: Energy) -> String {
+        format!("{} kWh", self.number(energy.as_kwh(), 1))
+    }
+
+    /// "80.00%" in the preferred number format
+    pub(super) fn percent(&self, percent: PercentFull) -> String {
+        format!("{}%", self.number(percent.as_float(), 2))
+    }
+
+    pub(super) fn distance_unit(&self) -> &'static str {
+        match self.units {
+            UnitSystem::Imperial => "miles",
+            UnitSystem::Metric => "km",
+        }
+    }
+
+    pub(super) fn speed_unit(&self) -> &'static str {
+        match self.units {
+            UnitSystem::Imperial => "mph",
+            UnitSystem::Metric => "km/h",
+        }
+    }
+
+    /// Miles in the preferred distance unit, also used for speeds
+    pub(super) fn distance_value(&self, miles: f64) -> f64 {
+        match self.units {
+            UnitSystem::Imperial => miles,
+            UnitSystem::Metric => miles * KM_PER_MILE,
+        }
+    }
+
+    /// A distance typed in the preferred unit back in miles
+    pub(super) fn miles_from(&self, distance: f64) -> f64 {
+        match self.units {
+            UnitSystem::Imperial => distance,
+            UnitSystem::Metric => distance / KM_PER_MILE,
+        }
+    }
+
+    /// "150 miles" or "241 km"
+    pub(super) fn distance(&self, miles: f64) -> String {
+        format!(
+            "{} {}",
+            self.number(self.distance_value(miles), 0),
+            self.distance_unit()
+        )
+    }
+
+    /// How far `energy` goes, as "3.5 mi/kWh" or "17.8 kWh/100km"
+    pub(super) fn efficiency(&self, miles: f64, energy: Energy) -> String {
+        match self.units {
+            UnitSystem::Imperial => format!(
+                "{} mi/kWh",
+                self.number(miles / energy.as_kwh().max(f64::EPSILON), 1)
+            ),
+            UnitSystem::Metric => format!(
+                "{} kWh/100km",
+                self.number(
+                    energy.as_kwh() * 100.0 / self.distance_value(miles).max(f64::EPSILON),
+                    1
+                )
+            ),
+        }
+    }
+
+    /// "18:30" or "6:30 PM" for minutes after midnight
+    pub(super) fn time_of_day(&self, minute_of_day: u16) -> String {
+        let (hours, minutes) = (minute_of_day / 60 % 24, minute_of_day % 60);
+        match self.clock {
+            ClockFormat::TwentyFourHour => format!("{hours:02}:{minutes:02}"),
+            ClockFormat::TwelveHour => format!(
+                "{}:{minutes:02} {}",
+                (hours + 11) % 12 + 1,
+                if hours < 12 { "AM" } else { "PM" }
+            ),
+        }
+    }
+}
+
+impl Display for Preferences {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}={}&{}={}&{}={}",
+            UnitSystem::PARAM,
+            self.units.param_value(),
+            NumberFormat::PARAM,
+            self.numbers.param_value(),
+            ClockFormat::PARAM,
+            self.clock.param_value()
+        )
+    }
+}
+
+impl FromStr for Preferences {
+    type Err = std::convert::Infallible;
+
+    /// Unknown or missing preferences are left at their defaults
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Preferences::default().with_params(s))
+    }
+}
+
+/// The stored preferences and the page's query string they're read with
+#[derive(Clone, Copy)]
+struct PreferencesContext {
+    preferences: Signal<Preferences>,
+    stored: Signal<Preferences>,
+    set_stored: WriteSignal<Preferences>,
+    query_string: Memo<String>,
+}
+
+/// Reads the reader's preferences for the EV tools on this page and below it
+pub(super) fn provide_preferences() {
+    let (stored, set_stored, _) = use_local_storage::<Preferences, FromToStringCodec>(STORAGE_KEY);
+    let search = use_location().search;
+    let query_string = Memo::new(move |_| search.with(|search| split_query(search).1.to_string()));
+    let preferences =
+        Signal::derive(move || query_string.with(|query| stored().with_params(query)));
+    provide_context(PreferencesContext {
+        preferences,
+        stored,
+        set_stored,
+        query_string,
+    });
+}
+
+/// The preferences provided for this page, or the defaults outside of one. Tracked when read
+/// reactively, so text written in a closure follows changes.
+pub(super) fn preferences() -> Preferences {
+    use_context::<PreferencesContext>()
+        .map(|context| match Observer::get() {
+            Some(_) => context.preferences.get(),
+            None => context.preferences.get_untracked(),
+        })
+        .unwrap_or_default()
+}
+
+#[component]
+fn PreferenceSelect<P: Preference + Send + Sync>(
+    context: PreferencesContext,
+    get: fn(&Preferences) -> P,
+    set: fn(&mut Preferences, P),
+) -> impl IntoView {
+    let from_link = move || {
+        context
+            .query_string
+            .with(|query| param(query, P::PARAM).is_some())
+    };
+    view! {
+        <label class="flex flex-row gap-1 items-center">
+            {P::LABEL}
+            <select class="dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 transition-colors"
+                disabled=from_link
+                title=move || from_link().then_some("Set by this page's link")
+                on:change=move |e| {
+                    if let Some(value) = P::from_param(&event_target_value(&e)) {
+                        let mut preferences = context.stored.get_untracked();
+                        set(&mut preferences, value);
+                        context.set_stored.set(preferences);
+                    }
+                }>
+                {P::ALL.iter().map(|(value, param, label)| view! {
+                    <option value=*param selected=move || get(&context.preferences.get()) == *value>{*label}</option>
+                }).collect_view()}
+            </select>
+        </label>
+    }
+}
+
+/// Lets the reader pick units, number format and clock, saved in this browser
+#[component]
+pub(super) fn PreferencesEditor() -> impl IntoView {
+    let Some(context) = use_context::<PreferencesContext>() else {
+        return ().into_any();
+    };
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 items-center text-sm">
+            <PreferenceSelect context get=|p: &Preferences| p.units set=|p: &mut Preferences, units| p.units = units />
+            <PreferenceSelect context get=|p: &Preferences| p.numbers set=|p: &mut Preferences, numbers| p.numbers = numbers />
+            <PreferenceSelect context get=|p: &Preferences| p.clock set=|p: &mut Preferences, clock| p.clock = clock />
+        </div>
+    }
+    .into_any()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with(numbers: NumberFormat, clock: ClockFormat) -> Preferences {
+        Preferences {
+            numbers,
+            clock,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn numbers_are_grouped_by_thousands() {
+        let grouped = with(NumberFormat::Grouped, ClockFormat::default());
+        assert_eq!(grouped.number(999.0, 0), "999");
+        assert_eq!(grouped.number(1234.5, 1), "1,234.5");
+        assert_eq!(grouped.number(1234567.0, 0), "1,234,567");
+
+        let comma = with(NumberFormat::DecimalComma, ClockFormat::default());
+        assert_eq!(comma.number(999.25, 2), "999,25");
+        assert_eq!(comma.number(1234.5, 1), "1.234,5");
+        assert_eq!(comma.number(1234567.0, 0), "1.234.567");
+
+        let plain = with(NumberFormat::Plain, ClockFormat::default());
+        assert_eq!(plain.number(1234567.5, 1), "1234567.5");
+    }
+
+    #[test]
+    fn negative_numbers_keep_the_sign_out_of_the_grouping() {
+        let grouped = with(NumberFormat::Grouped, ClockFormat::default());
+        assert_eq!(grouped.number(-123.0, 0), "-123");
+        assert_eq!(grouped.number(-1234.5, 1), "-1,234.5");
+        assert_eq!(grouped.number(-1234567.0, 0), "-1,234,567");
+        let comma = with(NumberFormat::DecimalComma, ClockFormat::default());
+        assert_eq!(comma.number(-1234.5, 2), "-1.234,50");
+    }
+
+    #[test]
+    fn twelve_hour_clock_handles_midnight_and_noon() {
+        let twelve = with(NumberFormat::default(), ClockFormat::TwelveHour);
+        assert_eq!(twelve.time_of_day(0), "12:00 AM");
+        assert_eq!(twelve.time_of_day(59), "12:59 AM");
+        assert_eq!(twelve.time_of_day(12 * 60), "12:00 PM");
+        assert_eq!(twelve.time_of_day(13 * 60 + 5), "1:05 PM");
+        assert_eq!(twelve.time_of_day(23 * 60 + 59), "11:59 PM");
+
+        let twenty_four = with(NumberFormat::default(), ClockFormat::TwentyFourHour);
+        assert_eq!(twenty_four.time_of_day(0), "00:00");
+        assert_eq!(twenty_four.time_of_day(12 * 60), "12:00");
+        assert_eq!(twenty_four.time_of_day(18 * 60 + 30), "18:30");
+        assert_eq!(twenty_four.time_of_day(24 * 60), "00:00");
+    }
+}
@@ -0,0 +1,197 @@
+use leptos::prelude::*;
+use leptos_meta::Meta;
+
+use super::split_query;
+
+/// Path the preview images are served under, followed by the same query string as the simulator
+pub const PREVIEW_PATH: &str = "/toys/ev-charger-sim/preview.png";
+
+/// The size link previews are shown at on most sites
+const PREVIEW_WIDTH: u32 = 1200;
+const PREVIEW_HEIGHT: u32 = 630;
+
+/// Fonts commonly installed on servers, the first one found draws the preview's text
+#[cfg(feature = "ssr")]
+const SANS_SERIF_FAMILIES: [&str; 5] = [
+    "Arial",
+    "Helvetica",
+    "DejaVu Sans",
+    "Liberation Sans",
+    "Noto Sans",
+];
+
+/// Most vehicles a day a previewed scenario may have, anyone can ask for a preview so the
+/// simulation it runs has to stay small
+#[cfg(feature = "ssr")]
+const MAX_PREVIEW_VEHICLES: usize = 200;
+#[cfg(feature = "ssr")]
+const MAX_PREVIEW_CHARGERS: usize = 50;
+#[cfg(feature = "ssr")]
+const MAX_PREVIEW_PLUGS: u32 = 200;
+
+/// Previews step a minute at a time, the chart can't show anything finer at preview size
+#[cfg(feature = "ssr")]
+const PREVIEW_SIM_STEP: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long a preview's simulation may run before it gives up, well inside the server's timeout
+#[cfg(feature = "ssr")]
+const PREVIEW_BUDGET: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Runs the scenario in `query_string` and draws its simulation chart to a PNG for link previews.
+/// Returns `None` if the scenario can't be read or there's nothing to chart.
+#[cfg(feature = "ssr")]
+pub fn scenario_preview_png(query_string: &str) -> Option<Vec<u8>> {
+    use resvg::{tiny_skia, usvg};
+
+    use std::time::Instant;
+
+    use super::{decode_compressed_query, simulation_chart, Query, Sim, SimChartMode, MAX_DAYS};
+    use crate::components::line_chart_svg;
+
+    let query = decode_compressed_query::<Query>(split_query(query_string).0)
+        .map_err(|e| log::error!("unable to read scenario for preview {e}"))
+        .ok()?;
+    let plugs = query.chargers.iter().map(|c| c.num_plugs()).sum::<u32>();
+    if query.vehicles.len() > MAX_PREVIEW_VEHICLES
+        || query.chargers.len() > MAX_PREVIEW_CHARGERS
+        || plugs > MAX_PREVIEW_PLUGS
+        || query.days > MAX_DAYS
+    {
+        log::error!("scenario is too large to preview");
+        return None;
+    }
+    let mut sim = Sim::new(
+        &query.vehicles,
+        query.chargers,
+        query.site_policy,
+        query.start,
+        query.days,
+        query.solar,
+        PREVIEW_SIM_STEP,
+    );
+    let specs = sim.arriving_specs();
+    let deadline = Instant::now() + PREVIEW_BUDGET;
+    let frames = sim.run_while(|| Instant::now() < deadline);
+    if Instant::now() >= deadline {
+        log::error!("scenario took too long to preview");
+        return None;
+    }
+    if frames.is_empty() {
+        return None;
+    }
+    let chart = simulation_chart(specs, &frames, query.start, SimChartMode::Power);
+    let svg = line_chart_svg(&chart, PREVIEW_WIDTH as f64, PREVIEW_HEIGHT as f64);
+    let mut options = usvg::Options::default();
+    let fonts = options.fontdb_mut();
+    fonts.load_system_fonts();
+    // sans-serif means Arial unless told otherwise, which most servers don't have
+    let installed = |family: &str| {
+        fonts
+            .faces()
+            .any(|face| face.families.iter().any(|(name, _)| name == family))
+    };
+    if let Some(family) = SANS_SERIF_FAMILIES.into_iter().find(|f| installed(f)) {
+        fonts.set_sans_serif_family(family);
+    }
+    let tree = usvg::Tree::from_str(&svg, &options)
+        .map_err(|e| log::error!("unable to parse preview svg {e}"))
+        .ok()?;
+    let mut pixmap = tiny_skia::Pixmap::new(PREVIEW_WIDTH, PREVIEW_HEIGHT)?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|e| log::error!("unable to encode preview png {e}"))
+        .ok()
+}
+
+/// Scheme and host the page was requested from, link previews need absolute image urls
+fn origin() -> String {
+    #[cfg(feature = "ssr")]
+    {
+        use_context::<http::request::Parts>()
+            .and_then(|parts| {
+                let host = parts.headers.get(http::header::HOST)?.to_str().ok()?;
+                let scheme = parts
+                    .headers
+                    .get("x-forwarded-proto")
+                    .and_then(|scheme| scheme.to_str().ok())
+                    .unwrap_or("http");
+                Some(format!("{scheme}://{host}"))
+            })
+            .unwrap_or_default()
+    }
+    #[cfg(not(feature = "ssr"))]
+    {
+        window().location().origin().unwrap_or_default()
+    }
+}
+
+/// Open Graph tags so a shared scenario link unfurls with its simulation chart
+#[component]
+pub(super) fn ScenarioPreviewMeta(#[prop(into)] query_string: Signal<String>) -> impl IntoView {
+    let origin = origin();
+    // without a scenario there's no chart, so no image at all
+    let image = move || {
+        query_string.with(|query| {
+            let (query, _) = split_query(query);
+            (!query.is_empty()).then(|| {
+                let image = [origin.as_str(), PREVIEW_PATH, "?", query].concat();
+                view! {
+                    <Meta property="og:image" content=image />
+                    <Meta property="og:image:width" content=PREVIEW_WIDTH.to_string() />
+                    <Meta property="og:image:height" content=PREVIEW_HEIGHT.to_string() />
+                    <Meta name="twitter:card" content="summary_large_image" />
+                }
+            })
+        })
+    };
+    view! {
+        <Meta property="og:title" content="DC Fast Charger Sim" />
+        <Meta property="og:description" content="A simulated EV fast charging site, the chart shows each vehicle's charging power." />
+        {image}
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::super::{
+        encode_compressed_query, Charger, ChargerKind, Energy, LoadSharingStrategy, Power, Query,
+        Vehicle, VEHICLES,
+    };
+    use super::*;
+
+    #[test]
+    fn scenarios_with_too_many_chargers_or_plugs_are_not_previewed() {
+        let charger = |plugs| {
+            Charger::new(
+                Power::from_kw(150.0),
+                LoadSharingStrategy::Split {
+                    number_of_plugs: plugs,
+                },
+                ChargerKind::Dc,
+            )
+        };
+        let spec = &VEHICLES[0];
+        let vehicle = Vehicle::new(
+            spec,
+            Energy {
+                watt_hours: spec.battery_max.watt_hours * 0.1,
+            },
+            Energy {
+                watt_hours: spec.battery_max.watt_hours * 0.8,
+            },
+        );
+        let preview = |chargers| {
+            let query = Query {
+                chargers,
+                vehicles: [vehicle.clone()].into(),
+                ..Default::default()
+            };
+            scenario_preview_png(&encode_compressed_query(&query))
+        };
+        assert!(preview(vec![charger(2)]).is_some());
+        assert_eq!(preview(vec![charger(1); MAX_PREVIEW_CHARGERS + 1]), None);
+        assert_eq!(preview(vec![charger(MAX_PREVIEW_PLUGS + 1)]), None);
+    }
+}
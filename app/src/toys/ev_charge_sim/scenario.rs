@@ -0,0 +1,460 @@
+use leptos::{prelude::*, task::spawn_local};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{wasm_bindgen::JsCast, HtmlInputElement};
+
+use super::{export::download_file, Query};
+
+#[derive(Debug, Error)]
+pub(super) enum ScenarioError {
+    #[error("the link isn't valid base64 ({0})")]
+    Base64(#[from] base64::DecodeError),
+    #[error("the link couldn't be decompressed ({0})")]
+    Decompress(#[from] std::io::Error),
+    #[error("the scenario isn't valid text ({0})")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("the scenario couldn't be read ({0})")]
+    Json(#[from] serde_json::Error),
+    #[error("the scenario was saved with version {0}, which is newer than this page understands")]
+    UnsupportedVersion(u64),
+    #[error("unable to read the file")]
+    File,
+    #[error("the scenario is larger than {} KiB", MAX_SCENARIO_BYTES / 1024)]
+    TooLarge,
+}
+
+/// The most a scenario may decompress to, links and stored scenarios past this are refused
+pub(super) const MAX_SCENARIO_BYTES: usize = 512 * 1024;
+
+/// A document that records its schema version so older saves can be migrated forwards
+pub(super) trait Versioned: Serialize + DeserializeOwned {
+    /// the version written into newly saved documents
+    const VERSION: u64;
+
+    /// upgrades a document from `version` to `version + 1`
+    fn migrate(version: u64, document: Value) -> Result<Value, ScenarioError>;
+}
+
+/// Vehicles whose capacity vehicle data version 3 changed from gross to usable, in kWh
+const RESIZED_BATTERIES: [(&str, f64, f64); 4] = [
+    ("Porsche Taycan 2022", 93.4, 83.7),
+    ("Tesla Model 3 LR AWD 2021", 82.0, 75.0),
+    ("Tesla Model Y LR AWD 2022", 82.0, 75.0),
+    ("Nissan Leaf Plus 2022", 62.0, 59.0),
+];
+
+impl Versioned for Query {
+    const VERSION: u64 = 8;
+
+    fn migrate(version: u64, mut document: Value) -> Result<Value, ScenarioError> {
+        match version {
+            // version 0 is the unversioned shape links were created with before versioning, the
+            // fields are unchanged so the version tag is all that needs to be added.
+            0 => Ok(document),
+            // AC chargers were added in version 2, everything before was DC
+            1 => {
+                if let Some(chargers) = document.get_mut("chargers").and_then(Value::as_array_mut) {
+                    for charger in chargers.iter_mut().filter_map(Value::as_object_mut) {
+                        charger.insert("kind".to_string(), "Dc".into());
+                    }
+                }
+                Ok(document)
+            }
+            // vehicle to grid was added in version 3, nothing before could discharge
+            2 => {
+                if let Some(chargers) = document.get_mut("chargers").and_then(Value::as_array_mut) {
+                    for charger in chargers.iter_mut().filter_map(Value::as_object_mut) {
+                        charger.insert("bidirectional".to_string(), false.into());
+                    }
+                }
+                if let Some(vehicles) = document.get_mut("vehicles").and_then(Value::as_array_mut) {
+                    for vehicle in vehicles.iter_mut().filter_map(Value::as_object_mut) {
+                        vehicle.insert("v2g_reserve".to_string(), Value::Null);
+                    }
+                }
+                if let Some(fields) = document.as_object_mut() {
+                    fields.insert("site_policy".to_string(), "None".into());
+                }
+                Ok(document)
+            }
+            // the start time and solar were added in version 4, the start is the one new
+            // scenarios get since earlier runs had no time of day
+            3 => {
+                if let Some(fields) = document.as_object_mut() {
+                    fields.insert(
+                        "start".to_string(),
+                        json!({ "day_of_year": 172, "minute_of_day": 480 }),
+                    );
+                    fields.insert("solar".to_string(), Value::Null);
+                }
+                Ok(document)
+            }
+            // multi-day runs and arrival times were added in version 5, before every vehicle
+            // arrived at the start of a single day
+            4 => {
+                if let Some(vehicles) = document.get_mut("vehicles").and_then(Value::as_array_mut) {
+                    for vehicle in vehicles.iter_mut().filter_map(Value::as_object_mut) {
+                        vehicle.insert("arrival".to_string(), Value::Null);
+                    }
+                }
+                if let Some(fields) = document.as_object_mut() {
+                    fields.insert("days".to_string(), 1.into());
+                }
+                Ok(document)
+            }
+            // the forecast was added in version 6, starting from an even year without pricing
+            5 => {
+                if let Some(fields) = document.as_object_mut() {
+                    fields.insert(
+                        "forecast".to_string(),
+                        json!({ "seasons": [1.0, 1.0, 1.0, 1.0], "pricing": null }),
+                    );
+                }
+                Ok(document)
+            }
+            // charger hardware presets were added in version 7, earlier chargers were built by hand
+            6 => {
+                if let Some(chargers) = document.get_mut("chargers").and_then(Value::as_array_mut) {
+                    for charger in chargers.iter_mut().filter_map(Value::as_object_mut) {
+                        charger.insert("preset".to_string(), Value::Null);
+                    }
+                }
+                Ok(document)
+            }
+            // vehicle data version 3 lists usable capacity for vehicles that had gross, charge
+            // is stored as energy so it's scaled to keep each vehicle at the same SOC
+            7 => {
+                if let Some(vehicles) = document.get_mut("vehicles").and_then(Value::as_array_mut) {
+                    for vehicle in vehicles.iter_mut().filter_map(Value::as_object_mut) {
+                        let name = vehicle
+                            .get("spec")
+                            .and_then(|spec| spec.get("name"))
+                            .and_then(Value::as_str);
+                        let Some((_, before, after)) = RESIZED_BATTERIES
+                            .iter()
+                            .find(|(resized, ..)| Some(*resized) == name)
+                        else {
+                            continue;
+                        };
+                        for field in ["current_charge", "unplug_at", "v2g_reserve"] {
+                            if let Some(watt_hours) = vehicle
+                                .get_mut(field)
+                                .and_then(|energy| energy.get_mut("watt_hours"))
+                            {
+                                if let Some(value) = watt_hours.as_f64() {
+                                    *watt_hours = (value * after / before).into();
+                                }
+                            }
+                        }
+                    }
+                }
+                Ok(document)
+            }
+            version => Err(ScenarioError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// Reads a versioned document, migrating it to the current version if needed.
+/// Documents without a `version` are treated as version 0.
+pub(super) fn from_versioned_json<T: Versioned>(json: &str) -> Result<T, ScenarioError> {
+    let mut document: Value = serde_json::from_str(json)?;
+    let mut version = document
+        .get("version")
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+    if version > T::VERSION {
+        return Err(ScenarioError::UnsupportedVersion(version));
+    }
+    while version < T::VERSION {
+        document = T::migrate(version, document)?;
+        version += 1;
+    }
+    Ok(serde_json::from_value(document)?)
+}
+
+/// Serializes the document with the current version tag
+pub(super) fn to_versioned_json<T: Versioned>(value: &T) -> Result<Value, ScenarioError> {
+    let mut document = serde_json::to_value(value)?;
+    if let Value::Object(fields) = &mut document {
+        fields.insert("version".to_string(), T::VERSION.into());
+    }
+    Ok(document)
+}
+
+#[component]
+pub(super) fn ScenarioErrorBanner(#[prop(into)] error: Signal<Option<String>>) -> impl IntoView {
+    view! {
+        {move || error().map(|error| view! {
+            <div role="alert" class="rounded-lg border border-red-700 bg-red-100 dark:bg-red-900 text-red-900 dark:text-red-100 p-3">
+                <span class="font-bold">"Unable to load this scenario: "</span>
+                {error}
+                ". You're starting from an empty scenario instead."
+            </div>
+        })}
+    }
+}
+
+#[component]
+pub(super) fn ScenarioFiles(
+    #[prop(into)] query: Signal<Query>,
+    set_query: SignalSetter<Query>,
+    set_error: WriteSignal<Option<String>>,
+) -> impl IntoView {
+    let button = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors cursor-pointer";
+    let load_file = move |e: leptos::ev::Event| {
+        let Some(file) = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0))
+        else {
+            return;
+        };
+        spawn_local(async move {
+            let text = JsFuture::from(file.text())
+                .await
+                .ok()
+                .and_then(|text| text.as_string())
+                .ok_or(ScenarioError::File);
+            match text.and_then(|text| from_versioned_json::<Query>(&text)) {
+                Ok(query) => {
+                    set_error(None);
+                    set_query(query);
+                }
+                Err(e) => set_error(Some(e.to_string())),
+            }
+        });
+    };
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 items-center">
+            "Scenario:"
+            <button class=button on:click=move |_| {
+                match query.with_untracked(to_versioned_json).and_then(|doc| Ok(serde_json::to_string_pretty(&doc)?)) {
+                    Ok(json) => download_file("ev-scenario.json", "application/json", &json),
+                    Err(e) => log::error!("unable to save scenario {e}"),
+                }
+            }>"Save to file"</button>
+            <label class=button>
+                "Load from file"
+                <input type="file" accept=".json,application/json" class="hidden" on:change=load_file />
+            </label>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        AcPhases, Charger, ChargerKind, Energy, LoadSharingStrategy, Power, ScenarioStart,
+        SitePolicy, Vehicle, VEHICLES,
+    };
+    use super::*;
+
+    // Scenarios as each version saved them, every version carries the fields the one before it
+    // added, set away from the defaults migration fills in
+
+    const V0: &str = r#"{
+        "chargers": [{ "grid_connection": { "watts": 150000 }, "strategy": { "Paired": { "number_of_plugs": 2 } } }],
+        "vehicles": [{ "spec": { "name": "KIA EV6 Long Range AWD" }, "current_charge": { "watt_hours": 10000.0 }, "unplug_at": { "watt_hours": 60000.0 } }]
+    }"#;
+
+    const V1: &str = r#"{
+        "version": 1,
+        "chargers": [{ "grid_connection": { "watts": 150000 }, "strategy": { "Paired": { "number_of_plugs": 2 } } }],
+        "vehicles": [{ "spec": { "name": "KIA EV6 Long Range AWD" }, "current_charge": { "watt_hours": 10000.0 }, "unplug_at": { "watt_hours": 60000.0 } }]
+    }"#;
+
+    const V2: &str = r#"{
+        "version": 2,
+        "chargers": [{ "grid_connection": { "watts": 22000 }, "strategy": "None", "kind": { "Ac": { "phases": "Three" } } }],
+        "vehicles": [{ "spec": { "name": "KIA EV6 Long Range AWD" }, "current_charge": { "watt_hours": 10000.0 }, "unplug_at": { "watt_hours": 60000.0 } }]
+    }"#;
+
+    const V3: &str = r#"{
+        "version": 3,
+        "chargers": [{ "grid_connection": { "watts": 22000 }, "strategy": "None", "kind": { "Ac": { "phases": "Three" } }, "bidirectional": true }],
+        "vehicles": [{ "spec": { "name": "KIA EV6 Long Range AWD" }, "current_charge": { "watt_hours": 10000.0 }, "unplug_at": { "watt_hours": 60000.0 }, "v2g_reserve": { "watt_hours": 20000.0 } }],
+        "site_policy": { "PeakShaving": { "threshold": { "watts": 80000 } } }
+    }"#;
+
+    const V4: &str = r#"{
+        "version": 4,
+        "chargers": [{ "grid_connection": { "watts": 22000 }, "strategy": "None", "kind": { "Ac": { "phases": "Three" } }, "bidirectional": true }],
+        "vehicles": [{ "spec": { "name": "KIA EV6 Long Range AWD" }, "current_charge": { "watt_hours": 10000.0 }, "unplug_at": { "watt_hours": 60000.0 }, "v2g_reserve": { "watt_hours": 20000.0 } }],
+        "site_policy": { "PeakShaving": { "threshold": { "watts": 80000 } } },
+        "start": { "day_of_year": 30, "minute_of_day": 600 },
+        "solar": null
+    }"#;
+
+    const V5: &str = r#"{
+        "version": 5,
+        "chargers": [{ "grid_connection": { "watts": 22000 }, "strategy": "None", "kind": { "Ac": { "phases": "Three" } }, "bidirectional": true }],
+        "vehicles": [{ "spec": { "name": "KIA EV6 Long Range AWD" }, "current_charge": { "watt_hours": 10000.0 }, "unplug_at": { "watt_hours": 60000.0 }, "v2g_reserve": { "watt_hours": 20000.0 }, "arrival": 540 }],
+        "site_policy": { "PeakShaving": { "threshold": { "watts": 80000 } } },
+        "start": { "day_of_year": 30, "minute_of_day": 600 },
+        "solar": null,
+        "days": 3
+    }"#;
+
+    const V6: &str = r#"{
+        "version": 6,
+        "chargers": [{ "grid_connection": { "watts": 22000 }, "strategy": "None", "kind": { "Ac": { "phases": "Three" } }, "bidirectional": true }],
+        "vehicles": [{ "spec": { "name": "KIA EV6 Long Range AWD" }, "current_charge": { "watt_hours": 10000.0 }, "unplug_at": { "watt_hours": 60000.0 }, "v2g_reserve": { "watt_hours": 20000.0 }, "arrival": 540 }],
+        "site_policy": { "PeakShaving": { "threshold": { "watts": 80000 } } },
+        "start": { "day_of_year": 30, "minute_of_day": 600 },
+        "solar": null,
+        "days": 3,
+        "forecast": { "seasons": [0.8, 1.0, 1.2, 1.0], "pricing": null }
+    }"#;
+
+    const V7: &str = r#"{
+        "version": 7,
+        "chargers": [],
+        "vehicles": [
+            { "spec": { "name": "Porsche Taycan 2022" }, "current_charge": { "watt_hours": 46700.0 }, "unplug_at": { "watt_hours": 74720.0 }, "v2g_reserve": { "watt_hours": 18680.0 }, "arrival": null },
+            { "spec": { "name": "KIA EV6 Long Range AWD" }, "current_charge": { "watt_hours": 10000.0 }, "unplug_at": { "watt_hours": 60000.0 }, "v2g_reserve": null, "arrival": null }
+        ],
+        "site_policy": "None",
+        "start": { "day_of_year": 172, "minute_of_day": 480 },
+        "solar": null,
+        "days": 1,
+        "forecast": { "seasons": [1.0, 1.0, 1.0, 1.0], "pricing": null }
+    }"#;
+
+    /// What a version 0 or 1 scenario reads as
+    fn before_ac() -> Query {
+        let vehicle = Vehicle::new(
+            &VEHICLES[0],
+            Energy {
+                watt_hours: 10_000.0,
+            },
+            Energy {
+                watt_hours: 60_000.0,
+            },
+        );
+        Query {
+            chargers: vec![Charger::new(
+                Power::from_kw(150.0),
+                LoadSharingStrategy::Paired { number_of_plugs: 2 },
+                ChargerKind::Dc,
+            )],
+            vehicles: [vehicle].into(),
+            ..Default::default()
+        }
+    }
+
+    fn with_ac() -> Query {
+        let mut query = before_ac();
+        query.chargers = vec![Charger::new(
+            Power::from_kw(22.0),
+            LoadSharingStrategy::None,
+            ChargerKind::Ac {
+                phases: AcPhases::Three,
+            },
+        )];
+        query
+    }
+
+    fn with_v2g() -> Query {
+        let mut query = with_ac();
+        query.chargers[0].bidirectional = true;
+        query.vehicles[0].v2g_reserve = Some(Energy {
+            watt_hours: 20_000.0,
+        });
+        query.site_policy = SitePolicy::PeakShaving {
+            threshold: Power::from_kw(80.0),
+        };
+        query
+    }
+
+    fn with_start() -> Query {
+        let mut query = with_v2g();
+        query.start = ScenarioStart {
+            day_of_year: 30,
+            minute_of_day: 600,
+        };
+        query
+    }
+
+    fn with_days() -> Query {
+        let mut query = with_start();
+        query.vehicles[0].arrival = Some(540);
+        query.days = 3;
+        query
+    }
+
+    fn with_forecast() -> Query {
+        let mut query = with_days();
+        query.forecast =
+            serde_json::from_value(json!({ "seasons": [0.8, 1.0, 1.2, 1.0], "pricing": null }))
+                .unwrap();
+        query
+    }
+
+    fn assert_reads_as(document: &str, expected: Query) {
+        let query = from_versioned_json::<Query>(document).unwrap();
+        assert_eq!(
+            to_versioned_json(&query).unwrap(),
+            to_versioned_json(&expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_unversioned_scenarios() {
+        assert_reads_as(V0, before_ac());
+    }
+
+    #[test]
+    fn reads_version_1_chargers_as_dc() {
+        assert_reads_as(V1, before_ac());
+    }
+
+    #[test]
+    fn reads_version_2_without_v2g() {
+        assert_reads_as(V2, with_ac());
+    }
+
+    #[test]
+    fn reads_version_3_starting_on_a_summer_morning() {
+        assert_reads_as(V3, with_v2g());
+    }
+
+    #[test]
+    fn reads_version_4_as_a_single_day() {
+        assert_reads_as(V4, with_start());
+    }
+
+    #[test]
+    fn reads_version_5_with_an_even_year() {
+        assert_reads_as(V5, with_days());
+    }
+
+    #[test]
+    fn reads_version_6_without_presets() {
+        assert_reads_as(V6, with_forecast());
+    }
+
+    #[test]
+    fn reads_version_7_resized_batteries_at_the_same_soc() {
+        let query = from_versioned_json::<Query>(V7).unwrap();
+        let (taycan, kia) = (&query.vehicles[0], &query.vehicles[1]);
+        assert_eq!(taycan.spec_details().battery_max.as_kwh(), 83.7);
+        let soc = |energy: Energy| energy.watt_hours / taycan.spec_details().battery_max.watt_hours;
+        assert!((soc(taycan.current_charge) - 0.5).abs() < 1e-9);
+        assert!((soc(taycan.unplug_at) - 0.8).abs() < 1e-9);
+        assert!((soc(taycan.v2g_reserve.unwrap()) - 0.2).abs() < 1e-9);
+        assert_eq!(kia.current_charge.watt_hours, 10_000.0);
+        assert_eq!(kia.unplug_at.watt_hours, 60_000.0);
+    }
+
+    #[test]
+    fn refuses_newer_versions() {
+        assert!(matches!(
+            from_versioned_json::<Query>(r#"{ "version": 9 }"#),
+            Err(ScenarioError::UnsupportedVersion(9))
+        ));
+    }
+}
@@ -0,0 +1,263 @@
+use leptos::prelude::*;
+use leptos_router::{components::Redirect, hooks::use_params_map};
+
+use super::{encode_compressed_query, Query};
+
+/// Path the short links are served under, followed by the id
+const SHORT_LINK_PATH: &str = "/toys/ev-charger-sim/s/";
+
+/// Stores the scenario, taking it in the same compressed form as the query string
+#[server]
+async fn save_short_link(scenario: String) -> Result<String, ServerFnError> {
+    let query = super::decode_compressed_query::<Query>(&scenario)?;
+    let document = super::scenario::to_versioned_json(&query)?.to_string();
+    let id = store::save(&document).await.map_err(|e| {
+        log::error!("unable to store scenario {e}");
+        ServerFnError::new("unable to store scenario")
+    })?;
+    Ok(id)
+}
+
+/// Returns the stored scenario as a compressed query string
+#[server]
+async fn load_short_link(id: String) -> Result<String, ServerFnError> {
+    let document = store::load(&id)
+        .await
+        .map_err(|e| {
+            log::error!("unable to read scenario {id} {e}");
+            ServerFnError::new("unable to read scenario")
+        })?
+        .ok_or_else(|| ServerFnError::new("this link doesn't exist"))?;
+    let query = super::scenario::from_versioned_json::<Query>(&document)?;
+    Ok(encode_compressed_query(&query))
+}
+
+/// File backed storage for shared scenarios.
+/// Scenarios are written to `SCENARIO_STORE_DIR` (defaults to `./scenarios`), one file per id.
+#[cfg(feature = "ssr")]
+mod store {
+    use std::{
+        io,
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use super::super::MAX_SCENARIO_BYTES;
+
+    /// Scenarios kept before new ones are refused, so anonymous saves can't fill the disk
+    const MAX_STORED_SCENARIOS: usize = 10_000;
+    const ID_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    /// Numbers temporary files so concurrent saves never write to the same one
+    static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+    fn store_dir() -> PathBuf {
+        std::env::var("SCENARIO_STORE_DIR")
+            .unwrap_or_else(|_| "scenarios".to_string())
+            .into()
+    }
+
+    /// FNV-1a, so the same scenario always gets the same id
+    fn content_id(document: &str, salt: u64) -> String {
+        let mut hash = 0xcbf29ce484222325u64 ^ salt;
+        for byte in document.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        let mut id = String::new();
+        while hash > 0 {
+            id.push(ID_ALPHABET[(hash % ID_ALPHABET.len() as u64) as usize] as char);
+            hash /= ID_ALPHABET.len() as u64;
+        }
+        id
+    }
+
+    fn is_valid_id(id: &str) -> bool {
+        !id.is_empty() && id.len() <= 16 && id.bytes().all(|b| ID_ALPHABET.contains(&b))
+    }
+
+    pub(super) async fn save(document: &str) -> io::Result<String> {
+        if document.len() > MAX_SCENARIO_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "scenario is too large",
+            ));
+        }
+        let dir = store_dir();
+        tokio::fs::create_dir_all(&dir).await?;
+        for salt in 0.. {
+            let id = content_id(document, salt);
+            let path = dir.join(&id);
+            match tokio::fs::read_to_string(&path).await {
+                Ok(existing) if existing == document => return Ok(id),
+                // hash collision with a different scenario, try the next salt
+                Ok(_) => continue,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    if stored_count(&dir).await? >= MAX_STORED_SCENARIOS {
+                        return Err(io::Error::new(
+                            io::ErrorKind::StorageFull,
+                            "too many scenarios are stored",
+                        ));
+                    }
+                    // written aside and moved into place, so a reader never sees half a file
+                    let temp = dir.join(format!(
+                        ".{id}.{}.{}.tmp",
+                        std::process::id(),
+                        NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+                    ));
+                    tokio::fs::write(&temp, document).await?;
+                    if let Err(e) = tokio::fs::rename(&temp, &path).await {
+                        let _ = tokio::fs::remove_file(&temp).await;
+                        return Err(e);
+                    }
+                    return Ok(id);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("ran out of salts")
+    }
+
+    /// Scenarios in the store, leaving out temporary files
+    async fn stored_count(dir: &Path) -> io::Result<usize> {
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        let mut count = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_name().to_str().is_some_and(is_valid_id) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    pub(super) async fn load(id: &str) -> io::Result<Option<String>> {
+        if !is_valid_id(id) {
+            return Ok(None);
+        }
+        match tokio::fs::read_to_string(store_dir().join(id)).await {
+            Ok(document) => Ok(Some(document)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn ids_are_stable_and_from_the_alphabet() {
+            let id = content_id("{}", 0);
+            assert_eq!(id, content_id("{}", 0));
+            assert_ne!(id, content_id("{}", 1));
+            assert_ne!(id, content_id("{ }", 0));
+            assert!(is_valid_id(&id));
+        }
+
+        #[test]
+        fn ids_that_could_leave_the_store_are_rejected() {
+            for id in [
+                "",
+                "..",
+                "../x",
+                "a/b",
+                "/etc",
+                ".x.1.2.tmp",
+                "0123456789ABCDEFG",
+            ] {
+                assert!(!is_valid_id(id), "{id}");
+            }
+        }
+
+        // the only test that points the store somewhere, the directory is process wide
+        #[tokio::test]
+        async fn saves_are_deduplicated_salted_and_capped() {
+            let dir = std::env::temp_dir().join(format!("scenario-store-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::env::set_var("SCENARIO_STORE_DIR", &dir);
+
+            let id = save(r#"{"a":1}"#).await.unwrap();
+            assert_eq!(save(r#"{"a":1}"#).await.unwrap(), id);
+            assert_eq!(load(&id).await.unwrap().as_deref(), Some(r#"{"a":1}"#));
+            assert_eq!(load("../x").await.unwrap(), None);
+
+            // a different scenario already under the id moves on to the next salt
+            let document = r#"{"b":2}"#;
+            std::fs::write(dir.join(content_id(document, 0)), "something else").unwrap();
+            assert_eq!(save(document).await.unwrap(), content_id(document, 1));
+
+            // leftover temporary files aren't scenarios
+            std::fs::write(dir.join(".x.1.2.tmp"), "").unwrap();
+            assert_eq!(stored_count(&dir).await.unwrap(), 3);
+            for i in 0..MAX_STORED_SCENARIOS - 3 {
+                std::fs::write(dir.join(format!("fill{i}")), "").unwrap();
+            }
+            let full = save(r#"{"c":3}"#).await.unwrap_err();
+            assert_eq!(full.kind(), io::ErrorKind::StorageFull);
+            // scenarios already stored still get their link
+            assert_eq!(save(r#"{"a":1}"#).await.unwrap(), id);
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
+
+#[component]
+pub(super) fn ShortLink(#[prop(into)] query: Signal<Query>) -> impl IntoView {
+    let save = Action::new(|query: &Query| save_short_link(encode_compressed_query(query)));
+    let link = move || {
+        save.value().get().map(|id| {
+            id.map(|id| {
+                let origin = window().location().origin().unwrap_or_default();
+                [origin.as_str(), SHORT_LINK_PATH, id.as_str()].concat()
+            })
+        })
+    };
+    // an older short link no longer matches once the scenario is edited
+    Effect::watch(
+        move || query.track(),
+        move |_, _, _| save.value().set(None),
+        false,
+    );
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 items-center">
+            <button class="rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors"
+                disabled=save.pending()
+                on:click=move |_| { save.dispatch(query.get_untracked()); }>
+                "Create short link"
+            </button>
+            {move || match link() {
+                Some(Ok(link)) => view! {
+                    <input readonly class="dark:bg-slate-700 bg-white border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-96" prop:value=link
+                        on:focus=move |e| { event_target::<web_sys::HtmlInputElement>(&e).select(); } />
+                }.into_any(),
+                Some(Err(e)) => view! { <span class="text-red-600">{e.to_string()}</span> }.into_any(),
+                None => ().into_any(),
+            }}
+        </div>
+    }
+}
+
+/// Loads a stored scenario and sends the user to the simulator with it in the query string
+#[component]
+pub fn SharedScenario() -> impl IntoView {
+    let params = use_params_map();
+    let scenario = Resource::new_blocking(
+        move || params.with(|p| p.get("id").unwrap_or_default()),
+        load_short_link,
+    );
+    view! {
+        <Suspense fallback=move || view! { "loading scenario" }>
+            {move || scenario.get().map(|scenario| match scenario {
+                Ok(query) => view! {
+                    <Redirect path=format!("/toys/ev-charger-sim?{query}") />
+                }.into_any(),
+                Err(e) => view! {
+                    <div role="alert" class="flex flex-col gap-2 py-8">
+                        <span>"Unable to load this scenario: "{e.to_string()}</span>
+                        <a class="text-amber-600 dark:text-teal-400 hover:underline" href="/toys/ev-charger-sim">"Open the simulator"</a>
+                    </div>
+                }.into_any(),
+            })}
+        </Suspense>
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use leptos::{prelude::*, task::spawn_local};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{wasm_bindgen::JsCast, HtmlInputElement};
+
+use super::{Power, ScenarioStart};
+
+/// Solar panels on the site, their output is used before any grid power
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(super) enum SolarArray {
+    /// Horizontal panels under a clear sky, `peak` is their rating at 1000 W/m²
+    ClearSky { peak: Power, latitude: f64 },
+    /// Output in kW for each hour from midnight on January 1st, repeating when it runs out,
+    /// so a single day of 24 rows is reused for every day
+    Hourly { kilowatts: Vec<f64> },
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+enum SolarError {
+    #[error("unable to read the file")]
+    File,
+    #[error("no hourly kW values were found in the file")]
+    Empty,
+}
+
+/// The share of a horizontal panel's rating it produces under a clear sky, using
+/// Meinel's air mass model for direct light. The clock is taken as solar time.
+fn clear_sky_fraction(latitude: f64, day_of_year: f64, hour_of_day: f64) -> f64 {
+    let declination =
+        (-23.44_f64).to_radians() * (360.0 / 365.0 * (day_of_year + 10.0)).to_radians().cos();
+    let hour_angle = ((hour_of_day - 12.0) * 15.0).to_radians();
+    let latitude = latitude.to_radians();
+    let sin_elevation =
+        latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    if sin_elevation <= 0.0 {
+        return 0.0;
+    }
+    let air_mass = 1.0 / sin_elevation;
+    (1.353 * 0.7_f64.powf(air_mass.powf(0.678)) * sin_elevation).min(1.0)
+}
+
+impl SolarArray {
+    /// Output `elapsed` into a simulation that began at `start`
+    pub(super) fn output_at(&self, start: ScenarioStart, elapsed: Duration) -> Power {
+        let hour_of_year = start.hour_of_year(elapsed);
+        match self {
+            SolarArray::ClearSky { peak, latitude } => {
+                let day_of_year = (hour_of_year / 24.0).floor() % 365.0 + 1.0;
+                *peak * clear_sky_fraction(*latitude, day_of_year, hour_of_year % 24.0)
+            }
+            SolarArray::Hourly { kilowatts } if !kilowatts.is_empty() => {
+                let hour = hour_of_year.floor() as usize % kilowatts.len();
+                Power::from_kw(kilowatts[hour].max(0.0))
+            }
+            SolarArray::Hourly { .. } => Power::default(),
+        }
+    }
+}
+
+/// Reads one kW value per row, from the last column so "hour,kw" files work too.
+/// Rows that aren't numbers, like a header, are skipped.
+fn parse_hourly_csv(text: &str) -> Result<Vec<f64>, SolarError> {
+    let kilowatts = text
+        .lines()
+        .filter_map(|line| line.rsplit(',').next())
+        .filter_map(|cell| cell.trim().trim_matches('"').parse::<f64>().ok())
+        .collect::<Vec<_>>();
+    if kilowatts.is_empty() {
+        return Err(SolarError::Empty);
+    }
+    Ok(kilowatts)
+}
+
+#[component]
+pub(super) fn SolarEditor(
+    #[prop(into)] solar: Signal<Option<SolarArray>>,
+    set_solar: SignalSetter<Option<SolarArray>>,
+) -> impl IntoView {
+    let (error, set_error) = signal(None::<SolarError>);
+    let btn_active =
+        "rounded-lg bg-amber-100 dark:bg-teal-900 p-1.5 border border-amber-400 dark:border-teal-500 font-medium";
+    let btn_inactive = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1.5 border border-slate-300 dark:border-slate-500 transition-colors cursor-pointer";
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors";
+    let clear_sky = move || match solar() {
+        Some(SolarArray::ClearSky { peak, latitude }) => Some((peak, latitude)),
+        _ => None,
+    };
+    let hours_loaded = move || match solar() {
+        Some(SolarArray::Hourly { kilowatts }) => Some(kilowatts.len()),
+        _ => None,
+    };
+    let load_file = move |e: leptos::ev::Event| {
+        let Some(file) = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0))
+        else {
+            return;
+        };
+        spawn_local(async move {
+            let text = JsFuture::from(file.text())
+                .await
+                .ok()
+                .and_then(|text| text.as_string())
+                .ok_or(SolarError::File);
+            match text.and_then(|text| parse_hourly_csv(&text)) {
+                Ok(kilowatts) => {
+                    set_error(None);
+                    set_solar(Some(SolarArray::Hourly { kilowatts }));
+                }
+                Err(e) => set_error(Some(e)),
+            }
+        });
+    };
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 items-center">
+            "Solar:"
+            <button class=move || if solar.with(Option::is_none) { btn_active } else { btn_inactive }
+                on:click=move |_| set_solar(None)>"none"</button>
+            <button class=move || if clear_sky().is_some() { btn_active } else { btn_inactive }
+                on:click=move |_| set_solar(Some(SolarArray::ClearSky { peak: Power::from_kw(100.0), latitude: 40.0 }))>"clear sky"</button>
+            <label class=move || if hours_loaded().is_some() { btn_active } else { btn_inactive }>
+                "hourly kW from CSV"
+                <input type="file" accept=".csv,text/csv" class="hidden" on:change=load_file />
+            </label>
+            {move || clear_sky().map(|(peak, latitude)| view! {
+                <span>"peak kW"</span>
+                <input class=input prop:value=peak.as_kw()
+                    on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                        set_solar(Some(SolarArray::ClearSky { peak: Power::from_kw(value.max(0.0)), latitude }));
+                    } } />
+                <span>"latitude"</span>
+                <input class=input prop:value=latitude
+                    on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                        set_solar(Some(SolarArray::ClearSky { peak, latitude: value.clamp(-90.0, 90.0) }));
+                    } } />
+            })}
+            {move || hours_loaded().map(|hours| view! { <span>{hours}" hours loaded, repeating from midnight January 1st"</span> })}
+            {move || error().map(|error| view! { <span role="alert" class="text-red-600">{error.to_string()}</span> })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(hours: u64) -> Duration {
+        Duration::from_secs(hours * 60 * 60)
+    }
+
+    #[test]
+    fn clear_sky_output_follows_the_sun() {
+        let array = SolarArray::ClearSky {
+            peak: Power::from_kw(100.0),
+            latitude: 40.0,
+        };
+        // starts at 8am on June 21st
+        let start = ScenarioStart::default();
+        let kw = |elapsed| array.output_at(start, elapsed).as_kw();
+        assert!((kw(hours(4)) - 89.84).abs() < 0.01);
+        // mirrored around solar noon
+        assert!((kw(hours(1)) - 66.08).abs() < 0.01);
+        assert!((kw(hours(7)) - 66.08).abs() < 0.01);
+        // dark before dawn and overnight
+        assert_eq!(kw(hours(20)), 0.0);
+        assert_eq!(kw(hours(16)), 0.0);
+        // a December noon is much lower
+        let december = ScenarioStart {
+            day_of_year: 355,
+            minute_of_day: 12 * 60,
+        };
+        assert!((array.output_at(december, Duration::ZERO).as_kw() - 32.69).abs() < 0.01);
+    }
+
+    #[test]
+    fn hourly_output_repeats_and_ignores_negatives() {
+        let array = SolarArray::Hourly {
+            kilowatts: (0..24).map(|hour| hour as f64 - 12.0).collect(),
+        };
+        let midnight = ScenarioStart {
+            day_of_year: 1,
+            minute_of_day: 0,
+        };
+        let kw = |start, elapsed| array.output_at(start, elapsed).as_kw();
+        assert_eq!(kw(midnight, hours(15)), 3.0);
+        assert_eq!(kw(midnight, hours(15) + Duration::from_secs(59 * 60)), 3.0);
+        // the single day repeats on every other day of the year
+        assert_eq!(kw(ScenarioStart::default(), hours(10)), 6.0);
+        assert_eq!(kw(midnight, hours(3)), 0.0);
+        assert_eq!(
+            SolarArray::Hourly { kilowatts: vec![] }.output_at(midnight, hours(12)),
+            Power::default()
+        );
+    }
+
+    #[test]
+    fn hourly_csv_takes_the_last_column_and_skips_headers() {
+        assert_eq!(
+            parse_hourly_csv("hour,kw\n0,0\n1,\"2.5\"\n2, 10\n"),
+            Ok(vec![0.0, 2.5, 10.0])
+        );
+        assert_eq!(parse_hourly_csv("1.5\r\n2\r\n"), Ok(vec![1.5, 2.0]));
+        assert_eq!(parse_hourly_csv("kw\n"), Err(SolarError::Empty));
+    }
+}
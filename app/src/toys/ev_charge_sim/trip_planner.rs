@@ -0,0 +1,499 @@
+use std::time::Duration;
+
+use itertools::Itertools;
+use leptos::prelude::*;
+use leptos_meta::Title;
+use thiserror::Error;
+
+use crate::components::{ChartAxis, ChartMarker, ChartSeries, LineChart, LineChartData};
+
+use super::{
+    preferences, provide_preferences, Energy, PercentFull, Power, PreferencesEditor,
+    VehicleDropdown, VehicleSpec,
+};
+
+/// State of charge resolution the planner searches over, in [`PercentFull`]'s internal units
+const SOC_STEP: i16 = 50;
+const SOC_LEVELS: usize = (10000 / SOC_STEP) as usize + 1;
+
+fn soc_level(level: usize) -> PercentFull {
+    PercentFull(level as i16 * SOC_STEP)
+}
+
+/// A charger somewhere along the route
+#[derive(Clone, Copy, PartialEq)]
+struct ChargerStop {
+    /// miles from the start of the trip
+    distance: f64,
+    max_power: Power,
+}
+
+#[derive(Clone, PartialEq)]
+struct Trip {
+    /// miles from start to destination
+    distance: f64,
+    start_soc: PercentFull,
+    /// no stop or the destination may be reached below this
+    min_arrival_soc: PercentFull,
+    stops: Vec<ChargerStop>,
+    /// time lost pulling in and plugging in at every stop that gets used
+    stop_overhead: Duration,
+    average_mph: f64,
+}
+
+impl Default for Trip {
+    fn default() -> Self {
+        Self {
+            distance: 500.0,
+            start_soc: PercentFull::new(90.0),
+            min_arrival_soc: PercentFull::new(10.0),
+            stops: vec![
+                ChargerStop {
+                    distance: 120.0,
+                    max_power: Power::from_kw(150.0),
+                },
+                ChargerStop {
+                    distance: 210.0,
+                    max_power: Power::from_kw(350.0),
+                },
+                ChargerStop {
+                    distance: 300.0,
+                    max_power: Power::from_kw(50.0),
+                },
+                ChargerStop {
+                    distance: 390.0,
+                    max_power: Power::from_kw(250.0),
+                },
+            ],
+            stop_overhead: Duration::from_secs(5 * 60),
+            average_mph: 65.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct PlannedStop {
+    stop: ChargerStop,
+    arrive_soc: PercentFull,
+    depart_soc: PercentFull,
+    charge_time: Duration,
+}
+
+#[derive(Clone, PartialEq)]
+struct TripPlan {
+    stops: Vec<PlannedStop>,
+    arrive_soc: PercentFull,
+    driving_time: Duration,
+    /// charging plus the overhead of each stop
+    stopped_time: Duration,
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+enum TripPlanError {
+    #[error("the vehicle needs a range to plan a trip")]
+    NoRange,
+    #[error("no combination of stops reaches the destination without dropping below {0}")]
+    Unreachable(PercentFull),
+}
+
+/// How the planner got to a stop with a given state of charge
+#[derive(Clone, Copy)]
+struct Arrival {
+    /// time spent at stops so far
+    stopped: Duration,
+    /// arrival and departure levels at the previous stop, `None` when coming from the start
+    previous: Option<(usize, usize)>,
+}
+
+/// Picks which stops to charge at and how far, minimizing the time spent stopped.
+/// Driving time doesn't depend on the plan since every stop is on the route.
+fn plan_trip(spec: &VehicleSpec, trip: &Trip) -> Result<TripPlan, TripPlanError> {
+    if spec.epa_miles <= 0.0 {
+        return Err(TripPlanError::NoRange);
+    }
+    let stops = trip
+        .stops
+        .iter()
+        .copied()
+        .filter(|stop| stop.distance > 0.0 && stop.distance < trip.distance)
+        .sorted_by(|a, b| a.distance.total_cmp(&b.distance))
+        .collect::<Vec<_>>();
+    let used = |from: f64, to: f64| ((to - from) / spec.epa_miles * 10000.0).ceil() as i32;
+    // arrivals are rounded down onto the grid, so the plan stays on the safe side
+    let arrival_level = |soc: i32| {
+        (soc >= trip.min_arrival_soc.0 as i32).then_some((soc / SOC_STEP as i32) as usize)
+    };
+    let next_distance = |index: usize| {
+        stops
+            .get(index)
+            .map(|stop| stop.distance)
+            .unwrap_or(trip.distance)
+    };
+    // arrivals[i][level] is the quickest way to reach stop i, the destination being the last
+    let mut arrivals = vec![[None::<Arrival>; SOC_LEVELS]; stops.len() + 1];
+    if let Some(level) = arrival_level(trip.start_soc.0 as i32 - used(0.0, next_distance(0))) {
+        arrivals[0][level] = Some(Arrival {
+            stopped: Duration::ZERO,
+            previous: None,
+        });
+    }
+    for (index, stop) in stops.iter().enumerate() {
+        let curve = spec.charge_curve.limited(Some(stop.max_power), None);
+        // time to charge from empty to each level, differences give the time between levels
+        let mut from_empty = vec![Duration::ZERO; SOC_LEVELS];
+        for level in 1..SOC_LEVELS {
+            from_empty[level] = from_empty[level - 1]
+                + curve.charge_time(spec.battery_max, soc_level(level - 1), soc_level(level));
+        }
+        let used = used(stop.distance, next_distance(index + 1));
+        for arrive in 0..SOC_LEVELS {
+            let Some(arrival) = arrivals[index][arrive] else {
+                continue;
+            };
+            for depart in arrive..SOC_LEVELS {
+                let Some(next) = arrival_level(soc_level(depart).0 as i32 - used) else {
+                    continue;
+                };
+                let mut stopped = arrival.stopped;
+                if depart > arrive {
+                    stopped += from_empty[depart] - from_empty[arrive] + trip.stop_overhead;
+                }
+                let best = &mut arrivals[index + 1][next];
+                if best.is_none_or(|best| stopped < best.stopped) {
+                    *best = Some(Arrival {
+                        stopped,
+                        previous: Some((arrive, depart)),
+                    });
+                }
+            }
+        }
+    }
+    // prefer arriving with more charge when the time is the same
+    let (arrive_level, destination) = arrivals[stops.len()]
+        .iter()
+        .enumerate()
+        .rev()
+        .filter_map(|(level, arrival)| arrival.map(|arrival| (level, arrival)))
+        .min_by_key(|(_, arrival)| arrival.stopped)
+        .ok_or(TripPlanError::Unreachable(trip.min_arrival_soc))?;
+    let mut planned = vec![];
+    let mut current = destination;
+    for (index, stop) in stops.iter().enumerate().rev() {
+        let Some((arrive, depart)) = current.previous else {
+            break;
+        };
+        if depart > arrive {
+            let curve = spec.charge_curve.limited(Some(stop.max_power), None);
+            planned.push(PlannedStop {
+                stop: *stop,
+                arrive_soc: soc_level(arrive),
+                depart_soc: soc_level(depart),
+                charge_time: curve.charge_time(
+                    spec.battery_max,
+                    soc_level(arrive),
+                    soc_level(depart),
+                ),
+            });
+        }
+        current = arrivals[index][arrive].expect("previous arrival was reachable");
+    }
+    planned.reverse();
+    Ok(TripPlan {
+        stops: planned,
+        arrive_soc: soc_level(arrive_level),
+        driving_time: Duration::try_from_secs_f64(trip.distance / trip.average_mph * 60.0 * 60.0)
+            .unwrap_or_default(),
+        stopped_time: destination.stopped,
+    })
+}
+
+/// (minutes, battery SOC%) points across the whole trip
+fn trip_timeline(spec: &VehicleSpec, trip: &Trip, plan: &TripPlan) -> Vec<(f64, f64)> {
+    let minutes_driving = |miles: f64| miles / trip.average_mph * 60.0;
+    let mut elapsed = 0.0;
+    let mut position = 0.0;
+    let mut points = vec![(0.0, trip.start_soc.as_float())];
+    for planned in &plan.stops {
+        elapsed += minutes_driving(planned.stop.distance - position);
+        position = planned.stop.distance;
+        points.push((elapsed, planned.arrive_soc.as_float()));
+        elapsed += trip.stop_overhead.as_secs_f64() / 60.0;
+        points.push((elapsed, planned.arrive_soc.as_float()));
+        let session = spec
+            .charge_curve
+            .limited(Some(planned.stop.max_power), None)
+            .charge_session(spec.battery_max, planned.arrive_soc, planned.depart_soc);
+        points.extend(session.iter().map(|point| {
+            (
+                elapsed + point.elapsed.as_secs_f64() / 60.0,
+                point.state_of_charge.as_float(),
+            )
+        }));
+        elapsed += planned.charge_time.as_secs_f64() / 60.0;
+    }
+    elapsed += minutes_driving(trip.distance - position);
+    points.push((elapsed, plan.arrive_soc.as_float()));
+    points
+}
+
+fn format_duration(duration: Duration) -> String {
+    let minutes = (duration.as_secs_f64() / 60.0).round() as u64;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+/// Minutes between time axis ticks, whichever keeps about five on the chart
+const TRIP_TIME_STEPS: &[f64] = &[15.0, 30.0, 60.0, 120.0, 180.0, 240.0, 360.0, 720.0];
+
+fn trip_chart(spec: &VehicleSpec, trip: &Trip, plan: &TripPlan) -> LineChartData {
+    let prefs = preferences();
+    let mut chart = LineChartData::new(
+        "Trip Timeline",
+        ChartAxis::new("Time into the trip")
+            .steps(TRIP_TIME_STEPS)
+            .label(|minutes| format_duration(Duration::from_secs_f64(minutes.max(0.0) * 60.0))),
+        ChartAxis::new("Battery SOC%")
+            .range(0.0, 100.0)
+            .label(move |soc| format!("{}%", prefs.number(soc, 0))),
+    )
+    .description(format!(
+        "{} leaves at {}%, stops {} time(s) for {} in total and arrives at {}%.",
+        spec.name,
+        prefs.number(trip.start_soc.as_float(), 0),
+        plan.stops.len(),
+        format_duration(plan.stopped_time),
+        prefs.number(plan.arrive_soc.as_float(), 0)
+    ))
+    .series(ChartSeries::line(spec.name, trip_timeline(spec, trip, plan)).filled());
+    // a line where each stop starts, labelled with how far along it is
+    let mut elapsed = Duration::ZERO;
+    let mut position = 0.0;
+    for planned in &plan.stops {
+        elapsed += Duration::try_from_secs_f64(
+            (planned.stop.distance - position) / trip.average_mph * 60.0 * 60.0,
+        )
+        .unwrap_or_default();
+        position = planned.stop.distance;
+        chart = chart.marker(ChartMarker::VerticalLine {
+            x: elapsed.as_secs_f64() / 60.0,
+            label: prefs.distance(planned.stop.distance),
+        });
+        elapsed += trip.stop_overhead + planned.charge_time;
+    }
+    chart
+}
+
+#[component]
+fn TripChart(
+    #[prop(into)] spec: Signal<Option<&'static VehicleSpec>>,
+    #[prop(into)] trip: Signal<Trip>,
+    #[prop(into)] plan: Signal<Option<TripPlan>>,
+) -> impl IntoView {
+    let chart = Signal::derive(move || match (spec(), plan()) {
+        (Some(spec), Some(plan)) => trip.with(|trip| trip_chart(spec, trip, &plan)),
+        _ => LineChartData::new(
+            "Trip Timeline",
+            ChartAxis::new("Time into the trip"),
+            ChartAxis::new("Battery SOC%"),
+        ),
+    });
+    view! {
+        <div class:collapse=move || plan.with(|plan| plan.is_none())>
+            <LineChart chart id="tripplan" />
+        </div>
+    }
+}
+
+#[component]
+fn StopEditor(trip: RwSignal<Trip>) -> impl IntoView {
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors";
+    let btn = "rounded-lg bg-white dark:bg-slate-700 hover:bg-amber-50 dark:hover:bg-slate-600 p-1 border border-slate-300 dark:border-slate-500 transition-colors";
+    view! {
+        <div class="flex flex-col gap-1">
+            <h4 class="text-xl">"Chargers along the route"</h4>
+            <div class="grid grid-cols-[auto_auto_auto] gap-1 items-center w-fit">
+                <span>{move || format!("Distance ({})", preferences().distance_unit())}</span>
+                <span>"Max kW"</span>
+                <span></span>
+                {move || (0..trip.with(|trip| trip.stops.len())).map(|index| view! {
+                    <input class=input prop:value=move || trip.with(|trip| trip.stops.get(index).map(|stop| preferences().distance_value(stop.distance).round()).unwrap_or_default())
+                        on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                            trip.update(|trip| if let Some(stop) = trip.stops.get_mut(index) { stop.distance = preferences().miles_from(value.max(0.0)) });
+                        } } />
+                    <input class=input prop:value=move || trip.with(|trip| trip.stops.get(index).map(|stop| stop.max_power.as_kw()).unwrap_or_default())
+                        on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                            trip.update(|trip| if let Some(stop) = trip.stops.get_mut(index) { stop.max_power = Power::from_kw(value.max(1.0)) });
+                        } } />
+                    <button class=btn on:click=move |_| trip.update(|trip| { trip.stops.remove(index); })>"remove"</button>
+                }).collect_view()}
+            </div>
+            <button class=btn on:click=move |_| trip.update(|trip| {
+                let distance = trip.stops.iter().map(|stop| stop.distance).fold(0.0, f64::max) + 50.0;
+                trip.stops.push(ChargerStop { distance: distance.min(trip.distance), max_power: Power::from_kw(150.0) });
+            })>"add charger +"</button>
+        </div>
+    }
+}
+
+/// Plans the charging stops of a road trip for a single vehicle
+#[component]
+pub fn TripPlanner() -> impl IntoView {
+    provide_preferences();
+    let (vehicle_spec, set_vehicle_spec) = signal::<Option<&'static VehicleSpec>>(None);
+    let trip = RwSignal::new(Trip::default());
+    let plan =
+        Memo::new(move |_| vehicle_spec().map(|spec| trip.with(|trip| plan_trip(spec, trip))));
+    let ok_plan = Signal::derive(move || plan.with(|plan| plan.clone().and_then(Result::ok)));
+    let input = "dark:bg-slate-700 bg-white hover:bg-amber-50 dark:hover:bg-slate-600 border border-slate-300 dark:border-slate-500 rounded-lg p-1 w-24 transition-colors";
+    let number_input = move |get: fn(&Trip) -> f64, set: fn(&mut Trip, f64)| {
+        view! {
+            <input class=input prop:value=move || trip.with(get)
+                on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                    trip.update(|trip| set(trip, value));
+                } } />
+        }
+    };
+    // distances and speeds are kept in miles, shown and typed in the preferred unit
+    let distance_input = move |get: fn(&Trip) -> f64, set: fn(&mut Trip, f64)| {
+        view! {
+            <input class=input prop:value=move || preferences().distance_value(trip.with(get)).round()
+                on:change=move |e| { if let Ok(value) = event_target_value(&e).parse::<f64>() {
+                    trip.update(|trip| set(trip, preferences().miles_from(value)));
+                } } />
+        }
+    };
+    view! {
+        <Title text="EV Trip Planner" />
+        <div class="flex flex-col gap-2">
+            <div class="flex flex-col gap-1">
+                <h2 class="text-3xl font-bold gradient-text">"EV Trip Planner"</h2>
+                <span>"Picks which chargers to stop at and how long to charge so a road trip spends the least time stopped."</span>
+            </div>
+            <PreferencesEditor />
+            <VehicleDropdown current_vehicle=vehicle_spec set_vehicle=set_vehicle_spec />
+            <div class="flex flex-col md:flex-row gap-4">
+                <div class="grid grid-cols-[auto_auto] gap-1 items-center h-fit">
+                    <span>{move || format!("Trip {}", preferences().distance_unit())}</span>
+                    {distance_input(|trip| trip.distance, |trip, value| trip.distance = value.max(0.0))}
+                    <span>"Starting battery%"</span>
+                    {number_input(|trip| trip.start_soc.as_float(), |trip, value| trip.start_soc = PercentFull::new(value.clamp(0.0, 100.0)))}
+                    <span>"Arrive with at least battery%"</span>
+                    {number_input(|trip| trip.min_arrival_soc.as_float(), |trip, value| trip.min_arrival_soc = PercentFull::new(value.clamp(0.0, 100.0)))}
+                    <span>{move || format!("Average {}", preferences().speed_unit())}</span>
+                    {distance_input(|trip| trip.average_mph, |trip, value| trip.average_mph = value.max(1.0))}
+                    <span>"Minutes lost per stop"</span>
+                    {number_input(|trip| trip.stop_overhead.as_secs_f64() / 60.0, |trip, value| trip.stop_overhead = Duration::try_from_secs_f64(value * 60.0).unwrap_or_default())}
+                </div>
+                <StopEditor trip />
+            </div>
+            {move || match plan() {
+                None => view! { <span>"Pick a vehicle to plan the trip."</span> }.into_any(),
+                Some(Err(e)) => view! { <span role="alert" class="text-red-600">{e.to_string()}</span> }.into_any(),
+                Some(Ok(plan)) => {
+                    let spec = vehicle_spec.get_untracked().cloned().unwrap_or_default();
+                    view! {
+                        <div class="flex flex-col gap-1">
+                            <span class="text-xl">"Total "{format_duration(plan.driving_time + plan.stopped_time)}", "{format_duration(plan.stopped_time)}" stopped, arriving at "{move || preferences().percent(plan.arrive_soc)}</span>
+                            <table class="w-fit">
+                                <thead>
+                                    <tr class="text-left"><th class="pr-4">{format!("Distance ({})", preferences().distance_unit())}</th><th class="pr-4">"Charger"</th><th class="pr-4">"Arrive"</th><th class="pr-4">"Charge to"</th><th class="pr-4">"Added"</th><th>"Charging"</th></tr>
+                                </thead>
+                                <tbody>
+                                    {plan.stops.iter().map(|planned| {
+                                        let added: Energy = (planned.depart_soc - planned.arrive_soc) * spec.battery_max;
+                                        let range = spec.epa_miles_for(added);
+                                        let (max_power, arrive_soc, depart_soc) = (planned.stop.max_power, planned.arrive_soc, planned.depart_soc);
+                                        view! {
+                                            <tr>
+                                                <td class="pr-4">{preferences().number(preferences().distance_value(planned.stop.distance), 0)}</td>
+                                                <td class="pr-4">{move || preferences().power(max_power)}</td>
+                                                <td class="pr-4">{move || preferences().percent(arrive_soc)}</td>
+                                                <td class="pr-4">{move || preferences().percent(depart_soc)}</td>
+                                                <td class="pr-4">{move || format!("{} ({})", preferences().energy(added), preferences().distance(range))}</td>
+                                                <td>{format_duration(planned.charge_time)}</td>
+                                            </tr>
+                                        }
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                        </div>
+                    }.into_any()
+                }
+            }}
+            <TripChart spec=vehicle_spec trip plan=ok_plan />
+            <span>"Uses the EPA range and assumes every charger can meet the vehicle's curve up to its max power."</span>
+        </div>
+    }
+    .into_any()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::toys::ev_charge_sim::{ChargeCurve, CurvePoint};
+
+    /// 100 kWh and 300 miles of range, charging at 100 kW the whole way
+    fn spec() -> VehicleSpec {
+        VehicleSpec {
+            name: "Test vehicle",
+            battery_max: Energy::from_kwh(100.0),
+            charge_curve: ChargeCurve {
+                data_points: Cow::Owned(vec![
+                    CurvePoint::new(0.0, 100.0),
+                    CurvePoint::new(100.0, 100.0),
+                ]),
+            },
+            epa_miles: 300.0,
+            ..Default::default()
+        }
+    }
+
+    fn trip(distance: f64, stops: Vec<ChargerStop>) -> Trip {
+        Trip {
+            distance,
+            stops,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_stop_needed() {
+        let plan = plan_trip(&spec(), &trip(100.0, Trip::default().stops)).unwrap();
+        assert!(plan.stops.is_empty());
+        assert_eq!(plan.stopped_time, Duration::ZERO);
+        // 90% less a third of the battery, rounded down onto the planner's grid
+        assert!((plan.arrive_soc.as_float() - 56.5).abs() < 0.6);
+    }
+
+    #[test]
+    fn single_forced_stop() {
+        let stop = ChargerStop {
+            distance: 200.0,
+            max_power: Power::from_kw(150.0),
+        };
+        let plan = plan_trip(&spec(), &trip(400.0, vec![stop])).unwrap();
+        assert_eq!(plan.stops.len(), 1);
+        let planned = plan.stops[0];
+        assert!(planned.stop == stop);
+        // just enough for the last 200 miles and the 10% reserve
+        assert!(planned.depart_soc.as_float() >= 200.0 / 3.0 + 10.0);
+        assert!(planned.depart_soc.as_float() < 200.0 / 3.0 + 11.0);
+        assert!(plan.arrive_soc.as_float() >= 10.0);
+        // the curve is capped at 100 kW, so each percent takes 36 seconds
+        let percent = planned.depart_soc.as_float() - planned.arrive_soc.as_float();
+        assert!((planned.charge_time.as_secs_f64() - percent * 36.0).abs() < 1.0);
+        assert_eq!(
+            plan.stopped_time,
+            planned.charge_time + Trip::default().stop_overhead
+        );
+    }
+
+    #[test]
+    fn unreachable_destination() {
+        assert_eq!(
+            plan_trip(&spec(), &trip(400.0, vec![])).err(),
+            Some(TripPlanError::Unreachable(PercentFull::new(10.0)))
+        );
+    }
+}
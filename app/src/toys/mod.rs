@@ -1,7 +1,12 @@
 use leptos::prelude::*;
 use leptos_router::components::A;
 mod ev_charge_sim;
-pub use ev_charge_sim::VehicleSim;
+#[cfg(feature = "ssr")]
+pub use ev_charge_sim::scenario_preview_png;
+pub use ev_charge_sim::{
+    ChargeTimeCalculator, DepotScheduler, SharedScenario, TripPlanner, VehicleCatalog, VehicleSim,
+    PREVIEW_PATH,
+};
 
 #[component]
 pub fn ToyPage() -> impl IntoView {
@@ -9,6 +14,10 @@ pub fn ToyPage() -> impl IntoView {
         <div class="flex flex-col py-8">
             <h2 class="text-3xl font-bold mb-4 gradient-text">"Toys"</h2>
             <A href="ev-charger-sim" attr:class="text-lg text-amber-600 dark:text-teal-400 hover:underline font-medium">"EV charger sim"</A>
+            <A href="charge-time" attr:class="text-lg text-amber-600 dark:text-teal-400 hover:underline font-medium">"EV charge time calculator"</A>
+            <A href="trip-planner" attr:class="text-lg text-amber-600 dark:text-teal-400 hover:underline font-medium">"EV trip planner"</A>
+            <A href="depot-scheduler" attr:class="text-lg text-amber-600 dark:text-teal-400 hover:underline font-medium">"EV fleet depot scheduler"</A>
+            <A href="vehicles" attr:class="text-lg text-amber-600 dark:text-teal-400 hover:underline font-medium">"EV catalog"</A>
         </div>
     }
 }
@@ -1,7 +1,6 @@
 #![recursion_limit = "256"]
 
 use app::*;
-use leptos::prelude::*;
 use wasm_bindgen::prelude::wasm_bindgen;
 
 #[wasm_bindgen]
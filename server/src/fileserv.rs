@@ -11,7 +11,7 @@ use tower_http::services::ServeDir;
 pub async fn file_and_error_handler(
     uri: Uri,
     State(options): State<LeptosOptions>,
-    req: Request<Body>,
+    _req: Request<Body>,
 ) -> Response {
     let root = options.site_root.clone();
     let res = get_static_file(uri.clone(), &root).await.unwrap();
@@ -1,14 +1,15 @@
 #![recursion_limit = "256"]
 
 use app::*;
-use axum::Router;
+use axum::{routing::get, Router};
 use fileserv::file_and_error_handler;
 use leptos::prelude::*;
 use leptos_axum::{generate_route_list, LeptosRoutes};
-use leptos_meta::*;
 use log::info;
+use preview::scenario_preview;
 
 pub mod fileserv;
+pub mod preview;
 
 fn shell(options: LeptosOptions) -> impl IntoView {
     use leptos::prelude::*;
@@ -47,6 +48,7 @@ async fn main() {
     let routes = generate_route_list(App);
     // build our application with a route
     let app = Router::new()
+        .route(PREVIEW_PATH, get(scenario_preview))
         .leptos_routes(&leptos_options, routes, {
             let leptos_options = leptos_options.clone();
             move || shell(leptos_options.clone())
@@ -54,7 +56,7 @@ async fn main() {
         .fallback(file_and_error_handler)
         .with_state(leptos_options);
 
-    info!("listening on http://{}", &addr);
+    info!("listening on http://{}", addr);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     axum::serve(listener, app.into_make_service())
         .await
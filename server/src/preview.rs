@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use axum::{
+    extract::RawQuery,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// How long a preview may take before the request gives up on it. The simulation stops itself
+/// well before this, the timeout covers drawing and encoding the image on a busy server.
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves the link preview image for a simulator scenario, read from the same query string
+pub async fn scenario_preview(RawQuery(query): RawQuery) -> Response {
+    let Some(query) = query else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    // the simulation is CPU bound, keep it off the async workers
+    let preview = tokio::task::spawn_blocking(move || app::scenario_preview_png(&query));
+    match tokio::time::timeout(PREVIEW_TIMEOUT, preview).await {
+        Ok(Ok(Some(png))) => (
+            [
+                (header::CONTENT_TYPE, "image/png"),
+                // the query string is the whole scenario, so the image never changes
+                (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+            ],
+            png,
+        )
+            .into_response(),
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}